@@ -0,0 +1,34 @@
+use tauri::State;
+
+use crate::models::Completion;
+use crate::state::ShellManager;
+
+/// Complete the word before the cursor: a command, `$VARIABLE`, path relative to the
+/// session's directory, or a command-specific argument such as a git branch.
+///
+/// # Arguments
+/// * `session_id` - Session whose directory and environment are used
+/// * `input` - The command line being typed
+/// * `cursor` - Byte offset of the cursor in `input`
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(Completion)` - Candidates replacing `input[start..end]`
+/// * `Err(String)` - Error message if the session does not exist or `cursor` is invalid
+#[tauri::command]
+pub async fn complete(
+    session_id: String,
+    input: String,
+    cursor: usize,
+    state: State<'_, ShellManager>,
+) -> Result<Completion, String> {
+    let session = state.get_session(&session_id)?;
+    let cwd = session.get_cwd().await;
+    let env = session.shell_state.get_env().await;
+    let completer = state.completer();
+
+    // Directory listings and `git` may block
+    tokio::task::spawn_blocking(move || completer.complete(&input, cursor, &cwd, &env))
+        .await
+        .map_err(|e| format!("Completion failed: {e}"))?
+}
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::models::ConfigStatus;
+use crate::state::{ConfigStore, ShellManager};
+
+/// How often the config file is checked for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reload the config file whenever it changes, applying the new settings to the
+/// `ShellManager` and emitting `config-changed` (also when the file is invalid, so the
+/// problem can be shown).
+pub fn watch_config(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CONFIG_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(status) = app.state::<ConfigStore>().reload_if_changed() else {
+                continue;
+            };
+            if status.error.is_none() {
+                tracing::info!("Reloaded config from {:?}", status.path);
+                app.state::<ShellManager>()
+                    .apply_config(&status.config)
+                    .await;
+            }
+            if let Err(e) = app.emit("config-changed", &status) {
+                tracing::error!("Failed to emit config-changed event: {}", e);
+            }
+        }
+    });
+}
+
+/// Get the user configuration in effect.
+///
+/// # Arguments
+/// * `store` - Tauri managed `ConfigStore` state
+///
+/// # Returns
+/// * `Ok(ConfigStatus)` - The settings, the config file path and its problem, if any
+#[tauri::command]
+pub async fn get_config(store: State<'_, ConfigStore>) -> Result<ConfigStatus, String> {
+    Ok(store.status())
+}
@@ -0,0 +1,50 @@
+use tauri::State;
+
+use crate::models::{CommandHistoryEntry, ExitStatusFilter, HistoryQuery};
+use crate::state::ShellManager;
+
+/// Get the commands entered in any session, for Up/Down recall.
+///
+/// # Arguments
+/// * `limit` - Return only the most recent `limit` commands
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(Vec<CommandHistoryEntry>)` - Commands oldest first, each appearing once
+#[tauri::command]
+pub async fn get_command_history(
+    limit: Option<usize>,
+    state: State<'_, ShellManager>,
+) -> Result<Vec<CommandHistoryEntry>, String> {
+    Ok(state.command_history().recent(limit))
+}
+
+/// Search the commands entered in any session.
+///
+/// # Arguments
+/// * `query` - Text to find, ignoring case: contained in the command, or its characters
+///   appearing in order (`gco` finds `git checkout`); empty matches every command
+/// * `cwd` - Only commands last run in this directory
+/// * `status` - Only commands whose last run succeeded or failed
+/// * `limit` - Return at most `limit` matches
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(Vec<CommandHistoryEntry>)` - Commands containing `query` first, then fuzzy
+///   matches, each most recent first
+#[tauri::command]
+pub async fn search_command_history(
+    query: String,
+    cwd: Option<String>,
+    status: Option<ExitStatusFilter>,
+    limit: Option<usize>,
+    state: State<'_, ShellManager>,
+) -> Result<Vec<CommandHistoryEntry>, String> {
+    tracing::debug!("Searching command history for {:?}", query);
+    Ok(state.command_history().search(&HistoryQuery {
+        text: query,
+        cwd,
+        status,
+        limit,
+    }))
+}
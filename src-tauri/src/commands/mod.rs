@@ -1 +1,5 @@
+pub mod completion;
+pub mod config;
+pub mod history;
+pub mod session;
 pub mod shell;
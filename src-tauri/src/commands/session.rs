@@ -0,0 +1,130 @@
+use tauri::State;
+
+use crate::models::{JobStatus, SessionInfo, ShellConfig};
+use crate::process::shell::{available_shells, Shell};
+use crate::process::signals::{self, JobSignal};
+use crate::state::{Session, ShellManager};
+
+/// Hang up the session's running command and its jobs, as closing a terminal window
+/// would.
+async fn hang_up(session: &Session) -> Result<(), String> {
+    let shell_state = &session.shell_state;
+    let result = match session.get_running_pid().await {
+        Some(pid) => {
+            tracing::info!("Hanging up PID {} of closed session {}", pid, session.id);
+            signals::send(pid, shell_state.get_pgid().await, JobSignal::Hangup).await
+        }
+        None => Ok(()),
+    };
+
+    for job in shell_state.jobs.all().await {
+        let Some(pid) = job.pid() else {
+            continue;
+        };
+        if job.is_foreground() {
+            continue;
+        }
+        tracing::info!("Hanging up job {} of closed session {}", job.id, session.id);
+        let mut sent = signals::send(pid, Some(pid), JobSignal::Hangup).await;
+        // A stopped job only acts on the hangup once continued
+        if sent.is_ok() && job.status() == JobStatus::Stopped {
+            sent = signals::send(pid, Some(pid), JobSignal::Continue).await;
+        }
+        if let Err(e) = sent {
+            tracing::warn!("Failed to hang up job {}: {}", job.id, e);
+        }
+    }
+    result
+}
+
+/// Create a new terminal session.
+///
+/// # Arguments
+/// * `cwd` - Optional initial working directory (defaults to the application's)
+/// * `shell` - Optional shell for the session's commands (defaults to the configured
+///   shell, or `$SHELL`)
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(SessionInfo)` - The new session
+/// * `Err(String)` - Error message if `cwd` is not a directory or the shell is not
+///   installed
+#[tauri::command]
+pub async fn create_session(
+    cwd: Option<String>,
+    shell: Option<ShellConfig>,
+    state: State<'_, ShellManager>,
+) -> Result<SessionInfo, String> {
+    if let Some(path) = &cwd {
+        if !std::path::Path::new(path).is_dir() {
+            return Err(format!("Directory does not exist: {path}"));
+        }
+    }
+    if let Some(config) = &shell {
+        let resolved = Shell::resolve(config);
+        if resolved.locate().is_none() {
+            return Err(format!("Shell not found: {}", resolved.program));
+        }
+    }
+
+    let session = state.create_session(cwd, shell);
+    tracing::info!(
+        "Created session {} running {}",
+        session.id,
+        session.shell.program
+    );
+    Ok(session.info().await)
+}
+
+/// Close a session, hanging up any command or job still running in it.
+///
+/// # Arguments
+/// * `session_id` - Session to close
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Err(String)` - Error message if the session does not exist
+#[tauri::command]
+pub async fn close_session(
+    session_id: String,
+    state: State<'_, ShellManager>,
+) -> Result<(), String> {
+    let session = state
+        .close_session(&session_id)
+        .ok_or_else(|| format!("Unknown session: {session_id}"))?;
+    tracing::info!("Closed session {}", session.id);
+
+    if let Err(e) = hang_up(&session).await {
+        tracing::warn!(
+            "Failed to stop command of closed session {}: {}",
+            session.id,
+            e
+        );
+    }
+    Ok(())
+}
+
+/// List open sessions in creation order.
+///
+/// # Arguments
+/// * `state` - Tauri managed `ShellManager` state
+#[tauri::command]
+pub async fn list_sessions(state: State<'_, ShellManager>) -> Result<Vec<SessionInfo>, String> {
+    let mut infos = Vec::new();
+    for session in state.sessions() {
+        infos.push(session.info().await);
+    }
+    Ok(infos)
+}
+
+/// List the shells installed on this system (from `/etc/shells` on Unix), for choosing
+/// the shell of a new session.
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - Shell paths in the order listed
+#[tauri::command]
+pub async fn get_available_shells() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(available_shells)
+        .await
+        .map_err(|e| format!("Failed to list shells: {e}"))
+}
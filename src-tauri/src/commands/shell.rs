@@ -1,25 +1,49 @@
+use std::collections::BTreeMap;
 use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use tauri::{AppHandle, Emitter, State};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::{Child, Command};
-
-use crate::models::{CommandResponse, NotificationLevel, OutputLine};
-use crate::state::{current_timestamp_ms, ShellManager};
-use strip_ansi_escapes::strip as strip_ansi_bytes;
-
-/// Remove ANSI escape sequences while preserving newlines, tabs, and carriage returns.
-fn sanitize_output(text: String) -> String {
-    // strip_ansi_escapes works on bytes; it preserves \r, \n, and \t.
-    // If stripping fails, fail closed and drop the text to avoid leaking escape sequences.
-    match strip_ansi_bytes(text.as_bytes()) {
-        Ok(clean_bytes) => String::from_utf8_lossy(&clean_bytes).into_owned(),
-        Err(e) => {
-            tracing::warn!("Failed to strip ANSI escapes: {}", e);
-            String::new()
-        }
-    }
-}
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Child;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::models::{
+    CommandFinished, CommandHistoryEntry, CommandResponse, CommandStarted, CwdChanged,
+    ExecutionMode, HistoryLine, JobInfo, JobStatus, LineUpdate, NotificationLevel, OutputBatch,
+    OutputLine, OutputStream, PtySize,
+};
+#[cfg(unix)]
+use crate::models::{PtyOutput, SessionScreen};
+use crate::process::ansi::AnsiParser;
+use crate::process::builtins::{self, Builtin};
+#[cfg(unix)]
+use crate::process::emulator::Emulator;
+use crate::process::lines::LineAssembler;
+#[cfg(unix)]
+use crate::process::persistent::{PersistentShell, RunOutcome, ShellOutput};
+#[cfg(unix)]
+use crate::process::pty::{self, PtyMaster};
+use crate::process::signals::{self, EscalationPolicy, JobSignal};
+#[cfg(unix)]
+use crate::state::shell_manager::SharedEmulator;
+use crate::state::{
+    current_timestamp_ms, CommandIo, Job, JobTag, OutputBatcher, OutputCounts, Queued, Session,
+    ShellManager,
+};
+
+/// How long an unterminated line (a prompt or progress bar) may go unshown, and how
+/// often it is updated while being rewritten.
+const PARTIAL_LINE_FLUSH: Duration = Duration::from_millis(100);
+
+/// How long to keep draining a PTY after its command exits. Background jobs that
+/// inherited the terminal would otherwise keep the reader alive indefinitely.
+#[cfg(unix)]
+const PTY_DRAIN_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Exit code reported for a command suspended with Ctrl+Z (128 + `SIGTSTP`), as shells do.
+const SUSPENDED_EXIT_CODE: i32 = 148;
 
 async fn kill_child_with_fallback(child: &mut Child) {
     match child.kill().await {
@@ -40,329 +64,1485 @@ async fn kill_child_with_fallback(child: &mut Child) {
     }
 }
 
-fn build_shell_command(command: &str) -> Command {
-    #[cfg(windows)]
-    {
-        let mut cmd = Command::new("cmd");
-        cmd.arg("/C").arg(command);
-        cmd
+/// Add a line to the session's history buffer and send it (plus any truncation warning)
+/// to the frontend with the session's other output.
+fn push_and_emit(session: &Session, app: &AppHandle, line: OutputLine) {
+    emit_line(session, app, line, None, false);
+}
+
+/// Record `line` and queue it for the frontend, replacing the partial line `open_line` if
+/// it is still in the history. Returns the line's sequence number.
+fn emit_line(
+    session: &Session,
+    app: &AppHandle,
+    line: OutputLine,
+    open_line: Option<u64>,
+    partial: bool,
+) -> u64 {
+    let replaced = open_line.is_some_and(|seq| session.history_buffer.replace(seq, line.clone()));
+    if !partial {
+        session.persist(&line);
     }
+    let seq = match open_line {
+        Some(seq) if replaced => seq,
+        _ => {
+            let (seq, warning) = session.history_buffer.push_with_seq(line.clone());
+            if let Some(warning) = warning {
+                queue_output(
+                    session,
+                    app,
+                    LineUpdate {
+                        seq: warning.seq,
+                        line: warning.line,
+                        partial: false,
+                        replace: false,
+                    },
+                );
+            }
+            seq
+        }
+    };
 
-    #[cfg(not(windows))]
-    {
-        let mut cmd = Command::new("sh");
-        cmd.arg("-c").arg(command);
-        cmd
+    queue_output(
+        session,
+        app,
+        LineUpdate {
+            seq,
+            line,
+            partial,
+            replace: replaced,
+        },
+    );
+    seq
+}
+
+/// Queue an output update for the session's next `output-batch` event, sent once
+/// `OutputBatcher::WINDOW` has passed or the batch is full.
+fn queue_output(session: &Session, app: &AppHandle, update: LineUpdate) {
+    match session.output.push(update) {
+        Queued::Started => {
+            let session = session.clone();
+            let app = app.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(OutputBatcher::WINDOW).await;
+                flush_output(&session, &app);
+            });
+        }
+        Queued::Joined => {}
+        Queued::Full => flush_output(session, app),
+    }
+}
+
+/// Send the session's queued output as an `output-batch` event.
+fn flush_output(session: &Session, app: &AppHandle) {
+    session.output.flush(|lines| {
+        let batch = OutputBatch {
+            session_id: session.id.clone(),
+            lines,
+        };
+        if let Err(e) = app.emit("output-batch", &batch) {
+            tracing::error!("Failed to emit output-batch event: {}", e);
+        }
+    });
+}
+
+/// Show a transient notification in the frontend.
+fn emit_notification(app: &AppHandle, level: NotificationLevel, message: String) {
+    let notification = OutputLine::Notification {
+        message,
+        level,
+        timestamp: current_timestamp_ms(),
+    };
+    if let Err(e) = app.emit("shell-notification", &notification) {
+        tracing::error!("Failed to emit notification: {}", e);
+    }
+}
+
+/// Notify the frontend that the session's working directory changed.
+fn emit_cwd_changed(session: &Session, app: &AppHandle, cwd: &str) {
+    let event = CwdChanged {
+        session_id: session.id.clone(),
+        cwd: cwd.to_string(),
+    };
+    if let Err(e) = app.emit("cwd-changed", &event) {
+        tracing::error!("Failed to emit cwd-changed event: {}", e);
+    }
+}
+
+/// Emit a command lifecycle event, `command-started` or `command-finished`, after the
+/// session's queued output.
+fn emit_lifecycle(
+    session: &Session,
+    app: &AppHandle,
+    event: &str,
+    payload: &impl serde::Serialize,
+) {
+    flush_output(session, app);
+    if let Err(e) = app.emit(event, payload) {
+        tracing::error!("Failed to emit {} event: {}", event, e);
+    }
+}
+
+/// Background tasks forwarding a running command's output.
+enum OutputReaders {
+    /// Separate stdout/stderr pipe readers
+    Piped {
+        stdout: JoinHandle<()>,
+        stderr: JoinHandle<()>,
+    },
+    /// Single reader on the PTY master (stdout and stderr share the terminal)
+    #[cfg(unix)]
+    Pty(JoinHandle<()>),
+}
+
+impl OutputReaders {
+    /// Wait for the readers to drain after the process has exited.
+    async fn join(self) {
+        match self {
+            Self::Piped { stdout, stderr } => {
+                if let Err(join_err) = stdout.await {
+                    tracing::warn!("stdout reader task join failed: {}", join_err);
+                }
+                if let Err(join_err) = stderr.await {
+                    tracing::warn!("stderr reader task join failed: {}", join_err);
+                }
+            }
+            #[cfg(unix)]
+            Self::Pty(mut reader) => {
+                if tokio::time::timeout(PTY_DRAIN_TIMEOUT, &mut reader)
+                    .await
+                    .is_err()
+                {
+                    tracing::debug!("PTY still held open after command exit; stopping reader");
+                    reader.abort();
+                }
+            }
+        }
+    }
+}
+
+/// A command that was just spawned, before anything waits for it.
+struct Spawned {
+    child: Child,
+    /// Input handles, attached to the session while the command is in the foreground
+    io: CommandIo,
+    readers: OutputReaders,
+}
+
+/// Forwards one output stream to the history buffer and frontend line by line.
+///
+/// An unterminated line (a prompt, a progress bar) is shown once it has waited
+/// `PARTIAL_LINE_FLUSH`, and at most that often while it keeps being rewritten; each
+/// update replaces the previous one until the line's newline arrives. Once the command
+/// runs as a job, its lines are tagged with the job number.
+struct LineForwarder {
+    stream: OutputStream,
+    tag: JobTag,
+    counts: Arc<OutputCounts>,
+    lines: LineAssembler,
+    /// Styling state at the start of the unterminated line
+    parser: AnsiParser,
+    /// Sequence number of the partial line shown for the unterminated line
+    open_line: Option<u64>,
+    last_flush: Instant,
+    session: Session,
+    app: AppHandle,
+}
+
+impl LineForwarder {
+    fn new(stream: OutputStream, job: &Job, session: Session, app: AppHandle) -> Self {
+        Self {
+            tag: job.tag.clone(),
+            ..Self::untagged(stream, &job.output, session, app)
+        }
+    }
+
+    /// Forwarder for output that never belongs to a job (the persistent shell's),
+    /// counted in `counts`
+    fn untagged(
+        stream: OutputStream,
+        counts: &Arc<OutputCounts>,
+        session: Session,
+        app: AppHandle,
+    ) -> Self {
+        Self {
+            stream,
+            tag: JobTag::default(),
+            counts: Arc::clone(counts),
+            lines: LineAssembler::new(),
+            parser: AnsiParser::new(),
+            open_line: None,
+            last_flush: Instant::now(),
+            session,
+            app,
+        }
     }
+
+    /// Forward the lines completed by `chunk`, and the unterminated line if it is due
+    fn feed(&mut self, chunk: &[u8]) {
+        self.counts.add_bytes(self.stream, chunk.len());
+        for line in self.lines.push(chunk) {
+            self.complete(&line);
+        }
+        if self.last_flush.elapsed() >= PARTIAL_LINE_FLUSH {
+            self.flush_partial();
+        }
+    }
+
+    /// Check whether the unterminated line has changes not shown yet
+    const fn has_pending(&self) -> bool {
+        self.lines.has_partial()
+    }
+
+    /// Show the unterminated line if it changed
+    fn flush_partial(&mut self) {
+        self.last_flush = Instant::now();
+        if let Some(text) = self.lines.take_partial() {
+            // Parse a copy: the styling state must stay at the start of the line
+            let output_line =
+                self.parser
+                    .clone()
+                    .output_line(self.stream, &text, current_timestamp_ms());
+            let output_line = self.tagged(output_line);
+            let seq = emit_line(&self.session, &self.app, output_line, self.open_line, true);
+            self.open_line = Some(seq);
+        }
+    }
+
+    /// Forward the unterminated line at the end of the stream as a complete line
+    fn finish(&mut self) {
+        if let Some(text) = self.lines.finish() {
+            self.complete(&text);
+        }
+    }
+
+    /// Forward a terminated line, replacing its partial line if one was shown
+    fn complete(&mut self, text: &str) {
+        self.counts.add_line(self.stream);
+        let output_line = self
+            .parser
+            .output_line(self.stream, text, current_timestamp_ms());
+        let output_line = self.tagged(output_line);
+        emit_line(
+            &self.session,
+            &self.app,
+            output_line,
+            self.open_line.take(),
+            false,
+        );
+    }
+
+    /// Tag `line` with the job number if the command runs as a job
+    fn tagged(&self, line: OutputLine) -> OutputLine {
+        match self.tag.get() {
+            Some(job_id) => line.into_job_output(job_id),
+            None => line,
+        }
+    }
+
+    /// Forget `chunk` and the unterminated line (their output went to the screen emulator
+    /// instead)
+    #[cfg(unix)]
+    fn discard(&mut self, chunk: &[u8]) {
+        self.counts.add_bytes(self.stream, chunk.len());
+        self.lines = LineAssembler::new();
+        self.open_line = None;
+    }
+}
+
+/// Spawn a task forwarding the output of `reader` (styled from its SGR sequences) to the
+/// history buffer and frontend.
+fn spawn_line_reader<R>(
+    mut reader: R,
+    stream: OutputStream,
+    job: &Job,
+    session: Session,
+    app: AppHandle,
+) -> JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let mut forwarder = LineForwarder::new(stream, job, session, app);
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 8192];
+
+        loop {
+            let read = if forwarder.has_pending() {
+                match tokio::time::timeout(PARTIAL_LINE_FLUSH, reader.read(&mut buf)).await {
+                    Ok(read) => read,
+                    Err(_elapsed) => {
+                        forwarder.flush_partial();
+                        continue;
+                    }
+                }
+            } else {
+                reader.read(&mut buf).await
+            };
+            match read {
+                Ok(0) => break,
+                Ok(n) => forwarder.feed(&buf[..n]),
+                Err(e) => {
+                    tracing::warn!("Output read failed: {}", e);
+                    break;
+                }
+            }
+        }
+
+        forwarder.finish();
+    })
+}
+
+/// Spawn a task streaming raw PTY bytes to the frontend (`pty-output` event) and
+/// feeding them to the screen emulator.
+///
+/// While a full-screen program has the alternate screen open, its output is sent as
+/// `screen-update` events instead of being recorded; otherwise complete lines are
+/// recorded in the history buffer.
+#[cfg(unix)]
+fn spawn_pty_reader(
+    master: Arc<PtyMaster>,
+    emulator: SharedEmulator,
+    job: &Job,
+    session: Session,
+    app: AppHandle,
+) -> JoinHandle<()> {
+    let mut forwarder = LineForwarder::new(OutputStream::Stdout, job, session.clone(), app.clone());
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 8192];
+        let mut was_alternate = false;
+
+        loop {
+            let read = if forwarder.has_pending() {
+                match tokio::time::timeout(PARTIAL_LINE_FLUSH, master.read(&mut buf)).await {
+                    Ok(read) => read,
+                    Err(_elapsed) => {
+                        forwarder.flush_partial();
+                        continue;
+                    }
+                }
+            } else {
+                master.read(&mut buf).await
+            };
+            let n = match read {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::warn!("PTY read failed: {}", e);
+                    break;
+                }
+            };
+
+            let chunk = &buf[..n];
+            let raw = PtyOutput {
+                session_id: session.id.clone(),
+                data: chunk.to_vec(),
+            };
+            if let Err(e) = app.emit("pty-output", &raw) {
+                tracing::error!("Failed to emit pty-output event: {}", e);
+            }
+
+            let (alternate, update, responses) = {
+                let mut screen = emulator
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                screen.feed(chunk);
+                let alternate = screen.is_alternate();
+                // The final update after leaving tells the frontend to switch back
+                let update = if alternate || was_alternate {
+                    screen.take_update()
+                } else {
+                    None
+                };
+                (alternate, update, screen.take_responses())
+            };
+
+            if !responses.is_empty() {
+                if let Err(e) = master.write_all(&responses).await {
+                    tracing::warn!("Failed to answer terminal query: {}", e);
+                }
+            }
+            if let Some(update) = update {
+                let event = SessionScreen {
+                    session_id: session.id.clone(),
+                    update,
+                };
+                if let Err(e) = app.emit("screen-update", &event) {
+                    tracing::error!("Failed to emit screen-update event: {}", e);
+                }
+            }
+
+            if alternate || was_alternate {
+                // Full-screen output is not line-oriented; keep it out of the history
+                forwarder.discard(chunk);
+            } else {
+                forwarder.feed(chunk);
+            }
+            was_alternate = alternate;
+        }
+
+        forwarder.finish();
+    })
+}
+
+/// Spawn `command` with piped stdout/stderr and start its output readers. Stdin is
+/// piped for foreground commands and `/dev/null` for background jobs.
+async fn spawn_piped(
+    command: &str,
+    working_dir: &str,
+    stdin: Stdio,
+    job: &Job,
+    session: &Session,
+    app: &AppHandle,
+) -> std::io::Result<Spawned> {
+    let mut shell_cmd = session.shell.command(command);
+    session.shell_state.get_env().await.apply(&mut shell_cmd);
+    // Lead a new process group so cancellation reaches the shell's children too
+    #[cfg(unix)]
+    shell_cmd.process_group(0);
+    let mut child = shell_cmd
+        .current_dir(working_dir)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Keep stdin open for `write_stdin` until the command finishes
+    let stdin = child.stdin.take().map(|stdin| Arc::new(Mutex::new(stdin)));
+    let stdout = child.stdout.take().expect("stdout not captured");
+    let stderr = child.stderr.take().expect("stderr not captured");
+
+    let readers = OutputReaders::Piped {
+        stdout: spawn_line_reader(
+            stdout,
+            OutputStream::Stdout,
+            job,
+            session.clone(),
+            app.clone(),
+        ),
+        stderr: spawn_line_reader(
+            stderr,
+            OutputStream::Stderr,
+            job,
+            session.clone(),
+            app.clone(),
+        ),
+    };
+    Ok(Spawned {
+        child,
+        io: CommandIo {
+            stdin,
+            ..CommandIo::default()
+        },
+        readers,
+    })
+}
+
+/// Spawn `command` attached to a pseudo-terminal sized from the session state.
+#[cfg(unix)]
+async fn spawn_in_pty(
+    command: &str,
+    working_dir: &str,
+    job: &Job,
+    session: &Session,
+    app: &AppHandle,
+) -> std::io::Result<Spawned> {
+    let mut shell_cmd = session.shell.command(command);
+    shell_cmd.env("TERM", "xterm-256color");
+    // Session variables may override TERM
+    session.shell_state.get_env().await.apply(&mut shell_cmd);
+    shell_cmd.current_dir(working_dir);
+
+    let size = session.shell_state.get_pty_size().await;
+    let (child, master) = pty::spawn(shell_cmd, size)?;
+    let master = Arc::new(master);
+    let emulator = Arc::new(std::sync::Mutex::new(Emulator::new(size)));
+
+    let io = CommandIo {
+        stdin: None,
+        pty: Some(Arc::clone(&master)),
+        screen: Some(Arc::clone(&emulator)),
+    };
+    let readers = OutputReaders::Pty(spawn_pty_reader(
+        master,
+        emulator,
+        job,
+        session.clone(),
+        app.clone(),
+    ));
+    Ok(Spawned { child, io, readers })
+}
+
+/// Run `command` in the session's persistent shell, starting it on first use and
+/// again after the previous shell exited.
+#[cfg(unix)]
+async fn execute_persistent(
+    command: &str,
+    working_dir: &str,
+    session: &Session,
+    app: &AppHandle,
+) -> Result<CommandResponse, String> {
+    let env = session.shell_state.get_env().await;
+    let mut shell_slot = session.shell_state.persistent_shell.lock().await;
+    if shell_slot.is_none() {
+        let shell = PersistentShell::spawn(&session.shell, working_dir, &env)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to start persistent shell: {}", e);
+                format!("Failed to start persistent shell: {e}")
+            })?;
+        tracing::info!(
+            "Started persistent shell {} with PID: {:?}",
+            session.shell.program,
+            shell.id()
+        );
+        *shell_slot = Some(shell);
+    }
+    let shell = shell_slot
+        .as_mut()
+        .expect("persistent shell was just started");
+    shell.sync_env(&env);
+
+    session.shell_state.set_persistent_shell(shell.id()).await;
+    session.shell_state.set_stdin(shell.stdin()).await;
+
+    let counts = Arc::new(OutputCounts::default());
+    let mut stdout =
+        LineForwarder::untagged(OutputStream::Stdout, &counts, session.clone(), app.clone());
+    let mut stderr =
+        LineForwarder::untagged(OutputStream::Stderr, &counts, session.clone(), app.clone());
+    let outcome = shell
+        .run(
+            command,
+            working_dir,
+            PARTIAL_LINE_FLUSH,
+            |output| match output {
+                ShellOutput::Stdout(chunk) => stdout.feed(&chunk),
+                ShellOutput::Stderr(chunk) => stderr.feed(&chunk),
+                ShellOutput::Idle => {
+                    stdout.flush_partial();
+                    stderr.flush_partial();
+                }
+            },
+        )
+        .await;
+    stdout.finish();
+    stderr.finish();
+
+    // A shell that exited or failed is discarded; the next command starts a fresh one
+    if !matches!(outcome, Ok(RunOutcome::Completed { .. })) {
+        *shell_slot = None;
+    }
+    drop(shell_slot);
+
+    let result = match outcome {
+        Ok(RunOutcome::Completed { exit_code, cwd }) => {
+            if !cwd.is_empty() && cwd != session.get_cwd().await {
+                session.shell_state.set_cwd(cwd.clone()).await;
+                emit_cwd_changed(session, app, &cwd);
+            }
+            Ok(CommandResponse::with_exit_code(exit_code))
+        }
+        Ok(RunOutcome::ShellExited { exit_code }) => {
+            tracing::info!("Persistent shell exited with code: {:?}", exit_code);
+            push_and_emit(
+                session,
+                app,
+                OutputLine::Notification {
+                    message: "Shell exited; a new shell will start with the next command"
+                        .to_string(),
+                    level: NotificationLevel::Info,
+                    timestamp: current_timestamp_ms(),
+                },
+            );
+            exit_code.map_or_else(
+                || {
+                    Ok(CommandResponse::failure(
+                        "Shell terminated without exit code",
+                        None,
+                    ))
+                },
+                |code| Ok(CommandResponse::with_exit_code(code)),
+            )
+        }
+        Err(e) => {
+            tracing::error!("Persistent shell failed: {}", e);
+            Err(format!("Persistent shell failed: {e}"))
+        }
+    };
+    result.map(|response| with_output_counts(response, &counts))
 }
 
 /// Execute a shell command and stream output to the terminal.
 ///
+/// Directory builtins (`cd`, `pushd`, `popd`, `dirs`), alone or chained with `&&`, change
+/// the session's working directory and emit `cwd-changed`; `export` and `unset` edit the
+/// session environment. `jobs`, `fg` and `bg` manage the session's jobs, and a trailing
+/// `&` starts the last command of the line (after any `;`) as a background job.
+///
 /// # Arguments
+/// * `session_id` - Session to run the command in
 /// * `command` - The shell command to execute
 /// * `cwd` - Optional working directory (defaults to current)
+/// * `mode` - Optional execution mode (defaults to `Piped`; `Pty` runs under a pseudo-terminal,
+///   `Persistent` reuses the session's long-lived shell)
+/// * `timeout_ms` - Optional timeout in milliseconds (defaults to the session's); a command
+///   running longer is cancelled as Ctrl+C would, escalating to `SIGTERM`/`SIGKILL`
 /// * `state` - Tauri managed `ShellManager` state
 /// * `app` - Tauri app handle for emitting events
 ///
 /// # Returns
-/// * `Ok(CommandResponse)` - Command execution result
+/// * `Ok(CommandResponse)` - Command execution result (`timed_out` if it was cancelled by
+///   its timeout)
 /// * `Err(String)` - Error message if execution failed
 #[tauri::command]
-#[allow(clippy::cognitive_complexity, clippy::too_many_lines)]
 pub async fn execute_command(
+    session_id: String,
     command: String,
     cwd: Option<String>,
+    mode: Option<ExecutionMode>,
+    timeout_ms: Option<u64>,
     state: State<'_, ShellManager>,
     app: AppHandle,
 ) -> Result<CommandResponse, String> {
-    tracing::info!("Executing command: {}", command);
+    tracing::info!("Executing command in {}: {}", session_id, command);
+    let session = state.get_session(&session_id)?;
 
     // Check if empty command
     if command.trim().is_empty() {
         return Err("Command cannot be empty".to_string());
     }
+    if timeout_ms == Some(0) {
+        return Err("Timeout must be greater than zero".to_string());
+    }
 
     // Try to set busy state atomically
-    if !state.shell_state.try_set_busy().await {
+    if !session.shell_state.try_set_busy().await {
         tracing::warn!("Attempted to execute command while busy");
         return Err("Command already running".to_string());
     }
+    let command_id = session.shell_state.current_command_id();
+    let started_at = current_timestamp_ms();
+    let started = Instant::now();
 
     // Add command to history
     let cmd_line = OutputLine::Command {
         text: command.clone(),
         timestamp: current_timestamp_ms(),
     };
-    if let Some(warning) = state.history_buffer.push(cmd_line.clone()) {
-        if let Err(e) = app.emit("output-line", &warning) {
-            tracing::error!("Failed to emit warning event: {}", e);
-        }
-    }
-
-    // Emit command line event
-    if let Err(e) = app.emit("output-line", &cmd_line) {
-        tracing::error!("Failed to emit output-line event: {}", e);
-    }
+    push_and_emit(&session, &app, cmd_line);
 
     // Determine working directory
     let working_dir = match cwd {
         Some(path) => {
             // Validate directory exists
             if !std::path::Path::new(&path).is_dir() {
-                state.shell_state.set_busy(false).await;
+                session.shell_state.set_busy(false).await;
                 return Err(format!("Directory does not exist: {path}"));
             }
             path
         }
-        None => state.get_cwd().await,
+        None => session.get_cwd().await,
     };
 
     tracing::debug!("Working directory: {}", working_dir);
+    let started_event = CommandStarted {
+        session_id: session.id.clone(),
+        command_id,
+        command: command.clone(),
+        cwd: working_dir.clone(),
+        started_at,
+    };
+    emit_lifecycle(&session, &app, "command-started", &started_event);
 
-    // Spawn the process
-    let child_result = {
-        let mut shell_cmd = build_shell_command(&command);
-        shell_cmd
-            .current_dir(&working_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
+    let timeout = match timeout_ms {
+        Some(ms) => Some(Duration::from_millis(ms)),
+        None => session.shell_state.get_command_timeout().await,
     };
+    let timer = timeout.map(|timeout| {
+        let app = app.clone();
+        session.shell_state.start_timeout(
+            command_id,
+            timeout,
+            state.cancel_policy(),
+            move |signal| {
+                let message = if signal == JobSignal::Interrupt {
+                    format!(
+                        "Command timed out after {timeout:?}; sent {}",
+                        signal.name()
+                    )
+                } else {
+                    format!("Command still running; sent {}", signal.name())
+                };
+                emit_notification(&app, NotificationLevel::Warning, message);
+            },
+        )
+    });
 
-    let mut child = match child_result {
-        Ok(c) => c,
-        Err(e) => {
-            tracing::error!("Failed to spawn process: {}", e);
-            state.shell_state.set_busy(false).await;
-            return Err(format!("Failed to spawn process: {e}"));
+    let mut result = run_command_line(
+        &command,
+        &working_dir,
+        mode.unwrap_or_default(),
+        &session,
+        &app,
+    )
+    .await;
+
+    if let (Some(timeout), Some(timer)) = (timeout, timer) {
+        if timer.finish() {
+            result = result.map(|response| response.into_timed_out(timeout));
         }
+    }
+
+    // Clear busy state and process
+    let pid = session.shell_state.get_pid().await;
+    session.shell_state.set_busy(false).await;
+    session.shell_state.clear_process().await;
+
+    let finished_at = current_timestamp_ms();
+    let duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let outcome = result.as_ref().map_or_else(
+        |error| CommandResponse::failure(error.clone(), None),
+        Clone::clone,
+    );
+    state.command_history().record(CommandHistoryEntry {
+        command: command.clone(),
+        cwd: working_dir,
+        exit_code: outcome.exit_code,
+        timestamp: started_at,
+    });
+    let finished_event = CommandFinished {
+        session_id: session.id.clone(),
+        command_id,
+        cwd: session.get_cwd().await,
+        pid,
+        success: outcome.success,
+        exit_code: outcome.exit_code,
+        signal: outcome.signal,
+        timed_out: outcome.timed_out,
+        error: outcome.error,
+        duration_ms,
+        finished_at,
     };
+    emit_lifecycle(&session, &app, "command-finished", &finished_event);
 
-    // Take stdout and stderr before storing the child process
-    let stdout = child.stdout.take().expect("stdout not captured");
-    let stderr = child.stderr.take().expect("stderr not captured");
+    result.map(|response| CommandResponse {
+        command_id,
+        started_at,
+        finished_at,
+        duration_ms,
+        ..response
+    })
+}
 
-    // Store the child process (which also stores the PID)
-    state.shell_state.set_process(child).await;
-    let pid = state.shell_state.get_pid().await;
-    tracing::debug!("Process spawned with PID: {:?}", pid);
+/// Run a command line, applying intercepted builtins (`cd`, `pushd`, `popd`, `dirs`,
+/// `export`, `unset`, `jobs`, `fg`, `bg`) to the session and passing the rest to a child
+/// shell. A line ending in `&` starts its last command as a background job instead,
+/// once the commands before it finished.
+async fn run_command_line(
+    command: &str,
+    working_dir: &str,
+    mode: ExecutionMode,
+    session: &Session,
+    app: &AppHandle,
+) -> Result<CommandResponse, String> {
+    let Some(line) = builtins::split_background(command) else {
+        return run_foreground(command, working_dir, mode, session, app).await;
+    };
 
-    // Clone state and app for background tasks
-    let state_stdout = state.inner().clone();
-    let app_stdout = app.clone();
+    let mut working_dir = working_dir.to_string();
+    if let Some(foreground) = line.foreground {
+        let response = run_foreground(foreground, &working_dir, mode, session, app).await?;
+        // Cancelling the foreground part abandons the rest of the line
+        if response.signal.is_some() {
+            return Ok(response);
+        }
+        working_dir = session.get_cwd().await;
+    }
+    run_background(line.background, &working_dir, session, app).await?;
+    Ok(CommandResponse::success())
+}
 
-    // Spawn task to read stdout
-    let stdout_handle = tokio::spawn(async move {
-        let reader = BufReader::new(stdout);
-        let mut lines = reader.lines();
+/// Run a command line in the foreground, see [`run_command_line`].
+async fn run_foreground(
+    command: &str,
+    working_dir: &str,
+    mode: ExecutionMode,
+    session: &Session,
+    app: &AppHandle,
+) -> Result<CommandResponse, String> {
+    // The persistent shell keeps its own directory and variables, reporting the
+    // directory after each command
+    #[cfg(unix)]
+    if mode == ExecutionMode::Persistent {
+        return execute_persistent(command, working_dir, session, app).await;
+    }
 
-        while let Ok(Some(line)) = lines.next_line().await {
-            let output_line = OutputLine::Stdout {
-                text: sanitize_output(line),
-                timestamp: current_timestamp_ms(),
-            };
-            if let Some(warning) = state_stdout.history_buffer.push(output_line.clone()) {
-                if let Err(e) = app_stdout.emit("output-line", &warning) {
-                    tracing::error!("Failed to emit warning event: {}", e);
-                }
-            }
+    let env = session.shell_state.get_env().await;
+    let line = builtins::parse(command, |name| env.get(name));
 
-            if let Err(e) = app_stdout.emit("output-line", &output_line) {
-                tracing::error!("Failed to emit stdout event: {}", e);
-            }
+    for builtin in &line.leading {
+        let exit_code = run_builtin(builtin, session, app).await;
+        if exit_code != 0 {
+            return Ok(CommandResponse::with_exit_code(exit_code));
         }
-    });
+    }
 
-    // Clone state and app for stderr task
-    let state_stderr = state.inner().clone();
-    let app_stderr = app.clone();
+    let mut response = CommandResponse::success();
+    if let Some(external) = &line.external {
+        let working_dir = if line.leading.is_empty() {
+            working_dir.to_string()
+        } else {
+            session.get_cwd().await
+        };
+        response = run_external(external, &working_dir, mode, session, app).await?;
+        if !response.success {
+            return Ok(response);
+        }
+    }
 
-    // Spawn task to read stderr
-    let stderr_handle = tokio::spawn(async move {
-        let reader = BufReader::new(stderr);
-        let mut lines = reader.lines();
+    for builtin in &line.trailing {
+        let exit_code = run_builtin(builtin, session, app).await;
+        if exit_code != 0 {
+            return Ok(CommandResponse::with_exit_code(exit_code));
+        }
+    }
 
-        while let Ok(Some(line)) = lines.next_line().await {
-            let output_line = OutputLine::Stderr {
-                text: sanitize_output(line),
-                timestamp: current_timestamp_ms(),
-            };
-            if let Some(warning) = state_stderr.history_buffer.push(output_line.clone()) {
-                if let Err(e) = app_stderr.emit("output-line", &warning) {
-                    tracing::error!("Failed to emit warning event: {}", e);
-                }
-            }
+    Ok(response)
+}
 
-            if let Err(e) = app_stderr.emit("output-line", &output_line) {
-                tracing::error!("Failed to emit stderr event: {}", e);
-            }
+/// Run an intercepted builtin, forwarding its output and any directory change.
+async fn run_builtin(builtin: &Builtin, session: &Session, app: &AppHandle) -> i32 {
+    tracing::debug!("Running builtin: {:?}", builtin);
+    let output = builtins::run(builtin, &session.shell_state).await;
+
+    for text in output.stdout {
+        let timestamp = current_timestamp_ms();
+        push_and_emit(session, app, OutputLine::Stdout { text, timestamp });
+    }
+    for text in output.stderr {
+        let timestamp = current_timestamp_ms();
+        push_and_emit(session, app, OutputLine::Stderr { text, timestamp });
+    }
+    if let Some(cwd) = output.new_cwd {
+        tracing::info!("Directory changed to: {}", cwd);
+        emit_cwd_changed(session, app, &cwd);
+    }
+    if let Some(job) = output.foreground_job {
+        if let Some(job) = session.shell_state.jobs.get(job).await {
+            tracing::info!("Job {} moved to the foreground", job.id);
+            let response = job_response(wait_for_job(&job, session).await);
+            // A signal is reported as 128 + its number, as shells do
+            return response
+                .exit_code
+                .or_else(|| response.signal.map(|signal| 128 + signal))
+                .unwrap_or(1);
         }
-    });
+    }
+
+    output.exit_code
+}
+
+/// Spawn `command` in a child shell as the session's foreground job and wait for it to
+/// finish or be suspended.
+async fn run_external(
+    command: &str,
+    working_dir: &str,
+    mode: ExecutionMode,
+    session: &Session,
+    app: &AppHandle,
+) -> Result<CommandResponse, String> {
+    let jobs = &session.shell_state.jobs;
+    let job = jobs.add(command, true).await;
+
+    // Spawn the process
+    let spawn_result = match mode {
+        #[cfg(unix)]
+        ExecutionMode::Pty => spawn_in_pty(command, working_dir, &job, session, app).await,
+        #[cfg(not(unix))]
+        ExecutionMode::Pty | ExecutionMode::Persistent => {
+            tracing::warn!(
+                "{:?} mode is not supported on this platform; using pipes",
+                mode
+            );
+            spawn_piped(command, working_dir, Stdio::piped(), &job, session, app).await
+        }
+        // Persistent commands never reach here on Unix
+        _ => spawn_piped(command, working_dir, Stdio::piped(), &job, session, app).await,
+    };
 
-    // Wait for process to complete
-    let taken_child = {
-        let mut process_opt = state.shell_state.process.lock().await;
-        process_opt.take()
+    let spawned = match spawn_result {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            jobs.remove(job.id).await;
+            tracing::error!("Failed to spawn process: {}", e);
+            return Err(format!("Failed to spawn process: {e}"));
+        }
     };
 
-    let Some(mut child_for_wait) = taken_child else {
-        tracing::error!("Process not found in state");
+    let pid = spawned.child.id();
+    tracing::debug!("Process spawned with PID: {:?}", pid);
+    job.set_pid(pid);
+    // The command leads its own process group (a PTY command its own session)
+    session.shell_state.set_process_group(pid).await;
+    session.shell_state.set_io(spawned.io).await;
+    spawn_job_waiter(
+        Arc::clone(&job),
+        spawned.child,
+        spawned.readers,
+        session.clone(),
+        app.clone(),
+    );
+
+    let status = wait_for_job(&job, session).await;
+    tracing::info!("Command ended as job {}: {:?}", job.id, status);
+    Ok(with_output_counts(job_response(status), &job.output))
+}
 
-        if let Err(join_err) = stdout_handle.await {
-            tracing::warn!("stdout reader task join failed: {}", join_err);
+/// Response of a foreground job that exited or was suspended.
+fn job_response(status: JobStatus) -> CommandResponse {
+    match status {
+        JobStatus::Done {
+            exit_code: Some(code),
+            ..
+        } => CommandResponse::with_exit_code(code),
+        JobStatus::Done {
+            signal: Some(signal),
+            core_dumped,
+            ..
+        } => CommandResponse::killed(signal, &signals::signal_name(signal), core_dumped),
+        JobStatus::Done { .. } => {
+            CommandResponse::failure("Process terminated without exit code", None)
         }
-        if let Err(join_err) = stderr_handle.await {
-            tracing::warn!("stderr reader task join failed: {}", join_err);
+        JobStatus::Running | JobStatus::Stopped => {
+            CommandResponse::with_exit_code(SUSPENDED_EXIT_CODE)
         }
+    }
+}
 
-        state.shell_state.set_busy(false).await;
-        state.shell_state.clear_process().await;
-        return Err("Process not found in state".to_string());
-    };
+/// Add the lines and bytes a command wrote to its response.
+fn with_output_counts(response: CommandResponse, counts: &OutputCounts) -> CommandResponse {
+    CommandResponse {
+        stdout_lines: counts.lines(OutputStream::Stdout),
+        stdout_bytes: counts.bytes(OutputStream::Stdout),
+        stderr_lines: counts.lines(OutputStream::Stderr),
+        stderr_bytes: counts.bytes(OutputStream::Stderr),
+        ..response
+    }
+}
+
+/// Start `command` as a background job with stdin from `/dev/null`, printing its job
+/// number and PID as a shell does.
+async fn run_background(
+    command: &str,
+    working_dir: &str,
+    session: &Session,
+    app: &AppHandle,
+) -> Result<Arc<Job>, String> {
+    let jobs = &session.shell_state.jobs;
+    let job = jobs.add(command, false).await;
 
-    let status = match child_for_wait.wait().await {
-        Ok(s) => s,
+    let spawned = match spawn_piped(command, working_dir, Stdio::null(), &job, session, app).await {
+        Ok(spawned) => spawned,
         Err(e) => {
-            tracing::error!("Failed to wait for process: {}", e);
+            jobs.remove(job.id).await;
+            tracing::error!("Failed to spawn background job: {}", e);
+            return Err(format!("Failed to spawn process: {e}"));
+        }
+    };
 
-            // Attempt to terminate the child process explicitly
-            kill_child_with_fallback(&mut child_for_wait).await;
+    let pid = spawned.child.id();
+    job.set_pid(pid);
+    tracing::info!("Started job {} with PID: {:?}", job.id, pid);
+    let text = pid.map_or_else(
+        || format!("[{}]", job.id),
+        |pid| format!("[{}] {pid}", job.id),
+    );
+    push_and_emit(
+        session,
+        app,
+        OutputLine::Stdout {
+            text,
+            timestamp: current_timestamp_ms(),
+        },
+    );
+    spawn_job_waiter(
+        Arc::clone(&job),
+        spawned.child,
+        spawned.readers,
+        session.clone(),
+        app.clone(),
+    );
 
-            // Wait for output readers to complete
-            if let Err(join_err) = stdout_handle.await {
-                tracing::warn!("stdout reader task join failed: {}", join_err);
-            }
-            if let Err(join_err) = stderr_handle.await {
-                tracing::warn!("stderr reader task join failed: {}", join_err);
+    Ok(job)
+}
+
+/// Wait for a job's process in a background task and record its exit status once its
+/// output is drained. A job that is not in the foreground is then removed from the
+/// table and its completion announced, as a shell reports `[1]+  Done`.
+fn spawn_job_waiter(
+    job: Arc<Job>,
+    mut child: Child,
+    readers: OutputReaders,
+    session: Session,
+    app: AppHandle,
+) {
+    tokio::spawn(async move {
+        let status = match child.wait().await {
+            Ok(status) => JobStatus::exited(status),
+            Err(e) => {
+                tracing::error!("Failed to wait for process: {}", e);
+
+                // Attempt to terminate the child process explicitly
+                kill_child_with_fallback(&mut child).await;
+                JobStatus::Done {
+                    exit_code: None,
+                    signal: None,
+                    core_dumped: false,
+                }
             }
+        };
 
-            state.shell_state.set_busy(false).await;
-            state.shell_state.clear_process().await;
+        // Wait for output readers to complete
+        readers.join().await;
+        job.set_status(status);
 
-            return Err(format!("Failed to wait for process: {e}"));
+        if job.is_foreground() || session.shell_state.jobs.remove(job.id).await.is_none() {
+            return;
         }
-    };
-
-    // Wait for output readers to complete
-    let _ = stdout_handle.await;
-    let _ = stderr_handle.await;
+        let message = format!("[{}]  {:<24}{}", job.id, job.status_text(), job.command);
+        tracing::info!("Background job finished: {}", message);
+        push_and_emit(
+            &session,
+            &app,
+            OutputLine::Notification {
+                message: message.clone(),
+                level: NotificationLevel::Info,
+                timestamp: current_timestamp_ms(),
+            },
+        );
+        emit_notification(&app, NotificationLevel::Info, message);
+    });
+}
 
-    // Clear busy state and process
-    state.shell_state.set_busy(false).await;
-    state.shell_state.clear_process().await;
+/// Wait while the session's foreground job runs. A job that exited is removed from the
+/// table; a suspended one stays there for `fg` and `bg`.
+async fn wait_for_job(job: &Job, session: &Session) -> JobStatus {
+    let status = job.wait_while_running().await;
+    if matches!(status, JobStatus::Done { .. }) {
+        session.shell_state.jobs.remove(job.id).await;
+    }
+    status
+}
 
-    let exit_code = status.code();
-    let success = status.success();
+/// Interrupt the currently running command (Ctrl+C).
+///
+/// The signal goes to the command's whole process group, so children of the shell
+/// (pipelines, dev servers) stop too. If the command keeps running, it is sent `SIGTERM`
+/// and then `SIGKILL` after the delays of the manager's cancel policy, with a
+/// notification at each step.
+///
+/// # Arguments
+/// * `session_id` - Session whose command to interrupt
+/// * `state` - Tauri managed `ShellManager` state
+/// * `app` - Tauri app handle for emitting notifications
+///
+/// # Returns
+/// * `Ok(())` - Interrupt signal sent successfully
+/// * `Err(String)` - Error message if no command is running
+#[tauri::command]
+pub async fn cancel_command(
+    session_id: String,
+    state: State<'_, ShellManager>,
+    app: AppHandle,
+) -> Result<(), String> {
+    tracing::info!("Cancel command requested in {}", session_id);
+    let session = state.get_session(&session_id)?;
+    let command_id = session.shell_state.current_command_id();
 
-    tracing::info!(
-        "Command completed with exit code: {:?}, success: {}",
-        exit_code,
-        success
+    if let Err(e) = session
+        .shell_state
+        .signal_command(JobSignal::Interrupt)
+        .await
+    {
+        tracing::warn!("Cancel failed: {}", e);
+        return Err(e);
+    }
+    emit_notification(
+        &app,
+        NotificationLevel::Info,
+        format!(
+            "Sent {} to the running command",
+            JobSignal::Interrupt.name()
+        ),
     );
 
-    exit_code.map_or_else(
-        || {
-            Ok(CommandResponse::failure(
-                "Process terminated without exit code",
-                None,
-            ))
-        },
-        |code| Ok(CommandResponse::with_exit_code(code)),
-    )
+    let policy = state.cancel_policy();
+    tokio::spawn(async move {
+        session
+            .shell_state
+            .escalate_cancel(command_id, policy, |signal| {
+                tracing::warn!("Command still running; escalating to {}", signal.name());
+                emit_notification(
+                    &app,
+                    NotificationLevel::Warning,
+                    format!("Command still running; sent {}", signal.name()),
+                );
+            })
+            .await;
+    });
+
+    Ok(())
 }
 
-/// Send interrupt signal to the currently running command (Ctrl+C).
+/// Kill the currently running command and its process group immediately (`SIGKILL`;
+/// `taskkill /F` on Windows), without waiting for it to clean up.
 ///
 /// # Arguments
+/// * `session_id` - Session whose command to kill
 /// * `state` - Tauri managed `ShellManager` state
+/// * `app` - Tauri app handle for emitting notifications
 ///
 /// # Returns
-/// * `Ok(())` - Interrupt signal sent successfully
+/// * `Ok(())` - Kill signal sent successfully
 /// * `Err(String)` - Error message if no command is running
 #[tauri::command]
-pub async fn cancel_command(state: State<'_, ShellManager>) -> Result<(), String> {
-    tracing::info!("Cancel command requested");
+pub async fn force_kill_command(
+    session_id: String,
+    state: State<'_, ShellManager>,
+    app: AppHandle,
+) -> Result<(), String> {
+    tracing::info!("Force kill requested in {}", session_id);
+    let session = state.get_session(&session_id)?;
 
-    let pid = state.get_running_pid().await;
+    if let Err(e) = session.shell_state.signal_command(JobSignal::Kill).await {
+        tracing::warn!("Force kill failed: {}", e);
+        return Err(e);
+    }
+    emit_notification(
+        &app,
+        NotificationLevel::Warning,
+        format!("Sent {} to the running command", JobSignal::Kill.name()),
+    );
+    Ok(())
+}
 
-    if let Some(pid) = pid {
-        tracing::info!("Sending interrupt signal to PID: {}", pid);
+/// Set the timeout applied to the session's commands that are run without one.
+///
+/// # Arguments
+/// * `session_id` - Session to configure
+/// * `timeout_ms` - Timeout in milliseconds, or `None` to let commands run indefinitely
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Err(String)` - Error message if the session does not exist or the timeout is zero
+#[tauri::command]
+pub async fn set_command_timeout(
+    session_id: String,
+    timeout_ms: Option<u64>,
+    state: State<'_, ShellManager>,
+) -> Result<(), String> {
+    let session = state.get_session(&session_id)?;
+    if timeout_ms == Some(0) {
+        return Err("Timeout must be greater than zero".to_string());
+    }
+    tracing::info!(
+        "Command timeout of {} set to {:?} ms",
+        session_id,
+        timeout_ms
+    );
+    session
+        .shell_state
+        .set_command_timeout(timeout_ms.map(Duration::from_millis))
+        .await;
+    Ok(())
+}
 
-        #[cfg(unix)]
-        {
-            use nix::errno::Errno;
-            use nix::sys::signal::{self, Signal};
-            use nix::unistd::Pid;
-
-            let pid_i32 = i32::try_from(pid)
-                .map_err(|_| "PID out of range for Unix signal delivery".to_string())?;
-
-            match signal::kill(Pid::from_raw(pid_i32), Signal::SIGINT) {
-                Ok(()) => Ok(()),
-                Err(Errno::ESRCH) => {
-                    tracing::warn!(
-                        "SIGINT skipped: process with PID {} does not exist (ESRCH)",
-                        pid
-                    );
-                    Ok(())
-                }
-                Err(e) => Err(format!("Failed to send SIGINT: {e}")),
-            }
+/// Set how long a cancelled command may keep running before `SIGTERM`, then `SIGKILL`,
+/// is sent, in every session.
+///
+/// # Arguments
+/// * `terminate_after_ms` - Wait after `SIGINT` before sending `SIGTERM`
+/// * `kill_after_ms` - Wait after `SIGTERM` before sending `SIGKILL`
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(())` - The delays apply to cancellations and timeouts from now on
+#[tauri::command]
+pub async fn set_cancel_policy(
+    terminate_after_ms: u64,
+    kill_after_ms: u64,
+    state: State<'_, ShellManager>,
+) -> Result<(), String> {
+    tracing::info!(
+        "Cancel escalation set to SIGTERM after {} ms, SIGKILL after {} ms",
+        terminate_after_ms,
+        kill_after_ms
+    );
+    state.set_cancel_policy(EscalationPolicy {
+        terminate_after: Duration::from_millis(terminate_after_ms),
+        kill_after: Duration::from_millis(kill_after_ms),
+    });
+    Ok(())
+}
+
+/// Suspend the session's foreground job (Ctrl+Z) with `SIGTSTP`.
+///
+/// The job keeps its number in the job table and its further output is tagged with it;
+/// the command waiting for it returns with exit code 148, leaving the session free.
+/// `fg` resumes it in the foreground and `bg` in the background.
+///
+/// # Arguments
+/// * `session_id` - Session whose command to suspend
+/// * `state` - Tauri managed `ShellManager` state
+/// * `app` - Tauri app handle for emitting output
+///
+/// # Returns
+/// * `Ok(JobInfo)` - The suspended job
+/// * `Err(String)` - Error message if no job is running in the foreground
+#[tauri::command]
+pub async fn suspend_command(
+    session_id: String,
+    state: State<'_, ShellManager>,
+    app: AppHandle,
+) -> Result<JobInfo, String> {
+    tracing::info!("Suspend requested in {}", session_id);
+    let session = state.get_session(&session_id)?;
+    let job = match session.shell_state.suspend_foreground().await {
+        Ok(job) => job,
+        Err(e) => {
+            tracing::warn!("Suspend failed: {}", e);
+            return Err(e);
         }
+    };
 
-        #[cfg(windows)]
-        {
-            let output = Command::new("taskkill")
-                .arg("/PID")
-                .arg(pid.to_string())
-                .arg("/T")
-                .output()
-                .await
-                .map_err(|e| format!("Failed to spawn taskkill: {e}"))?;
+    push_and_emit(
+        &session,
+        &app,
+        OutputLine::Notification {
+            message: format!("[{}]+  {:<24}{}", job.id, job.status_text(), job.command),
+            level: NotificationLevel::Info,
+            timestamp: current_timestamp_ms(),
+        },
+    );
+    Ok(job.info())
+}
 
-            if output.status.success() {
-                Ok(())
-            } else {
-                Err(format!(
-                    "taskkill failed: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ))
-            }
+/// Start a command as a background job, even while another command runs.
+///
+/// Equivalent to running `command &`: stdin is `/dev/null`, output is tagged with the
+/// job number, and a notification is shown when the job finishes.
+///
+/// # Arguments
+/// * `session_id` - Session to run the job in
+/// * `command` - The shell command to execute
+/// * `cwd` - Optional working directory (defaults to current)
+/// * `state` - Tauri managed `ShellManager` state
+/// * `app` - Tauri app handle for emitting events
+///
+/// # Returns
+/// * `Ok(JobInfo)` - The started job
+/// * `Err(String)` - Error message if the command could not be started
+#[tauri::command]
+pub async fn run_in_background(
+    session_id: String,
+    command: String,
+    cwd: Option<String>,
+    state: State<'_, ShellManager>,
+    app: AppHandle,
+) -> Result<JobInfo, String> {
+    tracing::info!("Starting background job in {}: {}", session_id, command);
+    let session = state.get_session(&session_id)?;
+    let command = command.trim();
+    if command.is_empty() {
+        return Err("Command cannot be empty".to_string());
+    }
+
+    let working_dir = match cwd {
+        Some(path) if !std::path::Path::new(&path).is_dir() => {
+            return Err(format!("Directory does not exist: {path}"));
         }
+        Some(path) => path,
+        None => session.get_cwd().await,
+    };
 
-        #[cfg(not(any(unix, windows)))]
-        {
-            Ok(())
+    push_and_emit(
+        &session,
+        &app,
+        OutputLine::Command {
+            text: format!("{command} &"),
+            timestamp: current_timestamp_ms(),
+        },
+    );
+    let job = run_background(command, &working_dir, &session, &app).await?;
+    Ok(job.info())
+}
+
+/// List the session's background and suspended jobs.
+///
+/// # Arguments
+/// * `session_id` - Session to read
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(Vec<JobInfo>)` - Jobs by number
+#[tauri::command]
+pub async fn list_jobs(
+    session_id: String,
+    state: State<'_, ShellManager>,
+) -> Result<Vec<JobInfo>, String> {
+    let session = state.get_session(&session_id)?;
+    Ok(session.shell_state.jobs.list().await)
+}
+
+/// Send input to the running command (e.g. an answer to a prompt).
+///
+/// In PTY mode the data goes through the terminal, which echoes and line-edits it;
+/// otherwise it is written to the command's stdin pipe unchanged.
+///
+/// # Arguments
+/// * `session_id` - Session whose command receives the input
+/// * `data` - Text to send, including any trailing newline
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(())` - Input delivered
+/// * `Err(String)` - Error message if no command is running or the write failed
+#[tauri::command]
+pub async fn write_stdin(
+    session_id: String,
+    data: String,
+    state: State<'_, ShellManager>,
+) -> Result<(), String> {
+    let session = state.get_session(&session_id)?;
+    if !session.is_busy().await {
+        tracing::warn!("Input sent but no command is running");
+        return Err("No command currently running".to_string());
+    }
+
+    tracing::debug!("Writing {} bytes to stdin in {}", data.len(), session_id);
+    session.shell_state.write_stdin(data.as_bytes()).await
+}
+
+/// Set the terminal window size for PTY-backed commands.
+///
+/// Applies immediately to a running PTY command (which receives `SIGWINCH`) and its
+/// screen emulator, and is remembered for subsequent commands.
+///
+/// # Arguments
+/// * `session_id` - Session to resize
+/// * `rows` - Number of rows
+/// * `cols` - Number of columns
+/// * `state` - Tauri managed `ShellManager` state
+#[tauri::command]
+pub async fn resize_pty(
+    session_id: String,
+    rows: u16,
+    cols: u16,
+    state: State<'_, ShellManager>,
+) -> Result<(), String> {
+    let session = state.get_session(&session_id)?;
+    let size = PtySize::new(rows, cols);
+    tracing::debug!("Resizing PTY to {}x{}", size.cols, size.rows);
+    session.shell_state.set_pty_size(size).await;
+
+    #[cfg(unix)]
+    {
+        if let Some(emulator) = session.shell_state.get_screen().await {
+            emulator
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .resize(size);
+        }
+        if let Some(master) = session.shell_state.get_pty().await {
+            master
+                .resize(size)
+                .map_err(|e| format!("Failed to resize PTY: {e}"))?;
         }
-    } else {
-        tracing::warn!("Cancel requested but no command is running");
-        Err("No command currently running".to_string())
     }
+
+    Ok(())
 }
 
-/// Retrieve the full terminal history buffer.
+/// Retrieve the full terminal history buffer of a session.
 ///
 /// # Arguments
+/// * `session_id` - Session to read
 /// * `state` - Tauri managed `ShellManager` state
 ///
 /// # Returns
-/// * `Ok(Vec<OutputLine>)` - All lines in the history buffer
+/// * `Ok(Vec<HistoryLine>)` - All lines in the history buffer, with their sequence numbers
 #[tauri::command]
-pub async fn get_history(state: State<'_, ShellManager>) -> Result<Vec<OutputLine>, String> {
-    tracing::debug!("Getting history buffer");
-    Ok(state.history_buffer.get_all())
+pub async fn get_history(
+    session_id: String,
+    state: State<'_, ShellManager>,
+) -> Result<Vec<HistoryLine>, String> {
+    tracing::debug!("Getting history buffer of {}", session_id);
+    let session = state.get_session(&session_id)?;
+    Ok(session.history_buffer.since(0))
 }
 
-/// Get the current working directory of the shell.
+/// Retrieve the lines of a session's history from a sequence number on, to catch up
+/// after missing `output-batch` events.
 ///
 /// # Arguments
+/// * `session_id` - Session to read
+/// * `seq` - Sequence number of the first line wanted; pass that of the last line seen to
+///   also get its final version if it was a partial line
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(Vec<HistoryLine>)` - The lines still in the history buffer with sequence number
+///   `seq` or later
+#[tauri::command]
+pub async fn get_history_since(
+    session_id: String,
+    seq: u64,
+    state: State<'_, ShellManager>,
+) -> Result<Vec<HistoryLine>, String> {
+    tracing::debug!("Getting history of {} since {}", session_id, seq);
+    let session = state.get_session(&session_id)?;
+    Ok(session.history_buffer.since(seq))
+}
+
+/// Retrieve a page of a session's history.
+///
+/// # Arguments
+/// * `session_id` - Session to read
+/// * `offset` - Lines to skip, counted from the oldest line kept
+/// * `limit` - Most lines to return
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Ok(Vec<HistoryLine>)` - Up to `limit` lines, with their sequence numbers
+#[tauri::command]
+pub async fn get_history_range(
+    session_id: String,
+    offset: usize,
+    limit: usize,
+    state: State<'_, ShellManager>,
+) -> Result<Vec<HistoryLine>, String> {
+    tracing::debug!(
+        "Getting {} history lines of {} from {}",
+        limit,
+        session_id,
+        offset
+    );
+    let session = state.get_session(&session_id)?;
+    Ok(session.history_buffer.range(offset, limit))
+}
+
+/// Get the current working directory of a session.
+///
+/// # Arguments
+/// * `session_id` - Session to read
 /// * `state` - Tauri managed `ShellManager` state
 ///
 /// # Returns
 /// * `Ok(String)` - Current working directory path
 #[tauri::command]
-pub async fn get_cwd(state: State<'_, ShellManager>) -> Result<String, String> {
-    let cwd = state.get_cwd().await;
+pub async fn get_cwd(session_id: String, state: State<'_, ShellManager>) -> Result<String, String> {
+    let session = state.get_session(&session_id)?;
+    let cwd = session.get_cwd().await;
     tracing::debug!("Getting CWD: {}", cwd);
     Ok(cwd)
 }
@@ -378,9 +1558,10 @@ pub fn get_home_dir() -> Result<String, String> {
     Ok(home.to_string_lossy().to_string())
 }
 
-/// Change the working directory for subsequent commands.
+/// Change the working directory for subsequent commands in a session.
 ///
 /// # Arguments
+/// * `session_id` - Session to change
 /// * `path` - New working directory path (absolute or relative)
 /// * `state` - Tauri managed `ShellManager` state
 /// * `app` - Tauri app handle for emitting events
@@ -390,17 +1571,19 @@ pub fn get_home_dir() -> Result<String, String> {
 /// * `Err(String)` - Error message if path is invalid
 #[tauri::command]
 pub async fn change_directory(
+    session_id: String,
     path: String,
     state: State<'_, ShellManager>,
     app: AppHandle,
 ) -> Result<String, String> {
-    tracing::info!("Changing directory to: {}", path);
+    tracing::info!("Changing directory of {} to: {}", session_id, path);
+    let session = state.get_session(&session_id)?;
 
     let target_path = std::path::Path::new(&path);
 
     // Handle relative paths
     let absolute_path = if target_path.is_relative() {
-        let current = state.get_cwd().await;
+        let current = session.get_cwd().await;
         std::path::Path::new(&current)
             .join(target_path)
             .canonicalize()
@@ -417,20 +1600,75 @@ pub async fn change_directory(
     }
 
     let new_cwd = absolute_path.to_string_lossy().to_string();
-    state.shell_state.set_cwd(new_cwd.clone()).await;
+    session.shell_state.change_cwd(new_cwd.clone()).await;
+    emit_cwd_changed(&session, &app, &new_cwd);
 
     tracing::info!("Directory changed to: {}", new_cwd);
 
-    // Emit notification
-    let notification = OutputLine::Notification {
-        message: format!("Changed directory to: {new_cwd}"),
-        level: NotificationLevel::Info,
-        timestamp: current_timestamp_ms(),
-    };
-
-    if let Err(e) = app.emit("shell-notification", &notification) {
-        tracing::error!("Failed to emit notification: {}", e);
-    }
+    emit_notification(
+        &app,
+        NotificationLevel::Info,
+        format!("Changed directory to: {new_cwd}"),
+    );
 
     Ok(new_cwd)
 }
+
+/// Get the environment variables set in a session.
+///
+/// Inherited variables are not included; they are passed to commands unchanged unless
+/// overridden or removed.
+///
+/// # Arguments
+/// * `session_id` - Session to read
+/// * `state` - Tauri managed `ShellManager` state
+#[tauri::command]
+pub async fn get_env(
+    session_id: String,
+    state: State<'_, ShellManager>,
+) -> Result<BTreeMap<String, String>, String> {
+    let session = state.get_session(&session_id)?;
+    Ok(session.shell_state.get_env().await.vars().clone())
+}
+
+/// Set an environment variable for all subsequent commands in the session.
+///
+/// # Arguments
+/// * `session_id` - Session to change
+/// * `name` - Variable name (`[A-Za-z_][A-Za-z0-9_]*`)
+/// * `value` - Variable value
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Err(String)` - Error message if the name or value is invalid
+#[tauri::command]
+pub async fn set_env(
+    session_id: String,
+    name: String,
+    value: String,
+    state: State<'_, ShellManager>,
+) -> Result<(), String> {
+    tracing::info!("Setting environment variable in {}: {}", session_id, name);
+    let session = state.get_session(&session_id)?;
+    session.shell_state.set_env_var(&name, &value).await
+}
+
+/// Remove an environment variable (including an inherited one) for subsequent commands.
+///
+/// # Arguments
+/// * `session_id` - Session to change
+/// * `name` - Variable name
+/// * `state` - Tauri managed `ShellManager` state
+///
+/// # Returns
+/// * `Err(String)` - Error message if the name is invalid
+#[tauri::command]
+pub async fn unset_env(
+    session_id: String,
+    name: String,
+    state: State<'_, ShellManager>,
+) -> Result<(), String> {
+    tracing::info!("Unsetting environment variable in {}: {}", session_id, name);
+    let session = state.get_session(&session_id)?;
+    session.shell_state.unset_env_var(&name).await
+}
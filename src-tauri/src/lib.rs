@@ -1,22 +1,38 @@
 pub mod commands;
 pub mod logging;
 pub mod models;
+pub mod process;
 pub mod state;
 
+use commands::completion::complete;
+use commands::config::{get_config, watch_config};
+use commands::history::{get_command_history, search_command_history};
+use commands::session::{close_session, create_session, get_available_shells, list_sessions};
 use commands::shell::{
-    cancel_command, change_directory, execute_command, get_cwd, get_history, get_home_dir,
+    cancel_command, change_directory, execute_command, force_kill_command, get_cwd, get_env,
+    get_history, get_history_range, get_history_since, get_home_dir, list_jobs, resize_pty,
+    run_in_background, set_cancel_policy, set_command_timeout, set_env, suspend_command, unset_env,
+    write_stdin,
 };
 use logging::setup_logging;
-use state::ShellManager;
+use state::{CommandHistory, ConfigStore, ScrollbackStore, ShellManager};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Load the user configuration first: it sets how many log files are kept
+    let config_store =
+        ConfigStore::default_path().map_or_else(ConfigStore::default, ConfigStore::load);
+    let config = config_store.config();
+
     // Initialize logging before starting Tauri
-    if let Err(e) = setup_logging() {
+    if let Err(e) = setup_logging(config.logging.max_files) {
         eprintln!("Warning: Failed to setup logging: {e}");
     }
 
     tracing::info!("Starting Cepheus terminal application");
+    if let Some(error) = config_store.status().error {
+        tracing::warn!("Using default settings, config file is invalid: {}", error);
+    }
 
     // Initialize shell manager state
     // Get CWD once before creating ShellManager to ensure logged value matches actual initialization
@@ -47,19 +63,68 @@ pub fn run() {
     let cwd_display = current_dir.display().to_string();
     let initial_cwd = current_dir.to_string_lossy().to_string();
 
-    let shell_manager = ShellManager::new_with_cwd(initial_cwd);
+    let mut shell_manager = ShellManager::new_with_cwd(initial_cwd);
+    tauri::async_runtime::block_on(shell_manager.apply_config(&config));
     tracing::info!("Shell manager initialized with cwd: {}", cwd_display);
 
+    // Reopen the output of sessions left open when the app last exited (or crashed)
+    match ScrollbackStore::default_dir().map(ScrollbackStore::open) {
+        Some(Ok(store)) => {
+            shell_manager = shell_manager.with_scrollback(store);
+            let restored = shell_manager.restore_sessions();
+            tracing::info!("Restored {} session(s) from scrollback", restored);
+        }
+        Some(Err(e)) => tracing::warn!("Scrollback disabled: {}", e),
+        None => tracing::warn!("Scrollback disabled: cannot find home directory"),
+    }
+
+    match CommandHistory::default_path()
+        .map(|path| CommandHistory::open(path, CommandHistory::DEFAULT_CAPACITY))
+    {
+        Some(Ok(history)) => {
+            tracing::info!("Loaded {} command(s) from command history", history.len());
+            shell_manager = shell_manager.with_command_history(history);
+        }
+        Some(Err(e)) => tracing::warn!("Command history not persisted: {}", e),
+        None => tracing::warn!("Command history not persisted: cannot find home directory"),
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .manage(shell_manager)
+        .manage(config_store)
+        .setup(|app| {
+            watch_config(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             execute_command,
             cancel_command,
+            force_kill_command,
+            suspend_command,
+            run_in_background,
+            list_jobs,
+            set_command_timeout,
+            set_cancel_policy,
+            write_stdin,
             get_history,
+            get_history_since,
+            get_history_range,
             get_cwd,
             get_home_dir,
-            change_directory
+            change_directory,
+            resize_pty,
+            get_env,
+            set_env,
+            unset_env,
+            create_session,
+            close_session,
+            list_sessions,
+            get_available_shells,
+            get_command_history,
+            search_command_history,
+            complete,
+            get_config
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
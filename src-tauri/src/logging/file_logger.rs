@@ -4,13 +4,12 @@ use std::path::Path;
 use tracing_appender::rolling;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-const MAX_LOG_FILES: usize = 14; // keep roughly two weeks of daily logs
-
-/// Setup file-based logging to ~/.cepheus/terminal.log
+/// Setup file-based logging to ~/.cepheus/terminal.log, keeping at most `max_files`
+/// daily log files
 ///
 /// # Errors
 /// Returns an error if the log directory cannot be created or logging fails to initialize.
-pub fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
+pub fn setup_logging(max_files: usize) -> Result<(), Box<dyn std::error::Error>> {
     let log_dir = dirs_next::home_dir()
         .ok_or("Cannot find home directory")?
         .join(".cepheus");
@@ -19,7 +18,7 @@ pub fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
 
     // Best-effort cleanup of old rotated logs to avoid unbounded disk growth.
     // Note: runs before tracing init so it can't use tracing; eprintln! fallback is intentional.
-    cleanup_old_logs(&log_dir, MAX_LOG_FILES);
+    cleanup_old_logs(&log_dir, max_files);
 
     let file_appender = rolling::daily(&log_dir, "terminal.log");
 
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
 /// Request to execute a shell command (frontend → backend IPC).
@@ -10,7 +12,8 @@ pub struct CommandRequest {
 }
 
 /// Response from shell command execution (backend → frontend IPC).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CommandResponse {
     /// Command execution succeeded
     pub success: bool,
@@ -18,16 +21,80 @@ pub struct CommandResponse {
     pub exit_code: Option<i32>,
     /// Error message (if execution failed)
     pub error: Option<String>,
+    /// Command was cancelled because it ran longer than its timeout
+    pub timed_out: bool,
+    /// ID of the command within its session, increasing with each command
+    pub command_id: u64,
+    /// When the command started (Unix timestamp in milliseconds)
+    pub started_at: u64,
+    /// When the command finished (Unix timestamp in milliseconds)
+    pub finished_at: u64,
+    /// Wall-clock run time in milliseconds
+    pub duration_ms: u64,
+    /// Signal that terminated the process (Unix)
+    pub signal: Option<i32>,
+    /// Name of `signal`, e.g. `SIGSEGV`
+    pub signal_name: Option<String>,
+    /// The process dumped core when the signal terminated it
+    pub core_dumped: bool,
+    /// Lines written to stdout (all output, in PTY mode)
+    pub stdout_lines: u64,
+    /// Bytes written to stdout (all output, in PTY mode)
+    pub stdout_bytes: u64,
+    /// Lines written to stderr
+    pub stderr_lines: u64,
+    /// Bytes written to stderr
+    pub stderr_bytes: u64,
+}
+
+/// How a command's standard streams are connected to the backend.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// stdout/stderr captured through pipes (programs see no TTY)
+    #[default]
+    Piped,
+    /// Command attached to a pseudo-terminal (Unix only; falls back to `Piped` elsewhere)
+    Pty,
+    /// Command written to the session's long-lived shell, so `cd`, variables, functions
+    /// and aliases persist between commands (Unix only; falls back to `Piped` elsewhere)
+    Persistent,
+}
+
+/// Terminal window size in character cells.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PtySize {
+    /// Number of rows
+    pub rows: u16,
+    /// Number of columns
+    pub cols: u16,
+}
+
+impl PtySize {
+    /// Create a window size, clamping zero dimensions to 1.
+    #[must_use]
+    pub fn new(rows: u16, cols: u16) -> Self {
+        Self {
+            rows: rows.max(1),
+            cols: cols.max(1),
+        }
+    }
+}
+
+impl Default for PtySize {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
 }
 
 impl CommandResponse {
     /// Create a successful command response with exit code 0.
     #[must_use]
-    pub const fn success() -> Self {
+    pub fn success() -> Self {
         Self {
             success: true,
             exit_code: Some(0),
             error: None,
+            ..Self::default()
         }
     }
 
@@ -44,6 +111,7 @@ impl CommandResponse {
             } else {
                 Some(format!("Command exited with code {exit_code}"))
             },
+            ..Self::default()
         }
     }
 
@@ -60,6 +128,30 @@ impl CommandResponse {
                 exit_code
             },
             error: Some(error.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Create a failed command response for a process killed by `signal`.
+    pub fn killed(signal: i32, signal_name: &str, core_dumped: bool) -> Self {
+        let core = if core_dumped { " (core dumped)" } else { "" };
+        Self {
+            signal: Some(signal),
+            signal_name: Some(signal_name.to_string()),
+            core_dumped,
+            ..Self::failure(format!("Killed by {signal_name}{core}"), None)
+        }
+    }
+
+    /// Mark the response of a command cancelled after running longer than `timeout`,
+    /// keeping how its process ended.
+    #[must_use]
+    pub fn into_timed_out(self, timeout: Duration) -> Self {
+        Self {
+            success: false,
+            error: Some(format!("Command timed out after {timeout:?}")),
+            timed_out: true,
+            ..self
         }
     }
 }
@@ -98,6 +190,27 @@ mod tests {
         assert!(deserialized.cwd.is_none());
     }
 
+    #[test]
+    fn test_execution_mode_serialization() {
+        assert_eq!(
+            serde_json::to_string(&ExecutionMode::Pty).unwrap(),
+            "\"Pty\""
+        );
+        let mode: ExecutionMode = serde_json::from_str("\"Piped\"").unwrap();
+        assert_eq!(mode, ExecutionMode::Piped);
+        assert_eq!(ExecutionMode::default(), ExecutionMode::Piped);
+    }
+
+    #[test]
+    fn test_pty_size_clamps_zero_dimensions() {
+        let size = PtySize::new(0, 0);
+        assert_eq!(size, PtySize { rows: 1, cols: 1 });
+
+        let json = serde_json::to_string(&PtySize::default()).unwrap();
+        assert!(json.contains("\"rows\":24"));
+        assert!(json.contains("\"cols\":80"));
+    }
+
     #[test]
     fn test_command_response_success_serialization() {
         let response = CommandResponse::success();
@@ -155,4 +268,41 @@ mod tests {
             Some("process succeeded but marked as failure".to_string())
         );
     }
+
+    #[test]
+    fn test_command_response_timed_out() {
+        let response = CommandResponse::with_exit_code(130).into_timed_out(Duration::from_secs(5));
+
+        assert!(!response.success);
+        assert!(response.timed_out);
+        assert_eq!(response.exit_code, Some(130));
+        assert_eq!(
+            response.error,
+            Some("Command timed out after 5s".to_string())
+        );
+
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains("\"timed_out\":true"));
+        assert!(!CommandResponse::success().timed_out);
+    }
+
+    #[test]
+    fn test_command_response_killed_by_signal() {
+        let response = CommandResponse::killed(11, "SIGSEGV", true);
+
+        assert!(!response.success);
+        assert_eq!(response.exit_code, None);
+        assert_eq!(response.signal, Some(11));
+        assert!(response.core_dumped);
+        assert_eq!(
+            response.error,
+            Some("Killed by SIGSEGV (core dumped)".to_string())
+        );
+
+        // Fields added later default when absent
+        let old: CommandResponse =
+            serde_json::from_str(r#"{"success":true,"exit_code":0,"error":null}"#).unwrap();
+        assert_eq!(old.signal_name, None);
+        assert_eq!(old.duration_ms, 0);
+    }
 }
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// What a completion candidate refers to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CandidateKind {
+    /// Executable on `PATH` or intercepted builtin
+    Command,
+    Directory,
+    File,
+    /// Environment variable
+    Variable,
+    /// Git branch or other ref
+    Branch,
+    /// Subcommand of the command being completed (`git commit`, `cargo build`)
+    Subcommand,
+}
+
+/// One way to complete the word under the cursor.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompletionCandidate {
+    /// Text replacing the word, quoted or escaped as needed
+    pub replacement: String,
+    /// Short label shown in the candidate list (a file name rather than its path)
+    pub display: String,
+    pub kind: CandidateKind,
+}
+
+/// Candidates for the word under the cursor (returned by `complete`).
+///
+/// Offsets are byte offsets into the input; `input[start..end]` is the word being
+/// completed, and `end` is the cursor.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Completion {
+    pub start: usize,
+    pub end: usize,
+    /// Candidates sorted by label
+    pub candidates: Vec<CompletionCandidate>,
+    /// Longest prefix shared by every candidate's replacement
+    pub common_prefix: String,
+}
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::ShellConfig;
+use crate::process::shell::Shell;
+
+/// Largest scrollback accepted, in lines
+const MAX_SCROLLBACK_LINES: usize = 1_000_000;
+
+/// User settings, read from `~/.cepheus/config.toml`. Missing keys keep their defaults;
+/// unknown keys are rejected so typos do not go unnoticed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub scrollback: ScrollbackConfig,
+    /// Shell of sessions created without one of their own
+    pub shell: ShellConfig,
+    /// Timeout of commands entered in the terminal
+    pub commands: CommandConfig,
+    pub notifications: NotificationConfig,
+    pub logging: LoggingConfig,
+}
+
+/// How much output is kept per session.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrollbackConfig {
+    /// Lines kept (and persisted) by the backend; applies to sessions created afterwards
+    pub lines: usize,
+    /// Lines kept in the terminal view
+    pub display_lines: usize,
+}
+
+impl Default for ScrollbackConfig {
+    fn default() -> Self {
+        Self {
+            lines: 10_000,
+            display_lines: 100_000,
+        }
+    }
+}
+
+/// How commands entered in the terminal run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandConfig {
+    /// Default timeout of every session; 0 lets commands run indefinitely.
+    /// `set_command_timeout` overrides it for a session until the value changes.
+    pub timeout_ms: u64,
+}
+
+impl CommandConfig {
+    /// The configured timeout, `None` if disabled
+    pub fn timeout(&self) -> Option<Duration> {
+        Some(self.timeout_ms)
+            .filter(|&ms| ms > 0)
+            .map(Duration::from_millis)
+    }
+}
+
+/// Transient notifications shown above the input.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    /// How long a notification stays visible; 0 keeps it until the next one
+    pub timeout_ms: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 3000 }
+    }
+}
+
+/// Log files under `~/.cepheus`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Daily log files kept; applied at startup
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        // Roughly two weeks of daily logs
+        Self { max_files: 14 }
+    }
+}
+
+impl Config {
+    /// Parse and validate the contents of a config file
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|e| {
+            e.span().map_or_else(
+                || e.message().to_string(),
+                |span| {
+                    let line = text[..span.start].matches('\n').count() + 1;
+                    format!("line {line}: {}", e.message())
+                },
+            )
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the settings can be applied, describing every problem found
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        for (key, lines) in [
+            ("scrollback.lines", self.scrollback.lines),
+            ("scrollback.display_lines", self.scrollback.display_lines),
+        ] {
+            if !(1..=MAX_SCROLLBACK_LINES).contains(&lines) {
+                problems.push(format!(
+                    "{key} must be between 1 and {MAX_SCROLLBACK_LINES}, got {lines}"
+                ));
+            }
+        }
+        if let Some(program) = &self.shell.program {
+            if Shell::resolve(&self.shell).locate().is_none() {
+                problems.push(format!("shell.program `{program}` was not found"));
+            }
+        }
+        if self.logging.max_files == 0 {
+            problems.push("logging.max_files must be at least 1".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+}
+
+/// The settings in effect, and why the config file could not be applied, if it could
+/// not (returned by `get_config`, payload of the `config-changed` event).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConfigStatus {
+    pub config: Config,
+    /// Config file location; `None` without a home directory
+    pub path: Option<String>,
+    /// Problem with the file; the previous settings stay in effect
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_file_uses_defaults() {
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_partial_file_keeps_other_defaults() {
+        let config = Config::from_toml(
+            "[scrollback]\nlines = 500\n\n[shell]\nlogin = true\n\n[commands]\ntimeout_ms = 60000\n\n\
+             [notifications]\ntimeout_ms = 0\n",
+        )
+        .unwrap();
+        assert_eq!(config.scrollback.lines, 500);
+        assert_eq!(config.scrollback.display_lines, 100_000);
+        assert!(config.shell.login);
+        assert_eq!(config.shell.program, None);
+        assert_eq!(config.commands.timeout_ms, 60_000);
+        assert_eq!(config.notifications.timeout_ms, 0);
+        assert_eq!(config.logging, LoggingConfig::default());
+    }
+
+    #[test]
+    fn test_unknown_keys_and_bad_types_are_rejected() {
+        let error = Config::from_toml("[scrollback]\nline = 5\n").unwrap_err();
+        assert!(error.starts_with("line 2: unknown field `line`"), "{error}");
+
+        let error = Config::from_toml("[scrollback]\nlines = \"many\"\n").unwrap_err();
+        assert!(error.contains("invalid type"), "{error}");
+    }
+
+    #[test]
+    fn test_validation_reports_every_problem() {
+        let error =
+            Config::from_toml("[scrollback]\nlines = 0\n\n[logging]\nmax_files = 0\n").unwrap_err();
+        assert_eq!(
+            error,
+            "scrollback.lines must be between 1 and 1000000, got 0; \
+             logging.max_files must be at least 1"
+        );
+
+        let error =
+            Config::from_toml("[shell]\nprogram = \"cepheus-no-such-shell\"\n").unwrap_err();
+        assert_eq!(error, "shell.program `cepheus-no-such-shell` was not found");
+    }
+}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+/// A command line entered in any session (returned by `get_command_history` and
+/// `search_command_history`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandHistoryEntry {
+    /// The command line as typed
+    pub command: String,
+    /// Directory the command ran in
+    pub cwd: String,
+    /// Exit code of its last run (`None` if it was killed by a signal or failed to start)
+    pub exit_code: Option<i32>,
+    /// When it last ran (Unix timestamp in milliseconds)
+    pub timestamp: u64,
+}
+
+/// Outcome of the last run a command history search is restricted to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExitStatusFilter {
+    /// Exited with code 0
+    Succeeded,
+    /// Exited with a non-zero code, was killed, or failed to start
+    Failed,
+}
+
+impl ExitStatusFilter {
+    /// Check if a run with `exit_code` passes the filter
+    pub fn matches(self, exit_code: Option<i32>) -> bool {
+        match self {
+            Self::Succeeded => exit_code == Some(0),
+            Self::Failed => exit_code != Some(0),
+        }
+    }
+}
+
+/// A search of the command history.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryQuery {
+    /// Text to find, ignoring case: contained in the command, or failing that, its
+    /// characters appearing in order (`gco` finds `git checkout`). Empty matches all.
+    pub text: String,
+    /// Only commands last run in this directory
+    pub cwd: Option<String>,
+    /// Only commands whose last run had this outcome
+    pub status: Option<ExitStatusFilter>,
+    /// Return at most this many matches
+    pub limit: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_round_trip() {
+        let entry = CommandHistoryEntry {
+            command: "cargo test".to_string(),
+            cwd: "/src".to_string(),
+            exit_code: Some(0),
+            timestamp: 42,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            json,
+            r#"{"command":"cargo test","cwd":"/src","exit_code":0,"timestamp":42}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<CommandHistoryEntry>(&json).unwrap(),
+            entry
+        );
+    }
+
+    #[test]
+    fn test_exit_status_filter() {
+        assert!(ExitStatusFilter::Succeeded.matches(Some(0)));
+        assert!(!ExitStatusFilter::Succeeded.matches(None));
+        assert!(ExitStatusFilter::Failed.matches(Some(2)));
+        assert!(ExitStatusFilter::Failed.matches(None));
+        assert!(!ExitStatusFilter::Failed.matches(Some(0)));
+    }
+}
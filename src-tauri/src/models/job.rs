@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+/// State of a job in a session's job table.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Running (in the foreground or the background)
+    Running,
+    /// Suspended with `SIGTSTP` (Ctrl+Z) until continued by `fg` or `bg`
+    Stopped,
+    /// Exited with `exit_code`, or was killed by `signal` (Unix)
+    Done {
+        exit_code: Option<i32>,
+        #[serde(default)]
+        signal: Option<i32>,
+        #[serde(default)]
+        core_dumped: bool,
+    },
+}
+
+impl JobStatus {
+    /// Status of a job whose process ended with `status`
+    pub fn exited(status: std::process::ExitStatus) -> Self {
+        #[cfg(unix)]
+        let (signal, core_dumped) = {
+            use std::os::unix::process::ExitStatusExt;
+            (status.signal(), status.core_dumped())
+        };
+        #[cfg(not(unix))]
+        let (signal, core_dumped) = (None, false);
+
+        Self::Done {
+            exit_code: status.code(),
+            signal,
+            core_dumped,
+        }
+    }
+}
+
+/// Summary of a job for `jobs` and the frontend (backend → frontend IPC).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JobInfo {
+    /// Job number, as used by `fg %1`
+    pub id: u32,
+    /// Command line the job runs
+    pub command: String,
+    /// Process group leader
+    pub pid: Option<u32>,
+    pub status: JobStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_info_serialization() {
+        let job = JobInfo {
+            id: 2,
+            command: "npm run watch".to_string(),
+            pid: Some(4321),
+            status: JobStatus::Done {
+                exit_code: Some(0),
+                signal: None,
+                core_dumped: false,
+            },
+        };
+
+        let json = serde_json::to_string(&job).unwrap();
+        assert!(json.contains("\"status\":{\"Done\":{\"exit_code\":0,"));
+
+        let deserialized: JobInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(job, deserialized);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exited_records_signal() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // Raw wait statuses: exit code in the high byte, signal in the low bits (0x8b is
+        // SIGSEGV with the core dump flag)
+        assert_eq!(
+            JobStatus::exited(std::process::ExitStatus::from_raw(2 << 8)),
+            JobStatus::Done {
+                exit_code: Some(2),
+                signal: None,
+                core_dumped: false,
+            }
+        );
+        assert_eq!(
+            JobStatus::exited(std::process::ExitStatus::from_raw(0x8b)),
+            JobStatus::Done {
+                exit_code: None,
+                signal: Some(11),
+                core_dumped: true,
+            }
+        );
+    }
+}
@@ -1,5 +1,26 @@
 pub mod command;
+pub mod completion;
+pub mod config;
+pub mod history;
+pub mod job;
 pub mod output;
+pub mod screen;
+pub mod session;
+pub mod shell;
 
-pub use command::{CommandRequest, CommandResponse};
-pub use output::{NotificationLevel, OutputLine};
+pub use command::{CommandRequest, CommandResponse, ExecutionMode, PtySize};
+pub use completion::{CandidateKind, Completion, CompletionCandidate};
+pub use config::{
+    CommandConfig, Config, ConfigStatus, LoggingConfig, NotificationConfig, ScrollbackConfig,
+};
+pub use history::{CommandHistoryEntry, ExitStatusFilter, HistoryQuery};
+pub use job::{JobInfo, JobStatus};
+pub use output::{
+    Color, HistoryLine, NotificationLevel, OutputLine, OutputStream, PtyOutput, StyledSpan,
+    TextStyle,
+};
+pub use screen::{ScreenLine, ScreenUpdate, SessionScreen};
+pub use session::{
+    CommandFinished, CommandStarted, CwdChanged, LineUpdate, OutputBatch, SessionInfo,
+};
+pub use shell::ShellConfig;
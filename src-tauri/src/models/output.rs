@@ -13,6 +13,23 @@ pub enum OutputLine {
     Stdout { text: String, timestamp: u64 },
     /// Standard error from command
     Stderr { text: String, timestamp: u64 },
+    /// Command output containing SGR styling (colors, bold, ...).
+    /// `text` is the plain text; `spans` mark its styled byte ranges.
+    Styled {
+        stream: OutputStream,
+        text: String,
+        spans: Vec<StyledSpan>,
+        timestamp: u64,
+    },
+    /// Output of a command running as a job (started with `&` or suspended), tagged
+    /// with the job number. `spans` is empty for unstyled text.
+    JobOutput {
+        job_id: u32,
+        stream: OutputStream,
+        text: String,
+        spans: Vec<StyledSpan>,
+        timestamp: u64,
+    },
     /// System notification (e.g., "Shell restarted", "Output truncated...")
     Notification {
         message: String,
@@ -28,6 +45,8 @@ impl OutputLine {
             Self::Command { timestamp, .. }
             | Self::Stdout { timestamp, .. }
             | Self::Stderr { timestamp, .. }
+            | Self::Styled { timestamp, .. }
+            | Self::JobOutput { timestamp, .. }
             | Self::Notification { timestamp, .. } => *timestamp,
         }
     }
@@ -35,12 +54,102 @@ impl OutputLine {
     /// Get the text content of this output line
     pub fn text(&self) -> &str {
         match self {
-            Self::Command { text, .. } | Self::Stdout { text, .. } | Self::Stderr { text, .. } => {
-                text
-            }
+            Self::Command { text, .. }
+            | Self::Stdout { text, .. }
+            | Self::Stderr { text, .. }
+            | Self::Styled { text, .. }
+            | Self::JobOutput { text, .. } => text,
             Self::Notification { message, .. } => message,
         }
     }
+
+    /// Tag command output with the job it came from; other lines are returned unchanged.
+    #[must_use]
+    pub fn into_job_output(self, job_id: u32) -> Self {
+        let (stream, text, spans, timestamp) = match self {
+            Self::Stdout { text, timestamp } => (OutputStream::Stdout, text, Vec::new(), timestamp),
+            Self::Stderr { text, timestamp } => (OutputStream::Stderr, text, Vec::new(), timestamp),
+            Self::Styled {
+                stream,
+                text,
+                spans,
+                timestamp,
+            } => (stream, text, spans, timestamp),
+            line => return line,
+        };
+        Self::JobOutput {
+            job_id,
+            stream,
+            text,
+            spans,
+            timestamp,
+        }
+    }
+}
+
+/// A line of a session's history with its sequence number (returned by `get_history`,
+/// `get_history_since` and `get_history_range`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HistoryLine {
+    /// Number of lines added to the session before this one; it increases with every
+    /// line and stays the same when a partial line is rewritten
+    pub seq: u64,
+    pub line: OutputLine,
+}
+
+/// Stream a line of command output was written to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Terminal color from an SGR sequence.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Color {
+    /// Palette index: 0-7 standard, 8-15 bright, 16-255 the xterm 256-color cube and greys
+    Indexed(u8),
+    /// 24-bit color
+    Rgb(u8, u8, u8),
+}
+
+/// Text attributes set by SGR sequences.
+// Attributes are independent on/off flags, as in the SGR protocol itself
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TextStyle {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+impl TextStyle {
+    /// True if no attribute differs from the terminal default
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A styled byte range `start..end` of an output line's text.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StyledSpan {
+    pub start: usize,
+    pub end: usize,
+    pub style: TextStyle,
+}
+
+/// Raw bytes read from a pseudo-terminal (payload of the `pty-output` event).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PtyOutput {
+    /// Session the PTY belongs to
+    pub session_id: String,
+    /// Unmodified bytes, including escape sequences
+    pub data: Vec<u8>,
 }
 
 /// Notification severity level
@@ -117,6 +226,36 @@ mod tests {
         assert_eq!(line, deserialized);
     }
 
+    #[test]
+    fn test_into_job_output_tags_command_output() {
+        let line = OutputLine::Stderr {
+            text: "warning".to_string(),
+            timestamp: 42,
+        }
+        .into_job_output(3);
+        assert_eq!(
+            line,
+            OutputLine::JobOutput {
+                job_id: 3,
+                stream: OutputStream::Stderr,
+                text: "warning".to_string(),
+                spans: Vec::new(),
+                timestamp: 42,
+            }
+        );
+
+        let json = serde_json::to_string(&line).unwrap();
+        assert!(json.contains("\"type\":\"JobOutput\""));
+        assert!(json.contains("\"job_id\":3"));
+
+        // Only command output is tagged
+        let command = OutputLine::Command {
+            text: "make".to_string(),
+            timestamp: 42,
+        };
+        assert_eq!(command.clone().into_job_output(3), command);
+    }
+
     #[test]
     fn test_output_line_timestamp_accessor() {
         let cmd = OutputLine::Command {
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use super::StyledSpan;
+
+/// One row of the emulated screen.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScreenLine {
+    /// Row index, 0 at the top
+    pub row: u16,
+    /// Row content without trailing blanks
+    pub text: String,
+    /// Styled byte ranges of `text`
+    pub spans: Vec<StyledSpan>,
+}
+
+/// Changes to the emulated screen since the previous update.
+// Cursor and mode flags mirror independent terminal modes
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScreenUpdate {
+    pub rows: u16,
+    pub cols: u16,
+    /// True if `lines` holds every row (after a resize or screen switch)
+    pub full: bool,
+    /// Rows that changed
+    pub lines: Vec<ScreenLine>,
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+    pub cursor_visible: bool,
+    /// Is the alternate screen (used by full-screen programs) shown?
+    pub alternate: bool,
+    /// Should arrow keys send application sequences (`ESC O A`) instead of `ESC [ A`?
+    pub application_cursor: bool,
+}
+
+/// Screen update tagged with its session (payload of the `screen-update` event).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionScreen {
+    pub session_id: String,
+    pub update: ScreenUpdate,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_screen_serialization() {
+        let event = SessionScreen {
+            session_id: "session-1".to_string(),
+            update: ScreenUpdate {
+                rows: 2,
+                cols: 10,
+                full: true,
+                lines: vec![ScreenLine {
+                    row: 0,
+                    text: "top".to_string(),
+                    spans: Vec::new(),
+                }],
+                cursor_row: 1,
+                cursor_col: 0,
+                cursor_visible: true,
+                alternate: true,
+                application_cursor: false,
+            },
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains("\"session_id\":\"session-1\""));
+        assert!(json.contains("\"alternate\":true"));
+
+        let deserialized: SessionScreen = serde_json::from_str(&json).unwrap();
+        assert_eq!(event, deserialized);
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use super::OutputLine;
+
+/// Summary of a terminal session (returned by `create_session` / `list_sessions`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// Session identifier passed to every session-scoped command
+    pub id: String,
+    /// Current working directory
+    pub cwd: String,
+    /// Is a command currently running in the session?
+    pub is_busy: bool,
+}
+
+/// Output of a session gathered over a short window, in order (payload of the
+/// `output-batch` event).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OutputBatch {
+    pub session_id: String,
+    pub lines: Vec<LineUpdate>,
+}
+
+/// A line of output added to a session's history, or a new version of the partial line
+/// of its stream.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LineUpdate {
+    /// Sequence number of the line in the session's history (see `HistoryLine`)
+    pub seq: u64,
+    pub line: OutputLine,
+    /// The line is not terminated yet; later updates for its stream replace it
+    #[serde(default)]
+    pub partial: bool,
+    /// The line replaces the partial line last sent for its stream
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// New working directory of a session (payload of the `cwd-changed` event).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CwdChanged {
+    pub session_id: String,
+    pub cwd: String,
+}
+
+/// A command began running in a session (payload of the `command-started` event).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandStarted {
+    pub session_id: String,
+    /// ID of the command within its session, as reported in its `CommandResponse`
+    pub command_id: u64,
+    pub command: String,
+    /// Directory the command runs in
+    pub cwd: String,
+    /// Unix timestamp in milliseconds
+    pub started_at: u64,
+}
+
+/// A command finished, failed to run, or was suspended (payload of the `command-finished`
+/// event). The session accepts a new command once this is emitted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandFinished {
+    pub session_id: String,
+    pub command_id: u64,
+    /// Working directory after the command, which may have changed it
+    pub cwd: String,
+    /// Last process the command spawned; `None` if it only ran builtins
+    pub pid: Option<u32>,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the process (Unix)
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+    /// Why the command failed, if it did
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Unix timestamp in milliseconds
+    pub finished_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_batch_serialization() {
+        let event = OutputBatch {
+            session_id: "session-2".to_string(),
+            lines: vec![LineUpdate {
+                seq: 4,
+                line: OutputLine::Stdout {
+                    text: "ready".to_string(),
+                    timestamp: 1,
+                },
+                partial: false,
+                replace: false,
+            }],
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains("\"session_id\":\"session-2\""));
+        assert!(json.contains("\"type\":\"Stdout\""));
+        assert!(json.contains("\"seq\":4"));
+
+        let deserialized: OutputBatch = serde_json::from_str(&json).unwrap();
+        assert_eq!(event, deserialized);
+    }
+
+    #[test]
+    fn test_command_finished_serialization() {
+        let event = CommandFinished {
+            session_id: "session-1".to_string(),
+            command_id: 3,
+            cwd: "/tmp".to_string(),
+            pid: Some(4321),
+            success: false,
+            exit_code: None,
+            signal: Some(15),
+            timed_out: true,
+            error: Some("Command timed out after 1s".to_string()),
+            duration_ms: 1000,
+            finished_at: 2000,
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains("\"command_id\":3"));
+        assert!(json.contains("\"pid\":4321"));
+        assert!(json.contains("\"timed_out\":true"));
+
+        let deserialized: CommandFinished = serde_json::from_str(&json).unwrap();
+        assert_eq!(event, deserialized);
+    }
+}
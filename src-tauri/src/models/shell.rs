@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Which shell runs a session's commands, and how it is started.
+///
+/// The application default applies to sessions created without one of their own
+/// (`create_session`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShellConfig {
+    /// Shell name or path (`zsh`, `/usr/local/bin/fish`); `None` uses `$SHELL`, falling
+    /// back to `sh` (`cmd` on Windows)
+    pub program: Option<String>,
+    /// Start it as a login shell (`-l`), reading the login profile
+    pub login: bool,
+    /// Start it as an interactive shell (`-i`), reading the rc file (`.bashrc`,
+    /// `.zshrc`) so aliases and functions defined there are available
+    pub interactive: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let config: ShellConfig = serde_json::from_str(r#"{"program":"zsh"}"#).unwrap();
+        assert_eq!(
+            config,
+            ShellConfig {
+                program: Some("zsh".to_string()),
+                login: false,
+                interactive: false,
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<ShellConfig>("{}").unwrap(),
+            ShellConfig::default()
+        );
+    }
+}
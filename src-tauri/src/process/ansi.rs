@@ -0,0 +1,314 @@
+use crate::models::{Color, OutputLine, OutputStream, StyledSpan, TextStyle};
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+/// 8-bit Control Sequence Introducer
+const C1_CSI: char = '\u{9b}';
+
+/// Turns raw command output into display text, applying SGR styling and dropping every
+/// other escape sequence (cursor movement, titles, ...) and control character except
+/// tab and carriage return.
+///
+/// Styling carries over from one line to the next, as in a terminal, so use one parser
+/// per output stream.
+#[derive(Debug, Clone, Default)]
+pub struct AnsiParser {
+    style: TextStyle,
+}
+
+impl AnsiParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse one line (without its newline) into plain text and styled ranges.
+    pub fn parse_line(&mut self, raw: &str) -> (String, Vec<StyledSpan>) {
+        let mut text = String::with_capacity(raw.len());
+        let mut spans: Vec<StyledSpan> = Vec::new();
+        let mut chars = raw.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                ESC => match chars.next() {
+                    Some('[') => self.control_sequence(&mut chars),
+                    // OSC, DCS, SOS, PM, APC: strings terminated by BEL or ST
+                    Some(']' | 'P' | 'X' | '^' | '_') => skip_string(&mut chars),
+                    // Intermediate bytes (e.g. charset selection `ESC ( B`) then a final byte
+                    Some(' '..='/') => {
+                        while chars.next_if(|c| matches!(c, ' '..='/')).is_some() {}
+                        chars.next();
+                    }
+                    _ => {}
+                },
+                C1_CSI => self.control_sequence(&mut chars),
+                '\t' | '\r' => text.push(c),
+                c if c.is_control() => {}
+                c => {
+                    let start = text.len();
+                    text.push(c);
+                    push_span(&mut spans, start, text.len(), self.style);
+                }
+            }
+        }
+
+        (text, spans)
+    }
+
+    /// Parse one line into an `OutputLine`: `Stdout`/`Stderr` when it has no styling,
+    /// `Styled` otherwise.
+    pub fn output_line(&mut self, stream: OutputStream, raw: &str, timestamp: u64) -> OutputLine {
+        let (text, spans) = self.parse_line(raw);
+        if !spans.is_empty() {
+            return OutputLine::Styled {
+                stream,
+                text,
+                spans,
+                timestamp,
+            };
+        }
+        match stream {
+            OutputStream::Stdout => OutputLine::Stdout { text, timestamp },
+            OutputStream::Stderr => OutputLine::Stderr { text, timestamp },
+        }
+    }
+
+    /// Consume a CSI sequence (after `ESC [`), applying it if it is SGR.
+    fn control_sequence(&mut self, chars: &mut impl Iterator<Item = char>) {
+        let mut params = String::new();
+        for c in chars.by_ref() {
+            match c {
+                '@'..='~' => {
+                    // Private sequences (`ESC [ ? ...`) are never SGR
+                    if c == 'm' && !params.starts_with(['<', '=', '>', '?']) {
+                        apply_sgr(&mut self.style, &params);
+                    }
+                    return;
+                }
+                _ => params.push(c),
+            }
+        }
+    }
+}
+
+/// Apply SGR parameters (`ESC [ <params> m`) to `style`.
+pub fn apply_sgr(style: &mut TextStyle, params: &str) {
+    let mut params = params.split(';');
+    while let Some(param) = params.next() {
+        if let Some((code, rest)) = param.split_once(':') {
+            apply_subparams(style, code, rest);
+            continue;
+        }
+
+        let Ok(code) = (if param.is_empty() {
+            Ok(0)
+        } else {
+            param.parse::<u16>()
+        }) else {
+            continue;
+        };
+        match code {
+            0 => *style = TextStyle::default(),
+            1 => style.bold = true,
+            2 => style.dim = true,
+            3 => style.italic = true,
+            4 | 21 => style.underline = true,
+            7 => style.inverse = true,
+            22 => {
+                style.bold = false;
+                style.dim = false;
+            }
+            23 => style.italic = false,
+            24 => style.underline = false,
+            27 => style.inverse = false,
+            30..=37 => style.fg = Some(Color::Indexed(palette(code - 30))),
+            38 => style.fg = extended_color(&mut params),
+            39 => style.fg = None,
+            40..=47 => style.bg = Some(Color::Indexed(palette(code - 40))),
+            48 => style.bg = extended_color(&mut params),
+            49 => style.bg = None,
+            90..=97 => style.fg = Some(Color::Indexed(palette(code - 90 + 8))),
+            100..=107 => style.bg = Some(Color::Indexed(palette(code - 100 + 8))),
+            _ => {}
+        }
+    }
+}
+
+/// Apply a parameter with colon sub-parameters: `38:5:n`, `38:2:r:g:b`,
+/// `38:2::r:g:b` (likewise `48`), or an underline style `4:n`.
+fn apply_subparams(style: &mut TextStyle, code: &str, rest: &str) {
+    let mut parts: Vec<&str> = rest.split(':').collect();
+    match code {
+        "38" | "48" => {
+            // `2:<color space>:r:g:b` carries a color space ID, which is ignored
+            if parts.first() == Some(&"2") && parts.len() == 5 {
+                parts.remove(1);
+            }
+            let color = extended_color(&mut parts.into_iter());
+            if code == "38" {
+                style.fg = color;
+            } else {
+                style.bg = color;
+            }
+        }
+        "4" => style.underline = rest != "0",
+        _ => {}
+    }
+}
+
+/// Add `start..end` in `style` to `spans`, extending the last span when it continues it.
+/// Plain text gets no span.
+pub fn push_span(spans: &mut Vec<StyledSpan>, start: usize, end: usize, style: TextStyle) {
+    if style.is_plain() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(span) if span.end == start && span.style == style => span.end = end,
+        _ => spans.push(StyledSpan { start, end, style }),
+    }
+}
+
+/// Palette index for a basic color offset (always below 16).
+fn palette(index: u16) -> u8 {
+    u8::try_from(index).unwrap_or_default()
+}
+
+/// Read the rest of an extended color (`5;n` or `2;r;g;b`) from the parameters.
+fn extended_color<'a>(params: &mut impl Iterator<Item = &'a str>) -> Option<Color> {
+    let mut next = || params.next().and_then(|p| p.parse::<u8>().ok());
+    match next()? {
+        5 => next().map(Color::Indexed),
+        2 => Some(Color::Rgb(next()?, next()?, next()?)),
+        _ => None,
+    }
+}
+
+/// Skip a string sequence up to its terminator (BEL or `ESC \`).
+fn skip_string(chars: &mut impl Iterator<Item = char>) {
+    while let Some(c) = chars.next() {
+        match c {
+            BEL => return,
+            ESC => {
+                chars.next();
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: usize, end: usize, style: TextStyle) -> StyledSpan {
+        StyledSpan { start, end, style }
+    }
+
+    #[test]
+    fn test_plain_text_passes_through() {
+        let mut parser = AnsiParser::new();
+        let (text, spans) = parser.parse_line("hello\tworld\r");
+        assert_eq!(text, "hello\tworld\r");
+        assert!(spans.is_empty());
+    }
+
+    #[test]
+    fn test_basic_colors_and_reset() {
+        let mut parser = AnsiParser::new();
+        let (text, spans) = parser.parse_line("\x1b[31merror\x1b[0m: \x1b[1;92mok\x1b[m");
+        assert_eq!(text, "error: ok");
+        assert_eq!(
+            spans,
+            vec![
+                span(
+                    0,
+                    5,
+                    TextStyle {
+                        fg: Some(Color::Indexed(1)),
+                        ..TextStyle::default()
+                    }
+                ),
+                span(
+                    7,
+                    9,
+                    TextStyle {
+                        fg: Some(Color::Indexed(10)),
+                        bold: true,
+                        ..TextStyle::default()
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_256_and_truecolor() {
+        let mut parser = AnsiParser::new();
+        let (_, spans) = parser.parse_line("\x1b[38;5;208;48;2;10;20;30mx");
+        assert_eq!(spans[0].style.fg, Some(Color::Indexed(208)));
+        assert_eq!(spans[0].style.bg, Some(Color::Rgb(10, 20, 30)));
+
+        let (_, spans) = parser.parse_line("\x1b[0;38:2::1:2:3;48:5:17my");
+        assert_eq!(spans[0].style.fg, Some(Color::Rgb(1, 2, 3)));
+        assert_eq!(spans[0].style.bg, Some(Color::Indexed(17)));
+    }
+
+    #[test]
+    fn test_attributes_toggle() {
+        let mut parser = AnsiParser::new();
+        let (_, spans) = parser.parse_line("\x1b[2;3;4;7ma\x1b[22;23;24;27mb");
+        assert_eq!(
+            spans,
+            vec![span(
+                0,
+                1,
+                TextStyle {
+                    dim: true,
+                    italic: true,
+                    underline: true,
+                    inverse: true,
+                    ..TextStyle::default()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_style_carries_over_lines() {
+        let mut parser = AnsiParser::new();
+        parser.parse_line("\x1b[32mfirst");
+        let (_, spans) = parser.parse_line("second\x1b[39m third");
+        assert_eq!(spans.len(), 1);
+        assert_eq!((spans[0].start, spans[0].end), (0, 6));
+    }
+
+    #[test]
+    fn test_other_sequences_are_stripped() {
+        let mut parser = AnsiParser::new();
+        let (text, spans) =
+            parser.parse_line("\x1b]0;title\x07\x1b[2K\x1b[?25l\x1b(Bdone\x1b]8;;url\x1b\\\x07");
+        assert_eq!(text, "done");
+        assert!(spans.is_empty());
+    }
+
+    #[test]
+    fn test_multibyte_offsets() {
+        let mut parser = AnsiParser::new();
+        let (text, spans) = parser.parse_line("é\x1b[1m✓\x1b[0m");
+        assert_eq!(&text[spans[0].start..spans[0].end], "✓");
+    }
+
+    #[test]
+    fn test_output_line_variants() {
+        let mut parser = AnsiParser::new();
+        assert!(matches!(
+            parser.output_line(OutputStream::Stderr, "plain", 1),
+            OutputLine::Stderr { .. }
+        ));
+        let line = parser.output_line(OutputStream::Stdout, "\x1b[1mbold\x1b[0m", 2);
+        assert!(matches!(
+            &line,
+            OutputLine::Styled { stream: OutputStream::Stdout, text, .. } if text == "bold"
+        ));
+    }
+}
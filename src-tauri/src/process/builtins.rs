@@ -0,0 +1,1043 @@
+use std::path::Path;
+
+use super::signals::{self, JobSignal};
+use crate::models::JobStatus;
+use crate::state::{SessionEnv, ShellState};
+
+/// Builtins handled by the backend instead of a child shell.
+///
+/// A `cd` or `export` run by `sh -c` only affects that short-lived shell, so these
+/// are intercepted and applied to the session's [`ShellState`]. Job control builtins
+/// work on the session's job table, which a child shell knows nothing about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinKind {
+    Cd,
+    Pushd,
+    Popd,
+    Dirs,
+    Export,
+    Unset,
+    Jobs,
+    Fg,
+    Bg,
+}
+
+impl BuiltinKind {
+    /// Names of the intercepted builtins
+    pub const NAMES: [&'static str; 9] = [
+        "cd", "pushd", "popd", "dirs", "export", "unset", "jobs", "fg", "bg",
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cd" => Some(Self::Cd),
+            "pushd" => Some(Self::Pushd),
+            "popd" => Some(Self::Popd),
+            "dirs" => Some(Self::Dirs),
+            "export" => Some(Self::Export),
+            "unset" => Some(Self::Unset),
+            "jobs" => Some(Self::Jobs),
+            "fg" => Some(Self::Fg),
+            "bg" => Some(Self::Bg),
+            _ => None,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Cd => "cd",
+            Self::Pushd => "pushd",
+            Self::Popd => "popd",
+            Self::Dirs => "dirs",
+            Self::Export => "export",
+            Self::Unset => "unset",
+            Self::Jobs => "jobs",
+            Self::Fg => "fg",
+            Self::Bg => "bg",
+        }
+    }
+}
+
+/// A builtin invocation with its arguments already expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Builtin {
+    pub kind: BuiltinKind,
+    pub args: Vec<String>,
+}
+
+/// A command line split around its intercepted builtins.
+///
+/// `cd src && make && cd -` becomes `leading: [cd src]`, `external: make`,
+/// `trailing: [cd -]`. Builtins between two external commands stay inside the
+/// external part, where the child shell runs them as usual.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandLine {
+    /// Builtins to run before the external command
+    pub leading: Vec<Builtin>,
+    /// Remaining command text for the child shell (if any)
+    pub external: Option<String>,
+    /// Builtins to run after the external command succeeds
+    pub trailing: Vec<Builtin>,
+}
+
+impl CommandLine {
+    fn external_only(command: &str) -> Self {
+        Self {
+            leading: Vec::new(),
+            external: Some(command.to_string()),
+            trailing: Vec::new(),
+        }
+    }
+}
+
+/// Result of running a builtin.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuiltinOutput {
+    pub stdout: Vec<String>,
+    pub stderr: Vec<String>,
+    pub exit_code: i32,
+    /// New working directory, if the builtin changed it
+    pub new_cwd: Option<String>,
+    /// Job moved to the foreground, which the caller should wait for
+    pub foreground_job: Option<u32>,
+}
+
+impl BuiltinOutput {
+    fn error(kind: BuiltinKind, message: &str) -> Self {
+        Self {
+            stderr: vec![format!("{}: {message}", kind.name())],
+            exit_code: 1,
+            ..Self::default()
+        }
+    }
+}
+
+/// Split `command` into leading builtins, an external part, and trailing builtins.
+///
+/// Only `&&` chains are split. Segments using other shell syntax (pipes, `;`,
+/// redirections, command substitution, globs, ...) are left to the shell, as is any
+/// line that does not tokenize cleanly. `lookup_var` resolves `$VAR` / `${VAR}`.
+pub fn parse<F>(command: &str, lookup_var: F) -> CommandLine
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let Some(segments) = split_and_chain(command) else {
+        return CommandLine::external_only(command);
+    };
+    if segments
+        .iter()
+        .any(|&(start, end)| command[start..end].trim().is_empty())
+    {
+        return CommandLine::external_only(command);
+    }
+
+    let builtins: Vec<Option<Builtin>> = segments
+        .iter()
+        .map(|&(start, end)| parse_builtin(&command[start..end], &lookup_var))
+        .collect();
+
+    let leading_count = builtins.iter().take_while(|b| b.is_some()).count();
+    if leading_count == builtins.len() {
+        return CommandLine {
+            leading: builtins.into_iter().flatten().collect(),
+            external: None,
+            trailing: Vec::new(),
+        };
+    }
+    let trailing_count = builtins.iter().rev().take_while(|b| b.is_some()).count();
+    let external_end = segments.len() - trailing_count;
+
+    let external_start = segments[leading_count].0;
+    let external_stop = segments[external_end - 1].1;
+
+    let mut builtins = builtins.into_iter();
+    let leading = builtins.by_ref().take(leading_count).flatten().collect();
+    let trailing = builtins
+        .skip(external_end - leading_count)
+        .flatten()
+        .collect();
+
+    CommandLine {
+        leading,
+        external: Some(command[external_start..external_stop].trim().to_string()),
+        trailing,
+    }
+}
+
+/// Byte ranges of the `&&`-separated segments of `command`, ignoring quoted `&&`.
+/// Returns `None` for unbalanced quotes.
+fn split_and_chain(command: &str) -> Option<Vec<(usize, usize)>> {
+    let bytes = command.as_bytes();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if !in_single => i += 1,
+            b'\'' if !in_double => in_single = !in_single,
+            b'"' if !in_single => in_double = !in_double,
+            b'&' if !in_single && !in_double && bytes.get(i + 1) == Some(&b'&') => {
+                segments.push((start, i));
+                i += 1;
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if in_single || in_double {
+        return None;
+    }
+    segments.push((start, command.len()));
+    Some(segments)
+}
+
+/// A command line ending in `&`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackgroundLine<'a> {
+    /// Commands before the last `;`, run in the foreground first
+    pub foreground: Option<&'a str>,
+    /// Command after the last `;`, started as a background job
+    pub background: &'a str,
+}
+
+/// Split a command line ending in `&` into the part run in the foreground and the
+/// background job, or `None` if `command` runs in the foreground.
+///
+/// As in a shell, `&` applies to the command after the last `;` (`make; ./server &`
+/// only backgrounds `./server`), while `a && b &` backgrounds the whole list; `&&`, `>&`
+/// and `|&` are not background markers.
+pub fn split_background(command: &str) -> Option<BackgroundLine<'_>> {
+    let trimmed = command.trim_end();
+    let bytes = trimmed.as_bytes();
+    if bytes.last() != Some(&b'&') {
+        return None;
+    }
+
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut last_separator = None;
+    let mut i = 0;
+    while i < bytes.len() - 1 {
+        match bytes[i] {
+            b'\\' if !in_single => i += 1,
+            b'\'' if !in_double => in_single = !in_single,
+            b'"' if !in_single => in_double = !in_double,
+            b';' if !in_single && !in_double => last_separator = Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    // The `&` was escaped, quoted, or is part of an operator
+    if in_single || in_double || i >= bytes.len() {
+        return None;
+    }
+    if let Some(b'&' | b'>' | b'<' | b'|') = bytes.len().checked_sub(2).map(|i| bytes[i]) {
+        return None;
+    }
+
+    let start = last_separator.map_or(0, |separator| separator + 1);
+    let background = trimmed[start..trimmed.len() - 1].trim();
+    let foreground = last_separator
+        .map(|separator| trimmed[..separator].trim())
+        .filter(|foreground| !foreground.is_empty());
+    (!background.is_empty()).then_some(BackgroundLine {
+        foreground,
+        background,
+    })
+}
+
+/// Parse one segment as a builtin invocation, or `None` if it is anything else.
+fn parse_builtin<F>(segment: &str, lookup_var: &F) -> Option<Builtin>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut words = split_words(segment, lookup_var)?;
+    if words.is_empty() {
+        return None;
+    }
+    let kind = BuiltinKind::from_name(&words.remove(0))?;
+    Some(Builtin { kind, args: words })
+}
+
+/// Split a segment into words, applying quote removal, `~` and `$VAR` expansion.
+/// Returns `None` if the segment uses syntax that only a real shell can evaluate.
+fn split_words<F>(segment: &str, lookup_var: &F) -> Option<Vec<String>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = segment.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => current.push(c),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('$' | '"' | '\\' | '`') => current.push(c),
+                            c => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                        },
+                        '$' => current.push_str(&expand_var(&mut chars, lookup_var)?),
+                        '`' => return None,
+                        c => current.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                current.push(chars.next()?);
+            }
+            '$' => {
+                in_word = true;
+                current.push_str(&expand_var(&mut chars, lookup_var)?);
+            }
+            '~' if !in_word && chars.peek().is_none_or(|&c| c == '/' || c.is_whitespace()) => {
+                in_word = true;
+                current.push_str(&home_dir()?);
+            }
+            ';' | '|' | '&' | '<' | '>' | '(' | ')' | '`' | '*' | '?' | '[' | '{' | '}' | '#' => {
+                return None;
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        words.push(current);
+    }
+    Some(words)
+}
+
+/// Expand the variable following a `$`. Only plain names are supported; special
+/// parameters and `$(...)` make the segment non-interceptable.
+fn expand_var<I, F>(chars: &mut std::iter::Peekable<I>, lookup_var: &F) -> Option<String>
+where
+    I: Iterator<Item = char>,
+    F: Fn(&str) -> Option<String>,
+{
+    let braced = chars.next_if_eq(&'{').is_some();
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+        name.push(c);
+    }
+    if braced && chars.next() != Some('}') {
+        return None;
+    }
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(lookup_var(&name).unwrap_or_default())
+}
+
+fn home_dir() -> Option<String> {
+    dirs_next::home_dir().map(|home| home.to_string_lossy().to_string())
+}
+
+/// Resolve `target` against `cwd` to a canonical directory path.
+fn resolve_dir(kind: BuiltinKind, cwd: &str, target: &str) -> Result<String, BuiltinOutput> {
+    let path = Path::new(cwd).join(target);
+    match path.canonicalize() {
+        Ok(resolved) if resolved.is_dir() => Ok(resolved.to_string_lossy().to_string()),
+        Ok(_) => Err(BuiltinOutput::error(
+            kind,
+            &format!("{target}: Not a directory"),
+        )),
+        Err(_) => Err(BuiltinOutput::error(
+            kind,
+            &format!("{target}: No such file or directory"),
+        )),
+    }
+}
+
+/// Abbreviate the home directory as `~` for display.
+fn tilde_path(path: &str) -> String {
+    match home_dir() {
+        Some(home) if path == home => "~".to_string(),
+        Some(home) => path
+            .strip_prefix(&home)
+            .filter(|rest| rest.starts_with('/'))
+            .map_or_else(|| path.to_string(), |rest| format!("~{rest}")),
+        None => path.to_string(),
+    }
+}
+
+/// Format the directory stack (current directory first) as `dirs` prints it.
+fn format_stack(cwd: &str, stack: &[String]) -> String {
+    std::iter::once(cwd)
+        .chain(stack.iter().map(String::as_str))
+        .map(tilde_path)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Run a builtin against the session state.
+pub async fn run(builtin: &Builtin, state: &ShellState) -> BuiltinOutput {
+    match builtin.kind {
+        BuiltinKind::Cd => run_cd(&builtin.args, state).await,
+        BuiltinKind::Pushd => run_pushd(&builtin.args, state).await,
+        BuiltinKind::Popd => run_popd(&builtin.args, state).await,
+        BuiltinKind::Dirs => run_dirs(&builtin.args, state).await,
+        BuiltinKind::Export => run_export(&builtin.args, state).await,
+        BuiltinKind::Unset => run_unset(&builtin.args, state).await,
+        BuiltinKind::Jobs => run_jobs(&builtin.args, state).await,
+        BuiltinKind::Fg => run_fg(&builtin.args, state).await,
+        BuiltinKind::Bg => run_bg(&builtin.args, state).await,
+    }
+}
+
+async fn run_cd(args: &[String], state: &ShellState) -> BuiltinOutput {
+    let kind = BuiltinKind::Cd;
+    let args = args.strip_prefix(&["--".to_string()]).unwrap_or(args);
+    let cwd = state.get_cwd().await;
+
+    let (target, print) = match args {
+        [] => match state.get_env().await.get("HOME") {
+            Some(home) => (home, false),
+            None => return BuiltinOutput::error(kind, "HOME not set"),
+        },
+        [dash] if dash == "-" => match state.get_previous_cwd().await {
+            Some(previous) => (previous, true),
+            None => return BuiltinOutput::error(kind, "OLDPWD not set"),
+        },
+        [target] => (target.clone(), false),
+        _ => return BuiltinOutput::error(kind, "too many arguments"),
+    };
+
+    let resolved = match resolve_dir(kind, &cwd, &target) {
+        Ok(resolved) => resolved,
+        Err(output) => return output,
+    };
+    state.change_cwd(resolved.clone()).await;
+
+    BuiltinOutput {
+        stdout: if print {
+            vec![resolved.clone()]
+        } else {
+            Vec::new()
+        },
+        new_cwd: Some(resolved),
+        ..BuiltinOutput::default()
+    }
+}
+
+async fn run_pushd(args: &[String], state: &ShellState) -> BuiltinOutput {
+    let kind = BuiltinKind::Pushd;
+    let cwd = state.get_cwd().await;
+    let mut stack = state.get_dir_stack().await;
+
+    let target = match args {
+        // No argument swaps the current directory with the top of the stack
+        [] => match stack.first() {
+            Some(top) => top.clone(),
+            None => return BuiltinOutput::error(kind, "no other directory"),
+        },
+        [target] => target.clone(),
+        _ => return BuiltinOutput::error(kind, "too many arguments"),
+    };
+
+    let resolved = match resolve_dir(kind, &cwd, &target) {
+        Ok(resolved) => resolved,
+        Err(output) => return output,
+    };
+
+    if args.is_empty() {
+        stack[0] = cwd;
+    } else {
+        stack.insert(0, cwd);
+    }
+    state.change_cwd(resolved.clone()).await;
+    let line = format_stack(&resolved, &stack);
+    state.set_dir_stack(stack).await;
+
+    BuiltinOutput {
+        stdout: vec![line],
+        new_cwd: Some(resolved),
+        ..BuiltinOutput::default()
+    }
+}
+
+async fn run_popd(args: &[String], state: &ShellState) -> BuiltinOutput {
+    let kind = BuiltinKind::Popd;
+    if let Some(arg) = args.first() {
+        return BuiltinOutput::error(kind, &format!("{arg}: invalid argument"));
+    }
+
+    let cwd = state.get_cwd().await;
+    let mut stack = state.get_dir_stack().await;
+    let Some(top) = stack.first().cloned() else {
+        return BuiltinOutput::error(kind, "directory stack empty");
+    };
+
+    let resolved = match resolve_dir(kind, &cwd, &top) {
+        Ok(resolved) => resolved,
+        Err(output) => return output,
+    };
+
+    stack.remove(0);
+    state.change_cwd(resolved.clone()).await;
+    let line = format_stack(&resolved, &stack);
+    state.set_dir_stack(stack).await;
+
+    BuiltinOutput {
+        stdout: vec![line],
+        new_cwd: Some(resolved),
+        ..BuiltinOutput::default()
+    }
+}
+
+async fn run_dirs(args: &[String], state: &ShellState) -> BuiltinOutput {
+    let kind = BuiltinKind::Dirs;
+    let cwd = state.get_cwd().await;
+    let stack = state.get_dir_stack().await;
+
+    let stdout = match args {
+        [] => vec![format_stack(&cwd, &stack)],
+        [flag] if flag == "-c" => {
+            state.set_dir_stack(Vec::new()).await;
+            Vec::new()
+        }
+        [flag] if flag == "-l" => vec![std::iter::once(&cwd)
+            .chain(&stack)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")],
+        [flag] if flag == "-v" => std::iter::once(&cwd)
+            .chain(&stack)
+            .enumerate()
+            .map(|(i, dir)| format!("{i:2}  {}", tilde_path(dir)))
+            .collect(),
+        [arg, ..] => return BuiltinOutput::error(kind, &format!("{arg}: invalid option")),
+    };
+
+    BuiltinOutput {
+        stdout,
+        ..BuiltinOutput::default()
+    }
+}
+
+async fn run_export(args: &[String], state: &ShellState) -> BuiltinOutput {
+    let kind = BuiltinKind::Export;
+    let args = args.strip_prefix(&["--".to_string()]).unwrap_or(args);
+
+    if args.is_empty() || args == ["-p"] {
+        let env = state.get_env().await;
+        return BuiltinOutput {
+            stdout: env
+                .vars()
+                .iter()
+                .map(|(name, value)| format!("export {name}='{}'", value.replace('\'', r"'\''")))
+                .collect(),
+            ..BuiltinOutput::default()
+        };
+    }
+
+    let mut output = BuiltinOutput::default();
+    for arg in args {
+        // `export NAME` without a value marks a shell variable for export; every
+        // variable the session knows about is already exported, so only validate it
+        let result = match arg.split_once('=') {
+            Some((name, value)) => state.set_env_var(name, value).await,
+            None => SessionEnv::validate_name(arg),
+        };
+        if result.is_err() {
+            let name = arg.split_once('=').map_or(arg.as_str(), |(name, _)| name);
+            output
+                .stderr
+                .push(format!("{}: `{name}': not a valid identifier", kind.name()));
+            output.exit_code = 1;
+        }
+    }
+    output
+}
+
+async fn run_unset(args: &[String], state: &ShellState) -> BuiltinOutput {
+    let kind = BuiltinKind::Unset;
+    let args = match args {
+        [flag, rest @ ..] if flag == "-v" => rest,
+        [flag, ..] if flag.starts_with('-') => {
+            return BuiltinOutput::error(kind, &format!("{flag}: invalid option"));
+        }
+        _ => args,
+    };
+
+    let mut output = BuiltinOutput::default();
+    for name in args {
+        if state.unset_env_var(name).await.is_err() {
+            output
+                .stderr
+                .push(format!("{}: `{name}': not a valid identifier", kind.name()));
+            output.exit_code = 1;
+        }
+    }
+    output
+}
+
+async fn run_jobs(args: &[String], state: &ShellState) -> BuiltinOutput {
+    if let Some(arg) = args.first() {
+        return BuiltinOutput::error(BuiltinKind::Jobs, &format!("{arg}: invalid option"));
+    }
+    BuiltinOutput {
+        stdout: state.jobs.describe().await,
+        ..BuiltinOutput::default()
+    }
+}
+
+/// Hand a job's input back to the session and resume it if it was stopped. The
+/// caller waits for it like any foreground command.
+async fn run_fg(args: &[String], state: &ShellState) -> BuiltinOutput {
+    let kind = BuiltinKind::Fg;
+    if args.len() > 1 {
+        return BuiltinOutput::error(kind, "too many arguments");
+    }
+    let job = match state.jobs.resolve(args.first().map(String::as_str)).await {
+        Ok(job) => job,
+        Err(message) => return BuiltinOutput::error(kind, &message),
+    };
+
+    if job.status() == JobStatus::Stopped {
+        if let Some(pid) = job.pid() {
+            if let Err(message) = signals::send(pid, Some(pid), JobSignal::Continue).await {
+                return BuiltinOutput::error(kind, &message);
+            }
+        }
+        job.resume();
+    }
+    job.set_foreground(true);
+    state.set_process_group(job.pid()).await;
+    state.set_io(job.take_io()).await;
+
+    BuiltinOutput {
+        stdout: vec![job.command.clone()],
+        foreground_job: Some(job.id),
+        ..BuiltinOutput::default()
+    }
+}
+
+async fn run_bg(args: &[String], state: &ShellState) -> BuiltinOutput {
+    let kind = BuiltinKind::Bg;
+    let specs: Vec<Option<&str>> = if args.is_empty() {
+        vec![None]
+    } else {
+        args.iter().map(|arg| Some(arg.as_str())).collect()
+    };
+
+    let mut output = BuiltinOutput::default();
+    for spec in specs {
+        let job = match state.jobs.resolve(spec).await {
+            Ok(job) => job,
+            Err(message) => {
+                output.stderr.push(format!("{}: {message}", kind.name()));
+                output.exit_code = 1;
+                continue;
+            }
+        };
+        if job.status() != JobStatus::Stopped {
+            output.stderr.push(format!(
+                "{}: job {} already in background",
+                kind.name(),
+                job.id
+            ));
+            continue;
+        }
+        if let Some(pid) = job.pid() {
+            if let Err(message) = signals::send(pid, Some(pid), JobSignal::Continue).await {
+                output.stderr.push(format!("{}: {message}", kind.name()));
+                output.exit_code = 1;
+                continue;
+            }
+        }
+        job.resume();
+        output
+            .stdout
+            .push(format!("[{}]+ {} &", job.id, job.command));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_vars(_: &str) -> Option<String> {
+        None
+    }
+
+    fn builtin(kind: BuiltinKind, args: &[&str]) -> Builtin {
+        Builtin {
+            kind,
+            args: args.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn temp_dir() -> String {
+        std::env::temp_dir()
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Create a fresh directory tree under the system temp dir for one test.
+    fn make_tree(name: &str) -> String {
+        let root =
+            Path::new(&temp_dir()).join(format!("cepheus-builtins-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        std::fs::write(root.join("file.txt"), "").unwrap();
+        root.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_parse_plain_cd() {
+        let line = parse("cd src", no_vars);
+        assert_eq!(line.leading, vec![builtin(BuiltinKind::Cd, &["src"])]);
+        assert!(line.external.is_none());
+        assert!(line.trailing.is_empty());
+    }
+
+    #[test]
+    fn test_parse_and_chain() {
+        let line = parse("cd src && cargo build && cd - && dirs", no_vars);
+        assert_eq!(line.leading, vec![builtin(BuiltinKind::Cd, &["src"])]);
+        assert_eq!(line.external.as_deref(), Some("cargo build"));
+        assert_eq!(
+            line.trailing,
+            vec![
+                builtin(BuiltinKind::Cd, &["-"]),
+                builtin(BuiltinKind::Dirs, &[])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_builtin_between_externals_stays_external() {
+        let line = parse("make && cd out && ls", no_vars);
+        assert!(line.leading.is_empty());
+        assert_eq!(line.external.as_deref(), Some("make && cd out && ls"));
+        assert!(line.trailing.is_empty());
+    }
+
+    #[test]
+    fn test_parse_quotes_and_variables() {
+        let lookup = |name: &str| (name == "DIR").then(|| "/opt/x".to_string());
+        let line = parse(r#"cd "$DIR/my dir" && cd ${DIR}/'a&&b' && cd \ x"#, lookup);
+        assert_eq!(
+            line.leading,
+            vec![
+                builtin(BuiltinKind::Cd, &["/opt/x/my dir"]),
+                builtin(BuiltinKind::Cd, &["/opt/x/a&&b"]),
+                builtin(BuiltinKind::Cd, &[" x"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_falls_back_for_shell_syntax() {
+        for command in [
+            "cd src; ls",
+            "cd src || true",
+            "cd $(pwd)",
+            "cd src*",
+            "cd 'unbalanced",
+            "cd src &&",
+            "cd $1",
+        ] {
+            let line = parse(command, no_vars);
+            assert!(
+                line.leading.is_empty(),
+                "{command} should not be intercepted"
+            );
+            assert_eq!(line.external.as_deref(), Some(command));
+        }
+    }
+
+    #[test]
+    fn test_parse_tilde() {
+        let home = home_dir().unwrap();
+        let line = parse("cd ~ && cd ~/src && cd a~b", no_vars);
+        assert_eq!(
+            line.leading,
+            vec![
+                builtin(BuiltinKind::Cd, &[&home]),
+                builtin(BuiltinKind::Cd, &[&format!("{home}/src")]),
+                builtin(BuiltinKind::Cd, &["a~b"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cd_relative_and_back() {
+        let root = make_tree("cd");
+        let state = ShellState::new(root.clone());
+
+        let output = run(&builtin(BuiltinKind::Cd, &["a/b"]), &state).await;
+        assert_eq!(output.exit_code, 0);
+        let nested = format!("{root}/a/b");
+        assert_eq!(output.new_cwd.as_deref(), Some(nested.as_str()));
+        assert_eq!(state.get_cwd().await, nested);
+
+        let output = run(&builtin(BuiltinKind::Cd, &["-"]), &state).await;
+        assert_eq!(output.stdout, vec![root.clone()]);
+        assert_eq!(state.get_cwd().await, root);
+    }
+
+    #[tokio::test]
+    async fn test_cd_errors_leave_cwd_unchanged() {
+        let root = make_tree("cd-errors");
+        let state = ShellState::new(root.clone());
+
+        let output = run(&builtin(BuiltinKind::Cd, &["missing"]), &state).await;
+        assert_eq!(output.exit_code, 1);
+        assert_eq!(
+            output.stderr,
+            vec!["cd: missing: No such file or directory"]
+        );
+
+        let output = run(&builtin(BuiltinKind::Cd, &["file.txt"]), &state).await;
+        assert_eq!(output.stderr, vec!["cd: file.txt: Not a directory"]);
+
+        let output = run(&builtin(BuiltinKind::Cd, &["-"]), &state).await;
+        assert_eq!(output.stderr, vec!["cd: OLDPWD not set"]);
+
+        let output = run(&builtin(BuiltinKind::Cd, &["a", "b"]), &state).await;
+        assert_eq!(output.stderr, vec!["cd: too many arguments"]);
+
+        assert_eq!(state.get_cwd().await, root);
+        assert!(output.new_cwd.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cd_without_args_goes_home() {
+        let state = ShellState::new(temp_dir());
+        let output = run(&builtin(BuiltinKind::Cd, &[]), &state).await;
+        let home = Path::new(&home_dir().unwrap())
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(output.new_cwd, Some(home));
+    }
+
+    #[tokio::test]
+    async fn test_pushd_popd_dirs() {
+        let root = make_tree("pushd");
+        let state = ShellState::new(root.clone());
+        let a = format!("{root}/a");
+
+        let output = run(&builtin(BuiltinKind::Pushd, &["a"]), &state).await;
+        assert_eq!(output.new_cwd.as_deref(), Some(a.as_str()));
+        assert_eq!(state.get_dir_stack().await, vec![root.clone()]);
+
+        // Swap the top two entries
+        run(&builtin(BuiltinKind::Pushd, &[]), &state).await;
+        assert_eq!(state.get_cwd().await, root);
+        assert_eq!(state.get_dir_stack().await, vec![a.clone()]);
+
+        let output = run(&builtin(BuiltinKind::Dirs, &["-l"]), &state).await;
+        assert_eq!(output.stdout, vec![format!("{root} {a}")]);
+
+        let output = run(&builtin(BuiltinKind::Popd, &[]), &state).await;
+        assert_eq!(output.new_cwd.as_deref(), Some(a.as_str()));
+        assert!(state.get_dir_stack().await.is_empty());
+
+        let output = run(&builtin(BuiltinKind::Popd, &[]), &state).await;
+        assert_eq!(output.stderr, vec!["popd: directory stack empty"]);
+    }
+
+    #[test]
+    fn test_parse_export_chain() {
+        let lookup = |name: &str| (name == "HOME").then(|| "/home/me".to_string());
+        let line = parse("export RUST_LOG=debug PATH=$HOME/bin && cargo run", lookup);
+        assert_eq!(
+            line.leading,
+            vec![builtin(
+                BuiltinKind::Export,
+                &["RUST_LOG=debug", "PATH=/home/me/bin"]
+            )]
+        );
+        assert_eq!(line.external.as_deref(), Some("cargo run"));
+    }
+
+    #[tokio::test]
+    async fn test_export_and_unset() {
+        let state = ShellState::new(temp_dir());
+
+        let output = run(
+            &builtin(BuiltinKind::Export, &["AWS_PROFILE=dev", "EMPTY="]),
+            &state,
+        )
+        .await;
+        assert_eq!(output.exit_code, 0);
+        let env = state.get_env().await;
+        assert_eq!(env.get("AWS_PROFILE").as_deref(), Some("dev"));
+        assert_eq!(env.get("EMPTY").as_deref(), Some(""));
+
+        let output = run(&builtin(BuiltinKind::Export, &[]), &state).await;
+        assert_eq!(
+            output.stdout,
+            vec!["export AWS_PROFILE='dev'", "export EMPTY=''"]
+        );
+
+        let output = run(&builtin(BuiltinKind::Unset, &["-v", "AWS_PROFILE"]), &state).await;
+        assert_eq!(output.exit_code, 0);
+        assert!(state.get_env().await.get("AWS_PROFILE").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_export_rejects_invalid_names() {
+        let state = ShellState::new(temp_dir());
+
+        let output = run(&builtin(BuiltinKind::Export, &["1BAD=x", "GOOD=y"]), &state).await;
+        assert_eq!(output.exit_code, 1);
+        assert_eq!(
+            output.stderr,
+            vec!["export: `1BAD': not a valid identifier"]
+        );
+        assert_eq!(state.get_env().await.get("GOOD").as_deref(), Some("y"));
+
+        let output = run(&builtin(BuiltinKind::Unset, &["-f", "name"]), &state).await;
+        assert_eq!(output.stderr, vec!["unset: -f: invalid option"]);
+    }
+
+    #[test]
+    fn test_split_background() {
+        let background = |command| split_background(command).map(|line| line.background);
+        assert_eq!(background("sleep 10 &"), Some("sleep 10"));
+        assert_eq!(
+            background("make && make test &  "),
+            Some("make && make test")
+        );
+        assert_eq!(background("sleep 10&"), Some("sleep 10"));
+        assert_eq!(background("sleep 10"), None);
+        assert_eq!(background("a &&"), None);
+        assert_eq!(background("echo 'a &'"), None);
+        assert_eq!(background("echo \\&"), None);
+        assert_eq!(background("cmd >&"), None);
+        assert_eq!(background("&"), None);
+    }
+
+    #[test]
+    fn test_split_background_after_separator() {
+        assert_eq!(
+            split_background("a; b &"),
+            Some(BackgroundLine {
+                foreground: Some("a"),
+                background: "b",
+            })
+        );
+        assert_eq!(
+            split_background("make; cd out; ./server &"),
+            Some(BackgroundLine {
+                foreground: Some("make; cd out"),
+                background: "./server",
+            })
+        );
+        assert_eq!(
+            split_background("; b &"),
+            Some(BackgroundLine {
+                foreground: None,
+                background: "b",
+            })
+        );
+        assert_eq!(split_background("echo 'a; b' &").unwrap().foreground, None);
+        assert_eq!(split_background("echo a\\; b &").unwrap().foreground, None);
+        assert_eq!(split_background("a; &"), None);
+    }
+
+    #[test]
+    fn test_parse_job_builtins() {
+        let line = parse("fg %2", no_vars);
+        assert_eq!(line.leading, vec![builtin(BuiltinKind::Fg, &["%2"])]);
+        assert_eq!(line.external, None);
+
+        let line = parse("jobs", no_vars);
+        assert_eq!(line.leading, vec![builtin(BuiltinKind::Jobs, &[])]);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_lists_background_jobs() {
+        let state = ShellState::new(temp_dir());
+        let output = run(&builtin(BuiltinKind::Jobs, &[]), &state).await;
+        assert!(output.stdout.is_empty());
+
+        state.jobs.add("sleep 10", false).await;
+        state.jobs.add("make", true).await;
+        let output = run(&builtin(BuiltinKind::Jobs, &[]), &state).await;
+        assert_eq!(output.stdout.len(), 1);
+        assert!(output.stdout[0].starts_with("[1]+  Running"));
+
+        let output = run(&builtin(BuiltinKind::Jobs, &["-l"]), &state).await;
+        assert_eq!(output.stderr, vec!["jobs: -l: invalid option"]);
+    }
+
+    #[tokio::test]
+    async fn test_fg_and_bg_without_jobs() {
+        let state = ShellState::new(temp_dir());
+
+        let output = run(&builtin(BuiltinKind::Fg, &[]), &state).await;
+        assert_eq!(output.stderr, vec!["fg: current: no such job"]);
+        assert_eq!(output.foreground_job, None);
+
+        let output = run(&builtin(BuiltinKind::Bg, &["%3"]), &state).await;
+        assert_eq!(output.stderr, vec!["bg: %3: no such job"]);
+        assert_eq!(output.exit_code, 1);
+    }
+
+    #[tokio::test]
+    async fn test_fg_moves_job_to_foreground() {
+        let state = ShellState::new(temp_dir());
+        let job = state.jobs.add("sleep 10", false).await;
+        job.set_status(JobStatus::Stopped);
+
+        let output = run(&builtin(BuiltinKind::Bg, &[]), &state).await;
+        assert_eq!(output.stdout, vec!["[1]+ sleep 10 &"]);
+        assert_eq!(job.status(), JobStatus::Running);
+
+        let output = run(&builtin(BuiltinKind::Bg, &["1"]), &state).await;
+        assert_eq!(output.stderr, vec!["bg: job 1 already in background"]);
+
+        let output = run(&builtin(BuiltinKind::Fg, &["%1"]), &state).await;
+        assert_eq!(output.stdout, vec!["sleep 10"]);
+        assert_eq!(output.foreground_job, Some(1));
+        assert!(job.is_foreground());
+    }
+
+    #[tokio::test]
+    async fn test_dirs_clear() {
+        let state = ShellState::new(temp_dir());
+        state.set_dir_stack(vec!["/".to_string()]).await;
+
+        let output = run(&builtin(BuiltinKind::Dirs, &["-c"]), &state).await;
+        assert_eq!(output.exit_code, 0);
+        assert!(state.get_dir_stack().await.is_empty());
+    }
+}
@@ -0,0 +1,763 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::builtins::BuiltinKind;
+use crate::models::{CandidateKind, Completion, CompletionCandidate};
+use crate::state::SessionEnv;
+
+/// Most candidates returned for one completion
+pub const MAX_CANDIDATES: usize = 500;
+
+/// The word under the cursor and what precedes it in its command.
+pub struct CompletionContext<'a> {
+    /// Earlier words of the command, starting with the command name (quotes removed)
+    pub args: &'a [String],
+    /// The word being completed, up to the cursor (quotes removed)
+    pub word: &'a str,
+    /// Working directory of the session
+    pub cwd: &'a str,
+    /// Environment of the session
+    pub env: &'a SessionEnv,
+}
+
+/// A completion of the whole word, before quoting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// Word as the command should receive it
+    pub text: String,
+    /// Label shown in the candidate list
+    pub display: String,
+    pub kind: CandidateKind,
+}
+
+impl Suggestion {
+    /// A suggestion shown as its own text
+    pub fn new(text: impl Into<String>, kind: CandidateKind) -> Self {
+        let text = text.into();
+        Self {
+            display: text.clone(),
+            text,
+            kind,
+        }
+    }
+}
+
+/// Completes the arguments of one command, such as branch names after `git checkout`.
+pub trait CompletionProvider: Send + Sync {
+    /// Name of the command whose arguments are completed
+    fn command(&self) -> &str;
+
+    /// Suggestions for `context.word`, or `None` to complete file paths instead
+    fn complete(&self, context: &CompletionContext<'_>) -> Option<Vec<Suggestion>>;
+}
+
+/// Completion engine: executables and builtins in command position, `$VARIABLES`,
+/// paths relative to the session's directory, and command-specific providers.
+pub struct Completer {
+    providers: Vec<Box<dyn CompletionProvider>>,
+}
+
+impl Completer {
+    /// Create an engine without command-specific providers
+    pub fn new() -> Self {
+        Self {
+            providers: Vec::new(),
+        }
+    }
+
+    /// Add a provider, taking precedence over those added before for the same command
+    #[must_use]
+    pub fn with_provider(mut self, provider: impl CompletionProvider + 'static) -> Self {
+        self.providers.insert(0, Box::new(provider));
+        self
+    }
+
+    /// Complete the word ending at byte offset `cursor` of `input`.
+    ///
+    /// # Errors
+    /// Returns an error if `cursor` is not a character boundary of `input`.
+    pub fn complete(
+        &self,
+        input: &str,
+        cursor: usize,
+        cwd: &str,
+        env: &SessionEnv,
+    ) -> Result<Completion, String> {
+        if !input.is_char_boundary(cursor) {
+            return Err(format!("Cursor {cursor} is not a character boundary"));
+        }
+        let line = &input[..cursor];
+        let word = scan_word(line);
+        let raw = &line[word.start..];
+
+        let mut candidates = if word.open_quote.is_none() && raw.starts_with('$') {
+            complete_variables(raw, env)
+        } else {
+            let context = CompletionContext {
+                args: &word.args,
+                word: &word.text,
+                cwd,
+                env,
+            };
+            self.suggest(&context)
+                .into_iter()
+                .map(|suggestion| CompletionCandidate {
+                    replacement: quote(
+                        &suggestion.text,
+                        word.open_quote,
+                        suggestion.kind != CandidateKind::Directory,
+                    ),
+                    display: suggestion.display,
+                    kind: suggestion.kind,
+                })
+                .collect()
+        };
+
+        candidates.sort_by(|a, b| a.display.cmp(&b.display));
+        candidates.dedup_by(|a, b| a.replacement == b.replacement);
+        candidates.truncate(MAX_CANDIDATES);
+        Ok(Completion {
+            start: word.start,
+            end: cursor,
+            common_prefix: common_prefix(candidates.iter().map(|c| c.replacement.as_str())),
+            candidates,
+        })
+    }
+
+    fn suggest(&self, context: &CompletionContext<'_>) -> Vec<Suggestion> {
+        let Some(command) = context.args.first() else {
+            return if context.word.contains('/') {
+                complete_paths(
+                    context.word,
+                    context.cwd,
+                    context.env,
+                    PathFilter::Executables,
+                )
+            } else {
+                complete_commands(context.word, context.env)
+            };
+        };
+
+        let provided = self
+            .providers
+            .iter()
+            .filter(|provider| provider.command() == command.as_str())
+            .find_map(|provider| provider.complete(context));
+        if let Some(suggestions) = provided {
+            return suggestions;
+        }
+
+        let filter = match command.as_str() {
+            "cd" | "pushd" => PathFilter::Directories,
+            _ => PathFilter::All,
+        };
+        complete_paths(context.word, context.cwd, context.env, filter)
+    }
+}
+
+impl Default for Completer {
+    /// Engine with the built-in providers (git, cargo)
+    fn default() -> Self {
+        Self::new()
+            .with_provider(GitCompletion)
+            .with_provider(CargoCompletion)
+    }
+}
+
+/// The word ending at the cursor, split from the rest of its command.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CursorWord {
+    /// Earlier words of the command (after the last `|`, `;`, `&` or parenthesis)
+    args: Vec<String>,
+    /// Byte offset where the word starts (the cursor if no word was started)
+    start: usize,
+    /// The word with quotes and escapes removed
+    text: String,
+    /// Quote left open at the cursor
+    open_quote: Option<char>,
+}
+
+/// Split the command line before the cursor into words, as far as completion needs.
+fn scan_word(line: &str) -> CursorWord {
+    let mut word = CursorWord::default();
+    let mut in_word = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if !in_word && !c.is_whitespace() && !matches!(c, ';' | '|' | '&' | '(' | ')') {
+            in_word = true;
+            word.start = i;
+        }
+
+        if escaped {
+            word.text.push(c);
+            escaped = false;
+        } else if let Some(quote) = word.open_quote {
+            match c {
+                c if c == quote => word.open_quote = None,
+                '\\' if quote == '"' => escaped = true,
+                c => word.text.push(c),
+            }
+        } else {
+            match c {
+                c if c.is_whitespace() => {
+                    if in_word {
+                        word.args.push(std::mem::take(&mut word.text));
+                        in_word = false;
+                    }
+                }
+                ';' | '|' | '&' | '(' | ')' => {
+                    word.args.clear();
+                    word.text.clear();
+                    in_word = false;
+                }
+                '\'' | '"' => word.open_quote = Some(c),
+                '\\' => escaped = true,
+                c => word.text.push(c),
+            }
+        }
+    }
+
+    if !in_word {
+        word.start = line.len();
+    }
+    word
+}
+
+/// Quote `text` for the command line, continuing the quote the word was started with.
+/// `complete` closes that quote, unless more can be typed (a directory).
+fn quote(text: &str, open_quote: Option<char>, complete: bool) -> String {
+    let Some(quote) = open_quote else {
+        return escape(text);
+    };
+    let mut quoted = String::from(quote);
+    if quote == '\'' {
+        quoted.push_str(&text.replace('\'', r"'\''"));
+    } else {
+        for c in text.chars() {
+            if matches!(c, '"' | '$' | '`' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+    }
+    if complete {
+        quoted.push(quote);
+    }
+    quoted
+}
+
+/// Backslash-escape the characters the shell would otherwise interpret. A leading `~`
+/// is left to expand to the home directory.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (i, c) in text.char_indices() {
+        let special = c.is_whitespace()
+            || (c == '~' && i > 0)
+            || matches!(
+                c,
+                '\'' | '"'
+                    | '\\'
+                    | '$'
+                    | '`'
+                    | '&'
+                    | '|'
+                    | ';'
+                    | '<'
+                    | '>'
+                    | '('
+                    | ')'
+                    | '*'
+                    | '?'
+                    | '['
+                    | ']'
+                    | '{'
+                    | '}'
+                    | '!'
+                    | '#'
+            );
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Longest prefix (on character boundaries) shared by all of `texts`
+fn common_prefix<'a>(mut texts: impl Iterator<Item = &'a str>) -> String {
+    let Some(first) = texts.next() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for text in texts {
+        len = first
+            .char_indices()
+            .zip(text.chars())
+            .take_while(|((i, a), b)| *i < len && a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8());
+    }
+    first[..len].to_string()
+}
+
+/// `$NAME` (or `${NAME}`) for each variable starting with the name typed after `$`
+fn complete_variables(raw: &str, env: &SessionEnv) -> Vec<CompletionCandidate> {
+    let braced = raw.starts_with("${");
+    let prefix = raw.trim_start_matches('$').trim_start_matches('{');
+    env.names()
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .map(|name| CompletionCandidate {
+            replacement: if braced {
+                format!("${{{name}}}")
+            } else {
+                format!("${name}")
+            },
+            display: name,
+            kind: CandidateKind::Variable,
+        })
+        .collect()
+}
+
+/// Builtins and `PATH` executables starting with `prefix`
+fn complete_commands(prefix: &str, env: &SessionEnv) -> Vec<Suggestion> {
+    let mut names: BTreeSet<String> = BuiltinKind::NAMES
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .map(str::to_string)
+        .collect();
+    names.extend(path_executables(prefix, env));
+    names
+        .into_iter()
+        .map(|name| Suggestion::new(name, CandidateKind::Command))
+        .collect()
+}
+
+/// Names of the executables on the session's `PATH` starting with `prefix`
+fn path_executables(prefix: &str, env: &SessionEnv) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let Some(path) = env.get("PATH") else {
+        return names;
+    };
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if name.starts_with(prefix) && !names.contains(&name) && is_executable(&entry.path()) {
+                names.insert(name);
+            }
+        }
+    }
+    names
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.is_file())
+}
+
+/// Which directory entries path completion offers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PathFilter {
+    All,
+    Directories,
+    /// Directories and executable files (a command typed as a path)
+    Executables,
+}
+
+/// Entries of the directory part of `word` whose names start with its last component.
+/// Hidden entries are offered only once that component starts with `.`.
+fn complete_paths(word: &str, cwd: &str, env: &SessionEnv, filter: PathFilter) -> Vec<Suggestion> {
+    let (dir_part, name_prefix) = word
+        .rfind('/')
+        .map_or(("", word), |i| (&word[..=i], &word[i + 1..]));
+    let Some(dir) = resolve_dir(dir_part, cwd, env) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(name_prefix)
+                || (name.starts_with('.') && !name_prefix.starts_with('.'))
+            {
+                return None;
+            }
+            // Follow symlinks, so a link to a directory completes like one
+            let path = entry.path();
+            let is_dir = fs::metadata(&path).is_ok_and(|m| m.is_dir());
+            let keep = match filter {
+                PathFilter::All => true,
+                PathFilter::Directories => is_dir,
+                PathFilter::Executables => is_dir || is_executable(&path),
+            };
+            keep.then(|| {
+                let (display, kind) = if is_dir {
+                    (format!("{name}/"), CandidateKind::Directory)
+                } else {
+                    (name, CandidateKind::File)
+                };
+                Suggestion {
+                    text: format!("{dir_part}{display}"),
+                    display,
+                    kind,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Directory named by the directory part of a word: relative to `cwd`, absolute, or
+/// under the home directory (`~/`)
+fn resolve_dir(dir_part: &str, cwd: &str, env: &SessionEnv) -> Option<PathBuf> {
+    if let Some(rest) = dir_part.strip_prefix("~/") {
+        let home = env
+            .get("HOME")
+            .map(PathBuf::from)
+            .or_else(dirs_next::home_dir)?;
+        return Some(home.join(rest));
+    }
+    Some(Path::new(cwd).join(dir_part))
+}
+
+/// Git subcommands offered after `git`
+const GIT_SUBCOMMANDS: [&str; 28] = [
+    "add",
+    "bisect",
+    "blame",
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "clone",
+    "commit",
+    "diff",
+    "fetch",
+    "grep",
+    "init",
+    "log",
+    "merge",
+    "mv",
+    "pull",
+    "push",
+    "rebase",
+    "reset",
+    "restore",
+    "revert",
+    "rm",
+    "show",
+    "stash",
+    "status",
+    "switch",
+    "tag",
+    "worktree",
+];
+
+/// Git subcommands whose arguments are usually refs
+const GIT_REF_SUBCOMMANDS: [&str; 10] = [
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "diff",
+    "log",
+    "merge",
+    "rebase",
+    "reset",
+    "show",
+    "switch",
+];
+
+/// Completes git subcommands, and branches and tags after the subcommands taking them.
+/// Falls back to paths when no ref matches (`git diff src/`).
+pub struct GitCompletion;
+
+impl GitCompletion {
+    /// Branches (local and remote) and tags of the repository containing `cwd`
+    fn refs(cwd: &str) -> Vec<String> {
+        let output = Command::new("git")
+            .args([
+                "for-each-ref",
+                "--format=%(refname:short)",
+                "refs/heads",
+                "refs/remotes",
+                "refs/tags",
+            ])
+            .current_dir(cwd)
+            .output();
+        match output {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|name| !name.is_empty() && !name.ends_with("/HEAD"))
+                .map(str::to_string)
+                .collect(),
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                tracing::debug!("Failed to list git refs: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl CompletionProvider for GitCompletion {
+    fn command(&self) -> &'static str {
+        "git"
+    }
+
+    fn complete(&self, context: &CompletionContext<'_>) -> Option<Vec<Suggestion>> {
+        match context.args {
+            [_] => Some(
+                GIT_SUBCOMMANDS
+                    .into_iter()
+                    .filter(|sub| sub.starts_with(context.word))
+                    .map(|sub| Suggestion::new(sub, CandidateKind::Subcommand))
+                    .collect(),
+            ),
+            [_, sub, ..]
+                if GIT_REF_SUBCOMMANDS.contains(&sub.as_str())
+                    && !context.word.starts_with('-') =>
+            {
+                let refs: Vec<_> = Self::refs(context.cwd)
+                    .into_iter()
+                    .filter(|name| name.starts_with(context.word))
+                    .map(|name| Suggestion::new(name, CandidateKind::Branch))
+                    .collect();
+                (!refs.is_empty()).then_some(refs)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Cargo's own subcommands offered after `cargo`
+const CARGO_SUBCOMMANDS: [&str; 24] = [
+    "add",
+    "bench",
+    "build",
+    "check",
+    "clean",
+    "clippy",
+    "doc",
+    "fetch",
+    "fix",
+    "fmt",
+    "init",
+    "install",
+    "metadata",
+    "new",
+    "package",
+    "publish",
+    "remove",
+    "run",
+    "search",
+    "test",
+    "tree",
+    "uninstall",
+    "update",
+    "vendor",
+];
+
+/// Completes cargo subcommands, including those installed as `cargo-*` executables.
+pub struct CargoCompletion;
+
+impl CompletionProvider for CargoCompletion {
+    fn command(&self) -> &'static str {
+        "cargo"
+    }
+
+    fn complete(&self, context: &CompletionContext<'_>) -> Option<Vec<Suggestion>> {
+        if context.args.len() != 1 || context.word.starts_with('-') {
+            return None;
+        }
+        let mut subcommands: BTreeSet<String> = CARGO_SUBCOMMANDS
+            .into_iter()
+            .filter(|sub| sub.starts_with(context.word))
+            .map(str::to_string)
+            .collect();
+        subcommands.extend(
+            path_executables(&format!("cargo-{}", context.word), context.env)
+                .into_iter()
+                .filter_map(|name| name.strip_prefix("cargo-").map(str::to_string)),
+        );
+        Some(
+            subcommands
+                .into_iter()
+                .map(|sub| Suggestion::new(sub, CandidateKind::Subcommand))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cepheus-complete-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("alpine")).unwrap();
+        for file in ["alpha.txt", "my file", ".hidden"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        dir
+    }
+
+    fn replacements(completion: &Completion) -> Vec<&str> {
+        completion
+            .candidates
+            .iter()
+            .map(|c| c.replacement.as_str())
+            .collect()
+    }
+
+    fn complete_in(dir: &Path, input: &str) -> Completion {
+        Completer::default()
+            .complete(
+                input,
+                input.len(),
+                &dir.to_string_lossy(),
+                &SessionEnv::default(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_scan_word() {
+        let word = scan_word("ls -la | grep 'my fi");
+        assert_eq!(word.args, ["grep"]);
+        assert_eq!(word.start, 14);
+        assert_eq!(word.text, "my fi");
+        assert_eq!(word.open_quote, Some('\''));
+
+        let word = scan_word(r"cat my\ fi");
+        assert_eq!(word.args, ["cat"]);
+        assert_eq!(word.text, "my fi");
+
+        let word = scan_word("cd src && ");
+        assert!(word.args.is_empty());
+        assert_eq!(word.start, 10);
+        assert_eq!(word.text, "");
+    }
+
+    #[test]
+    fn test_paths_relative_to_cwd() {
+        let dir = test_dir("paths");
+        let completion = complete_in(&dir, "cat al");
+        assert_eq!(completion.start, 4);
+        assert_eq!(replacements(&completion), ["alpha.txt", "alpine/"]);
+        assert_eq!(completion.common_prefix, "alp");
+
+        // Hidden files only when asked for
+        assert_eq!(replacements(&complete_in(&dir, "cat .h")), [".hidden"]);
+        assert!(!replacements(&complete_in(&dir, "cat ")).contains(&".hidden"));
+
+        // `cd` only offers directories, keeping the typed directory part
+        let nested = format!("cd {}/al", dir.display());
+        assert_eq!(
+            replacements(&complete_in(Path::new("/"), &nested)),
+            [format!("{}/alpine/", dir.display())]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_paths_are_quoted() {
+        let dir = test_dir("quoting");
+        assert_eq!(replacements(&complete_in(&dir, "cat my")), [r"my\ file"]);
+        assert_eq!(replacements(&complete_in(&dir, "cat 'my")), ["'my file'"]);
+        assert_eq!(
+            replacements(&complete_in(&dir, "cat \"al")),
+            ["\"alpha.txt\"", "\"alpine/"]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut env = SessionEnv::default();
+        env.set("CEPHEUS_COMPLETE_VAR", "1").unwrap();
+        let completer = Completer::new();
+
+        let completion = completer
+            .complete("echo $CEPHEUS_COMP", 18, "/", &env)
+            .unwrap();
+        assert_eq!(replacements(&completion), ["$CEPHEUS_COMPLETE_VAR"]);
+        let completion = completer
+            .complete("echo ${CEPHEUS_COMP", 19, "/", &env)
+            .unwrap();
+        assert_eq!(replacements(&completion), ["${CEPHEUS_COMPLETE_VAR}"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commands_include_builtins_and_path() {
+        let completion = complete_in(Path::new("/"), "expo");
+        assert_eq!(replacements(&completion), ["export"]);
+        assert!(replacements(&complete_in(Path::new("/"), "s")).contains(&"sh"));
+    }
+
+    #[test]
+    fn test_git_and_cargo_subcommands() {
+        let dir = test_dir("subcommands");
+        assert_eq!(replacements(&complete_in(&dir, "git chec")), ["checkout"]);
+        assert!(replacements(&complete_in(&dir, "cargo b")).contains(&"build"));
+        // Arguments without refs fall back to paths
+        assert_eq!(replacements(&complete_in(&dir, "git add al")).len(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_provider_takes_precedence() {
+        struct Make;
+        impl CompletionProvider for Make {
+            fn command(&self) -> &'static str {
+                "make"
+            }
+            fn complete(&self, context: &CompletionContext<'_>) -> Option<Vec<Suggestion>> {
+                Some(vec![Suggestion::new(
+                    format!("{}-target", context.word),
+                    CandidateKind::Subcommand,
+                )])
+            }
+        }
+
+        let completion = Completer::new()
+            .with_provider(Make)
+            .complete("make te", 7, "/", &SessionEnv::default())
+            .unwrap();
+        assert_eq!(replacements(&completion), ["te-target"]);
+    }
+
+    #[test]
+    fn test_cursor_must_be_char_boundary() {
+        let completer = Completer::new();
+        let env = SessionEnv::default();
+        assert!(completer.complete("é", 1, "/", &env).is_err());
+        assert!(completer.complete("ls", 3, "/", &env).is_err());
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        assert_eq!(common_prefix(["abc", "abd", "ab"].into_iter()), "ab");
+        assert_eq!(common_prefix(["é1", "é2"].into_iter()), "é");
+        assert_eq!(common_prefix(["x"].into_iter()), "x");
+        assert_eq!(common_prefix(std::iter::empty()), "");
+    }
+}
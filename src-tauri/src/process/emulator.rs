@@ -0,0 +1,977 @@
+use std::borrow::Cow;
+use std::mem;
+
+use crate::models::{PtySize, ScreenLine, ScreenUpdate, TextStyle};
+use crate::process::ansi::{apply_sgr, push_span};
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+const TAB_WIDTH: usize = 8;
+/// Content of the second cell of a double-width character
+const WIDE_TAIL: char = '\0';
+
+/// One character cell of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    ch: char,
+    style: TextStyle,
+}
+
+impl Cell {
+    /// Blank cell keeping the background of `style`, as erasing does in xterm
+    fn blank(style: TextStyle) -> Self {
+        Self {
+            ch: ' ',
+            style: TextStyle {
+                bg: style.bg,
+                ..TextStyle::default()
+            },
+        }
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::blank(TextStyle::default())
+    }
+}
+
+/// Cursor state stored by `ESC 7` / `CSI s` / mode 1049.
+#[derive(Debug, Clone, Copy, Default)]
+struct SavedCursor {
+    row: usize,
+    col: usize,
+    style: TextStyle,
+    origin_mode: bool,
+}
+
+/// Position of the parser within an escape sequence.
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Ground,
+    /// After `ESC`
+    Escape,
+    /// After `ESC` and an intermediate byte (charset designation, ...); the next
+    /// final byte ends the sequence
+    EscapeIntermediate,
+    /// Inside a control sequence, collecting parameter and intermediate bytes
+    Csi(String),
+    /// Inside an OSC/DCS/APC/PM/SOS string, which is ignored
+    String,
+    /// `ESC` inside a string (start of the `ESC \` terminator)
+    StringEscape,
+}
+
+/// Cursor and mode state reported alongside changed rows
+type Status = (usize, usize, bool, bool, bool);
+
+/// VT100/xterm screen emulator for full-screen programs (editors, pagers, `top`).
+///
+/// Feed it the raw output of a PTY; it keeps a grid of styled cells, the cursor, the
+/// scroll region and the alternate screen, and reports changed rows through
+/// [`Emulator::take_update`]. Replies to terminal queries (cursor position, device
+/// attributes) are collected for the caller to write back to the PTY.
+// Terminal modes are independent on/off switches
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug)]
+pub struct Emulator {
+    rows: usize,
+    cols: usize,
+    grid: Vec<Vec<Cell>>,
+    /// Primary screen contents while the alternate screen is shown
+    saved_primary: Option<Vec<Vec<Cell>>>,
+    cursor_row: usize,
+    cursor_col: usize,
+    /// A character was written in the last column; the next one wraps first
+    wrap_pending: bool,
+    style: TextStyle,
+    saved_cursor: SavedCursor,
+    /// First and last row of the scroll region (inclusive)
+    scroll_top: usize,
+    scroll_bottom: usize,
+    autowrap: bool,
+    /// Cursor addressing is relative to the scroll region (DECOM)
+    origin_mode: bool,
+    /// Printed characters shift the rest of the row right (IRM)
+    insert_mode: bool,
+    cursor_visible: bool,
+    application_cursor: bool,
+    /// Last printed character, repeated by `CSI b`
+    last_char: Option<char>,
+    state: State,
+    /// Bytes of a UTF-8 character split across reads
+    utf8_pending: Vec<u8>,
+    /// Replies to queries, to be written to the PTY
+    responses: Vec<u8>,
+    dirty: Vec<bool>,
+    full_redraw: bool,
+    /// Status sent with the last update
+    reported: Option<Status>,
+}
+
+impl Emulator {
+    /// Create an emulator with a blank screen of the given size
+    pub fn new(size: PtySize) -> Self {
+        let rows = usize::from(size.rows).max(1);
+        let cols = usize::from(size.cols).max(1);
+        Self {
+            rows,
+            cols,
+            grid: vec![vec![Cell::default(); cols]; rows],
+            saved_primary: None,
+            cursor_row: 0,
+            cursor_col: 0,
+            wrap_pending: false,
+            style: TextStyle::default(),
+            saved_cursor: SavedCursor::default(),
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            autowrap: true,
+            origin_mode: false,
+            insert_mode: false,
+            cursor_visible: true,
+            application_cursor: false,
+            last_char: None,
+            state: State::Ground,
+            utf8_pending: Vec::new(),
+            responses: Vec::new(),
+            dirty: vec![false; rows],
+            full_redraw: true,
+            reported: None,
+        }
+    }
+
+    /// Is the alternate screen shown?
+    pub const fn is_alternate(&self) -> bool {
+        self.saved_primary.is_some()
+    }
+
+    /// Process a chunk of raw terminal output
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let data = if self.utf8_pending.is_empty() {
+            Cow::Borrowed(bytes)
+        } else {
+            let mut data = mem::take(&mut self.utf8_pending);
+            data.extend_from_slice(bytes);
+            Cow::Owned(data)
+        };
+
+        let mut rest: &[u8] = &data;
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    text.chars().for_each(|c| self.process(c));
+                    return;
+                }
+                Err(e) => {
+                    let (valid, invalid) = rest.split_at(e.valid_up_to());
+                    String::from_utf8_lossy(valid)
+                        .chars()
+                        .for_each(|c| self.process(c));
+                    if let Some(len) = e.error_len() {
+                        self.process(char::REPLACEMENT_CHARACTER);
+                        rest = &invalid[len..];
+                    } else {
+                        // Incomplete character at the end: wait for the next chunk
+                        self.utf8_pending = invalid.to_vec();
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Change the screen size. Rows are dropped from the top if the cursor would
+    /// otherwise fall off the bottom.
+    pub fn resize(&mut self, size: PtySize) {
+        let rows = usize::from(size.rows).max(1);
+        let cols = usize::from(size.cols).max(1);
+        if (rows, cols) == (self.rows, self.cols) {
+            return;
+        }
+
+        let overflow = (self.cursor_row + 1).saturating_sub(rows);
+        resize_grid(&mut self.grid, rows, cols, overflow);
+        if let Some(primary) = &mut self.saved_primary {
+            resize_grid(primary, rows, cols, 0);
+        }
+
+        self.rows = rows;
+        self.cols = cols;
+        self.cursor_row = (self.cursor_row - overflow).min(rows - 1);
+        self.cursor_col = self.cursor_col.min(cols - 1);
+        self.wrap_pending = false;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.dirty = vec![false; rows];
+        self.full_redraw = true;
+    }
+
+    /// Changes since the last call, or `None` if nothing changed
+    pub fn take_update(&mut self) -> Option<ScreenUpdate> {
+        let status = self.status();
+        if !self.full_redraw && !self.dirty.contains(&true) && self.reported == Some(status) {
+            return None;
+        }
+
+        let full = mem::take(&mut self.full_redraw);
+        let lines = (0..self.rows)
+            .filter(|&row| full || self.dirty[row])
+            .map(|row| self.line(row))
+            .collect();
+        self.dirty.fill(false);
+        self.reported = Some(status);
+
+        Some(ScreenUpdate {
+            rows: to_u16(self.rows),
+            cols: to_u16(self.cols),
+            full,
+            lines,
+            cursor_row: to_u16(self.cursor_row),
+            cursor_col: to_u16(self.cursor_col),
+            cursor_visible: self.cursor_visible,
+            alternate: self.is_alternate(),
+            application_cursor: self.application_cursor,
+        })
+    }
+
+    /// Take the pending replies to terminal queries
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        mem::take(&mut self.responses)
+    }
+
+    const fn status(&self) -> Status {
+        (
+            self.cursor_row,
+            self.cursor_col,
+            self.cursor_visible,
+            self.is_alternate(),
+            self.application_cursor,
+        )
+    }
+
+    /// Text and styling of one row, without trailing blanks
+    fn line(&self, row: usize) -> ScreenLine {
+        let cells = &self.grid[row];
+        let len = cells
+            .iter()
+            .rposition(|cell| *cell != Cell::default())
+            .map_or(0, |last| last + 1);
+
+        let mut text = String::with_capacity(len);
+        let mut spans = Vec::new();
+        for cell in cells[..len].iter().filter(|cell| cell.ch != WIDE_TAIL) {
+            let start = text.len();
+            text.push(cell.ch);
+            push_span(&mut spans, start, text.len(), cell.style);
+        }
+
+        ScreenLine {
+            row: to_u16(row),
+            text,
+            spans,
+        }
+    }
+
+    fn process(&mut self, c: char) {
+        match mem::take(&mut self.state) {
+            State::Ground => self.ground(c),
+            State::Escape => self.escape(c),
+            State::EscapeIntermediate => {
+                if matches!(c, ' '..='/') {
+                    self.state = State::EscapeIntermediate;
+                }
+            }
+            State::Csi(mut params) => match c {
+                // CAN and SUB abort the sequence
+                '\u{18}' | '\u{1a}' => {}
+                ESC => self.state = State::Escape,
+                '@'..='~' => self.control_sequence(&params, c),
+                c if c.is_control() => {
+                    self.execute(c);
+                    self.state = State::Csi(params);
+                }
+                c => {
+                    params.push(c);
+                    self.state = State::Csi(params);
+                }
+            },
+            State::String => match c {
+                BEL => {}
+                ESC => self.state = State::StringEscape,
+                _ => self.state = State::String,
+            },
+            // `ESC \` ends the string; any other `ESC x` ends it too
+            State::StringEscape => {}
+        }
+    }
+
+    fn ground(&mut self, c: char) {
+        match c {
+            ESC => self.state = State::Escape,
+            '\u{9b}' => self.state = State::Csi(String::new()),
+            '\u{90}' | '\u{98}' | '\u{9d}' | '\u{9e}' | '\u{9f}' => self.state = State::String,
+            c if c.is_control() => self.execute(c),
+            c => self.print(c),
+        }
+    }
+
+    /// Run a C0 control character
+    fn execute(&mut self, c: char) {
+        match c {
+            '\u{8}' => {
+                self.cursor_col = self.cursor_col.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            '\t' => self.tab_forward(1),
+            // The terminal's line discipline already turned `\n` into `\r\n`
+            '\n' | '\u{b}' | '\u{c}' => self.linefeed(),
+            '\r' => {
+                self.cursor_col = 0;
+                self.wrap_pending = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, c: char) {
+        match c {
+            '[' => self.state = State::Csi(String::new()),
+            ']' | 'P' | 'X' | '^' | '_' => self.state = State::String,
+            ' '..='/' => self.state = State::EscapeIntermediate,
+            '7' => self.save_cursor(),
+            '8' => self.restore_cursor(),
+            'D' => self.linefeed(),
+            'E' => {
+                self.cursor_col = 0;
+                self.linefeed();
+            }
+            'M' => self.reverse_index(),
+            'c' => self.reset(),
+            _ => {}
+        }
+    }
+
+    /// Run a control sequence; `raw` holds everything between `CSI` and `action`.
+    fn control_sequence(&mut self, raw: &str, action: char) {
+        let private = raw.chars().next().filter(|c| matches!(c, '<'..='?'));
+        let body = &raw[private.map_or(0, char::len_utf8)..];
+        let intermediates = body.trim_start_matches(|c: char| !matches!(c, ' '..='/'));
+        let params_str = &body[..body.len() - intermediates.len()];
+        let params: Vec<usize> = params_str
+            .split(';')
+            .map(|param| {
+                param
+                    .split(':')
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or(0)
+            })
+            .collect();
+        // Count or position parameter `index`, where 0 and missing mean `default`
+        let param = |index: usize, default: usize| {
+            params
+                .get(index)
+                .copied()
+                .filter(|&p| p != 0)
+                .unwrap_or(default)
+        };
+
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        match (private, action) {
+            (None, '@') => self.insert_cells(param(0, 1)),
+            (None, 'A') => self.cursor_up(param(0, 1)),
+            (None, 'B' | 'e') => self.cursor_down(param(0, 1)),
+            (None, 'C' | 'a') => self.set_col(self.cursor_col.saturating_add(param(0, 1))),
+            (None, 'D') => self.set_col(self.cursor_col.saturating_sub(param(0, 1))),
+            (None, 'E') => {
+                self.cursor_down(param(0, 1));
+                self.cursor_col = 0;
+            }
+            (None, 'F') => {
+                self.cursor_up(param(0, 1));
+                self.cursor_col = 0;
+            }
+            (None, 'G' | '`') => self.set_col(param(0, 1) - 1),
+            (None, 'H' | 'f') => self.move_to_addressed(param(0, 1) - 1, param(1, 1) - 1),
+            (None, 'I') => self.tab_forward(param(0, 1)),
+            (None | Some('?'), 'J') => self.erase_display(params[0]),
+            (None | Some('?'), 'K') => self.erase_line(params[0]),
+            (None, 'L') => self.insert_lines(param(0, 1)),
+            (None, 'M') => self.delete_lines(param(0, 1)),
+            (None, 'P') => self.delete_cells(param(0, 1)),
+            (None, 'S') => self.delete_rows(self.scroll_top, param(0, 1)),
+            // `CSI T` with several parameters is a mouse tracking request
+            (None, 'T') if params.len() == 1 => self.insert_rows(self.scroll_top, param(0, 1)),
+            (None, 'X') => {
+                let end = self.cursor_col.saturating_add(param(0, 1));
+                self.erase_cells(self.cursor_row, self.cursor_col, end);
+            }
+            (None, 'Z') => self.tab_backward(param(0, 1)),
+            (None, 'b') => {
+                if let Some(c) = self.last_char {
+                    (0..param(0, 1).min(self.rows * self.cols)).for_each(|_| self.print(c));
+                }
+            }
+            (None, 'c') => self.responses.extend_from_slice(b"\x1b[?1;2c"),
+            (Some('>'), 'c') => self.responses.extend_from_slice(b"\x1b[>0;10;1c"),
+            (None, 'd') => self.move_to_addressed(param(0, 1) - 1, self.cursor_col),
+            (None, 'h' | 'l') if params.contains(&4) => self.insert_mode = action == 'h',
+            (Some('?'), 'h' | 'l') => {
+                for &mode in &params {
+                    self.set_private_mode(mode, action == 'h');
+                }
+            }
+            (None, 'm') => apply_sgr(&mut self.style, params_str),
+            (None, 'n') => self.device_status(params[0]),
+            (None, 'r') => self.set_scroll_region(param(0, 1) - 1, param(1, self.rows) - 1),
+            (None, 's') if params_str.is_empty() => self.save_cursor(),
+            (None, 'u') => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    /// Set (`CSI ? n h`) or reset (`CSI ? n l`) a DEC private mode
+    fn set_private_mode(&mut self, mode: usize, enable: bool) {
+        match mode {
+            1 => self.application_cursor = enable,
+            6 => {
+                self.origin_mode = enable;
+                self.move_to_addressed(0, 0);
+            }
+            7 => self.autowrap = enable,
+            25 => self.cursor_visible = enable,
+            47 | 1047 => self.set_alternate(enable),
+            1048 if enable => self.save_cursor(),
+            1048 => self.restore_cursor(),
+            1049 if enable => {
+                self.save_cursor();
+                self.set_alternate(true);
+            }
+            1049 => {
+                self.set_alternate(false);
+                self.restore_cursor();
+            }
+            _ => {}
+        }
+    }
+
+    /// Switch to a blank alternate screen, or back to the primary one
+    fn set_alternate(&mut self, enable: bool) {
+        if enable && self.saved_primary.is_none() {
+            let blank = vec![vec![Cell::default(); self.cols]; self.rows];
+            self.saved_primary = Some(mem::replace(&mut self.grid, blank));
+            self.full_redraw = true;
+        } else if !enable {
+            if let Some(primary) = self.saved_primary.take() {
+                self.grid = primary;
+                self.full_redraw = true;
+            }
+        }
+    }
+
+    fn device_status(&mut self, request: usize) {
+        match request {
+            5 => self.responses.extend_from_slice(b"\x1b[0n"),
+            6 => {
+                let row = if self.origin_mode {
+                    self.cursor_row - self.scroll_top
+                } else {
+                    self.cursor_row
+                };
+                let reply = format!("\x1b[{};{}R", row + 1, self.cursor_col + 1);
+                self.responses.extend_from_slice(reply.as_bytes());
+            }
+            _ => {}
+        }
+    }
+
+    /// Full reset (`ESC c`), keeping the size and any unsent replies
+    fn reset(&mut self) {
+        let responses = mem::take(&mut self.responses);
+        *self = Self::new(PtySize::new(to_u16(self.rows), to_u16(self.cols)));
+        self.responses = responses;
+    }
+
+    fn print(&mut self, c: char) {
+        let width = char_width(c);
+        if width == 0 {
+            return;
+        }
+        if self.wrap_pending && self.autowrap {
+            self.cursor_col = 0;
+            self.linefeed();
+        }
+        self.wrap_pending = false;
+
+        if width == 2 && self.cursor_col + 1 >= self.cols {
+            // No room for both halves: wrap early, like xterm
+            if !self.autowrap || self.cols < 2 {
+                return;
+            }
+            self.erase_cells(self.cursor_row, self.cursor_col, self.cols);
+            self.cursor_col = 0;
+            self.linefeed();
+        }
+        if self.insert_mode {
+            self.insert_cells(width);
+        }
+
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        self.split_wide_chars(row, col, col + width);
+        let style = self.style;
+        self.grid[row][col] = Cell { ch: c, style };
+        if width == 2 {
+            self.grid[row][col + 1] = Cell {
+                ch: WIDE_TAIL,
+                style,
+            };
+        }
+        self.dirty[row] = true;
+        self.last_char = Some(c);
+
+        if col + width >= self.cols {
+            self.cursor_col = self.cols - 1;
+            self.wrap_pending = self.autowrap;
+        } else {
+            self.cursor_col = col + width;
+        }
+    }
+
+    /// Blank the halves of double-width characters cut by overwriting `start..end`
+    fn split_wide_chars(&mut self, row: usize, start: usize, end: usize) {
+        let cells = &mut self.grid[row];
+        if start > 0 && cells.get(start).is_some_and(|cell| cell.ch == WIDE_TAIL) {
+            cells[start - 1].ch = ' ';
+        }
+        if let Some(cell) = cells.get_mut(end).filter(|cell| cell.ch == WIDE_TAIL) {
+            cell.ch = ' ';
+        }
+    }
+
+    fn linefeed(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor_row == self.scroll_bottom {
+            self.delete_rows(self.scroll_top, 1);
+        } else if self.cursor_row + 1 < self.rows {
+            self.cursor_row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor_row == self.scroll_top {
+            self.insert_rows(self.scroll_top, 1);
+        } else {
+            self.cursor_row = self.cursor_row.saturating_sub(1);
+        }
+    }
+
+    fn blank_row(&self) -> Vec<Cell> {
+        vec![Cell::blank(self.style); self.cols]
+    }
+
+    /// Remove `count` rows at `at`, pulling up the rest of the scroll region
+    fn delete_rows(&mut self, at: usize, count: usize) {
+        let count = count.min(self.scroll_bottom + 1 - at);
+        let blank = self.blank_row();
+        self.grid.drain(at..at + count);
+        let insert_at = self.scroll_bottom + 1 - count;
+        self.grid
+            .splice(insert_at..insert_at, (0..count).map(|_| blank.clone()));
+        self.dirty[at..=self.scroll_bottom].fill(true);
+    }
+
+    /// Insert `count` blank rows at `at`, pushing the rest of the scroll region down
+    fn insert_rows(&mut self, at: usize, count: usize) {
+        let count = count.min(self.scroll_bottom + 1 - at);
+        let blank = self.blank_row();
+        self.grid
+            .drain(self.scroll_bottom + 1 - count..=self.scroll_bottom);
+        self.grid.splice(at..at, (0..count).map(|_| blank.clone()));
+        self.dirty[at..=self.scroll_bottom].fill(true);
+    }
+
+    fn cursor_in_scroll_region(&self) -> bool {
+        (self.scroll_top..=self.scroll_bottom).contains(&self.cursor_row)
+    }
+
+    fn insert_lines(&mut self, count: usize) {
+        if self.cursor_in_scroll_region() {
+            self.insert_rows(self.cursor_row, count);
+            self.cursor_col = 0;
+            self.wrap_pending = false;
+        }
+    }
+
+    fn delete_lines(&mut self, count: usize) {
+        if self.cursor_in_scroll_region() {
+            self.delete_rows(self.cursor_row, count);
+            self.cursor_col = 0;
+            self.wrap_pending = false;
+        }
+    }
+
+    /// Insert blanks at the cursor, shifting the rest of the row right
+    fn insert_cells(&mut self, count: usize) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        let count = count.min(self.cols - col);
+        self.split_wide_chars(row, col, col);
+        let blank = Cell::blank(self.style);
+        let cells = &mut self.grid[row];
+        cells.splice(col..col, std::iter::repeat_n(blank, count));
+        cells.truncate(self.cols);
+        trim_wide_head(cells);
+        self.dirty[row] = true;
+        self.wrap_pending = false;
+    }
+
+    /// Delete cells at the cursor, shifting the rest of the row left
+    fn delete_cells(&mut self, count: usize) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        let count = count.min(self.cols - col);
+        self.split_wide_chars(row, col, col + count);
+        let blank = Cell::blank(self.style);
+        let cells = &mut self.grid[row];
+        cells.drain(col..col + count);
+        cells.resize(self.cols, blank);
+        self.dirty[row] = true;
+        self.wrap_pending = false;
+    }
+
+    /// Blank cells `start..end` of `row`
+    fn erase_cells(&mut self, row: usize, start: usize, end: usize) {
+        let end = end.min(self.cols);
+        if start >= end {
+            return;
+        }
+        self.split_wide_chars(row, start, end);
+        let blank = Cell::blank(self.style);
+        self.grid[row][start..end].fill(blank);
+        self.dirty[row] = true;
+        self.wrap_pending = false;
+    }
+
+    /// `CSI n J`: 0 erases below the cursor, 1 above it, 2 and 3 the whole screen
+    fn erase_display(&mut self, mode: usize) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        let rows = match mode {
+            0 => {
+                self.erase_cells(row, col, self.cols);
+                row + 1..self.rows
+            }
+            1 => {
+                self.erase_cells(row, 0, col + 1);
+                0..row
+            }
+            2 | 3 => 0..self.rows,
+            _ => return,
+        };
+        for row in rows {
+            self.erase_cells(row, 0, self.cols);
+        }
+    }
+
+    /// `CSI n K`: 0 erases right of the cursor, 1 left of it, 2 the whole line
+    fn erase_line(&mut self, mode: usize) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        match mode {
+            0 => self.erase_cells(row, col, self.cols),
+            1 => self.erase_cells(row, 0, col + 1),
+            2 => self.erase_cells(row, 0, self.cols),
+            _ => {}
+        }
+    }
+
+    fn set_col(&mut self, col: usize) {
+        self.cursor_col = col.min(self.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    /// Move up, stopping at the top margin if the cursor is below it
+    fn cursor_up(&mut self, count: usize) {
+        let limit = if self.cursor_row >= self.scroll_top {
+            self.scroll_top
+        } else {
+            0
+        };
+        self.cursor_row = self.cursor_row.saturating_sub(count).max(limit);
+        self.wrap_pending = false;
+    }
+
+    /// Move down, stopping at the bottom margin if the cursor is above it
+    fn cursor_down(&mut self, count: usize) {
+        let limit = if self.cursor_row <= self.scroll_bottom {
+            self.scroll_bottom
+        } else {
+            self.rows - 1
+        };
+        self.cursor_row = self.cursor_row.saturating_add(count).min(limit);
+        self.wrap_pending = false;
+    }
+
+    /// Move to a position given by the program, relative to the scroll region in
+    /// origin mode
+    fn move_to_addressed(&mut self, row: usize, col: usize) {
+        self.cursor_row = if self.origin_mode {
+            row.saturating_add(self.scroll_top).min(self.scroll_bottom)
+        } else {
+            row.min(self.rows - 1)
+        };
+        self.set_col(col);
+    }
+
+    fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let bottom = bottom.min(self.rows - 1);
+        if top < bottom {
+            self.scroll_top = top;
+            self.scroll_bottom = bottom;
+            self.move_to_addressed(0, 0);
+        }
+    }
+
+    fn tab_forward(&mut self, count: usize) {
+        for _ in 0..count {
+            let next = (self.cursor_col / TAB_WIDTH + 1) * TAB_WIDTH;
+            self.cursor_col = next.min(self.cols - 1);
+        }
+        self.wrap_pending = false;
+    }
+
+    fn tab_backward(&mut self, count: usize) {
+        for _ in 0..count {
+            self.cursor_col = self.cursor_col.saturating_sub(1) / TAB_WIDTH * TAB_WIDTH;
+        }
+        self.wrap_pending = false;
+    }
+
+    const fn save_cursor(&mut self) {
+        self.saved_cursor = SavedCursor {
+            row: self.cursor_row,
+            col: self.cursor_col,
+            style: self.style,
+            origin_mode: self.origin_mode,
+        };
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.saved_cursor;
+        self.style = saved.style;
+        self.origin_mode = saved.origin_mode;
+        self.cursor_row = saved.row.min(self.rows - 1);
+        self.set_col(saved.col);
+    }
+}
+
+/// Resize every row of `grid` to `cols` and the grid to `rows`, first dropping
+/// `drop_top` rows from the top.
+fn resize_grid(grid: &mut Vec<Vec<Cell>>, rows: usize, cols: usize, drop_top: usize) {
+    grid.drain(..drop_top.min(grid.len()));
+    grid.resize(rows, vec![Cell::default(); cols]);
+    for row in grid.iter_mut() {
+        row.resize(cols, Cell::default());
+        trim_wide_head(row);
+    }
+}
+
+/// Blank a double-width character whose second half was cut off at the end of a row
+const fn trim_wide_head(cells: &mut [Cell]) {
+    if let Some(last) = cells.last_mut() {
+        if char_width(last.ch) == 2 {
+            last.ch = ' ';
+        }
+    }
+}
+
+/// Number of cells a character occupies: 0 for combining marks and other zero-width
+/// characters (which are dropped), 2 for East Asian wide characters and emoji.
+const fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+fn to_u16(value: usize) -> u16 {
+    u16::try_from(value).unwrap_or(u16::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Color;
+
+    fn emulator(rows: u16, cols: u16) -> Emulator {
+        Emulator::new(PtySize::new(rows, cols))
+    }
+
+    fn rows(emulator: &Emulator) -> Vec<String> {
+        (0..emulator.rows)
+            .map(|row| emulator.line(row).text)
+            .collect()
+    }
+
+    #[test]
+    fn test_prints_and_wraps() {
+        let mut emu = emulator(3, 5);
+        emu.feed(b"hello world");
+        assert_eq!(rows(&emu), vec!["hello", " worl", "d"]);
+        assert_eq!((emu.cursor_row, emu.cursor_col), (2, 1));
+    }
+
+    #[test]
+    fn test_scrolls_at_bottom() {
+        let mut emu = emulator(2, 10);
+        emu.feed(b"one\r\ntwo\r\nthree");
+        assert_eq!(rows(&emu), vec!["two", "three"]);
+    }
+
+    #[test]
+    fn test_cursor_movement_and_erase() {
+        let mut emu = emulator(3, 10);
+        emu.feed(b"abcdef\r\nline two\x1b[1;3H\x1b[K\x1b[2;5H\x1b[1K");
+        assert_eq!(rows(&emu), vec!["ab", "     two", ""]);
+
+        emu.feed(b"\x1b[2J\x1b[3;4Hx\x1b[Ay\x1b[2Dz");
+        assert_eq!(rows(&emu), vec!["", "   zy", "   x"]);
+    }
+
+    #[test]
+    fn test_scroll_region() {
+        let mut emu = emulator(4, 10);
+        emu.feed(b"top\r\na\r\nb\r\nbottom\x1b[2;3r\x1b[3;1H\nc");
+        assert_eq!(rows(&emu), vec!["top", "b", "c", "bottom"]);
+
+        // Reverse index at the top margin scrolls the region down
+        emu.feed(b"\x1b[2;1H\x1bMnew");
+        assert_eq!(rows(&emu), vec!["top", "new", "b", "bottom"]);
+    }
+
+    #[test]
+    fn test_insert_and_delete() {
+        let mut emu = emulator(3, 8);
+        emu.feed(b"abcdef\x1b[1;3H\x1b[2@XY\x1b[1;2H\x1b[P");
+        assert_eq!(rows(&emu)[0], "aXYcdef");
+
+        emu.feed(b"\r\nsecond\r\nthird\x1b[2;1H\x1b[L");
+        assert_eq!(rows(&emu), vec!["aXYcdef", "", "second"]);
+        emu.feed(b"\x1b[M\x1b[M");
+        assert_eq!(rows(&emu), vec!["aXYcdef", "", ""]);
+
+        emu.feed(b"\x1b[1;1H\x1b[3X");
+        assert_eq!(rows(&emu)[0], "   cdef");
+    }
+
+    #[test]
+    fn test_alternate_screen_restores_primary() {
+        let mut emu = emulator(3, 10);
+        emu.feed(b"$ vim\r\n");
+        emu.feed(b"\x1b[?1049h\x1b[H\x1b[2Jediting");
+        assert!(emu.is_alternate());
+        assert_eq!(rows(&emu), vec!["editing", "", ""]);
+
+        emu.feed(b"\x1b[?1049l");
+        assert!(!emu.is_alternate());
+        assert_eq!(rows(&emu), vec!["$ vim", "", ""]);
+        assert_eq!((emu.cursor_row, emu.cursor_col), (1, 0));
+    }
+
+    #[test]
+    fn test_styles_are_reported_as_spans() {
+        let mut emu = emulator(2, 10);
+        emu.feed(b"\x1b[31mred\x1b[0m \x1b[44m  \x1b[0m");
+        let line = emu.line(0);
+        assert_eq!(line.text, "red   ");
+        assert_eq!(line.spans.len(), 2);
+        assert_eq!(line.spans[0].style.fg, Some(Color::Indexed(1)));
+        assert_eq!((line.spans[1].start, line.spans[1].end), (4, 6));
+    }
+
+    #[test]
+    fn test_utf8_split_across_chunks_and_wide_chars() {
+        let mut emu = emulator(2, 6);
+        let bytes = "é漢字".as_bytes();
+        emu.feed(&bytes[..1]);
+        emu.feed(&bytes[1..4]);
+        emu.feed(&bytes[4..]);
+        assert_eq!(rows(&emu)[0], "é漢字");
+        assert_eq!(emu.cursor_col, 5);
+
+        // A wide character that does not fit wraps to the next row
+        emu.feed("字".as_bytes());
+        assert_eq!(rows(&emu), vec!["é漢字", "字"]);
+    }
+
+    #[test]
+    fn test_query_responses() {
+        let mut emu = emulator(5, 10);
+        emu.feed(b"\x1b[3;4H\x1b[6n\x1b[c");
+        assert_eq!(emu.take_responses(), b"\x1b[3;4R\x1b[?1;2c".to_vec());
+        assert!(emu.take_responses().is_empty());
+    }
+
+    #[test]
+    fn test_updates_contain_changed_rows() {
+        let mut emu = emulator(3, 10);
+        let update = emu.take_update().unwrap();
+        assert!(update.full);
+        assert_eq!(update.lines.len(), 3);
+        assert!(emu.take_update().is_none());
+
+        emu.feed(b"\x1b[2;1Hhi");
+        let update = emu.take_update().unwrap();
+        assert!(!update.full);
+        assert_eq!(update.lines.len(), 1);
+        assert_eq!(
+            (update.lines[0].row, update.lines[0].text.as_str()),
+            (1, "hi")
+        );
+        assert_eq!((update.cursor_row, update.cursor_col), (1, 2));
+
+        // Cursor-only changes are reported without rows
+        emu.feed(b"\x1b[?25l\x1b[?1h");
+        let update = emu.take_update().unwrap();
+        assert!(update.lines.is_empty());
+        assert!(!update.cursor_visible);
+        assert!(update.application_cursor);
+    }
+
+    #[test]
+    fn test_resize_keeps_cursor_row() {
+        let mut emu = emulator(4, 10);
+        emu.feed(b"1\r\n2\r\n3\r\n4");
+        emu.take_update();
+
+        emu.resize(PtySize::new(2, 5));
+        assert_eq!(rows(&emu), vec!["3", "4"]);
+        assert_eq!(emu.cursor_row, 1);
+        let update = emu.take_update().unwrap();
+        assert!(update.full);
+        assert_eq!((update.rows, update.cols), (2, 5));
+    }
+
+    #[test]
+    fn test_ignores_strings_and_charsets() {
+        let mut emu = emulator(2, 20);
+        emu.feed(b"\x1b]0;title\x07\x1b(B\x1b]8;;url\x1b\\ok\x1bP+q\x1b\\");
+        assert_eq!(rows(&emu)[0], "ok");
+    }
+}
@@ -0,0 +1,269 @@
+use std::mem;
+
+/// Splits raw command output into lines without waiting for a newline to see the
+/// line being written.
+///
+/// A carriage return moves back to the start of the line without clearing it: the text
+/// after it overwrites the line character by character, as on a terminal (progress bars,
+/// spinners). Escape sequences take up no room, and erase-in-line (`ESC [ K`) clears
+/// around the cursor as a terminal would.
+#[derive(Debug, Default)]
+pub struct LineAssembler {
+    /// Raw bytes of the unterminated line
+    current: Vec<u8>,
+    /// Byte offset in `current` where the next character is written
+    cursor: usize,
+    /// Escape sequence being read, applied once complete
+    escape: Vec<u8>,
+    /// `current` changed since it was last taken as a partial line
+    changed: bool,
+}
+
+impl LineAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk of output, returning the lines it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut completed = Vec::new();
+        for &byte in chunk {
+            if !self.escape.is_empty() {
+                if byte != b'\n' {
+                    self.escape.push(byte);
+                    if escape_len(&self.escape).is_some() {
+                        let sequence = mem::take(&mut self.escape);
+                        self.apply_escape(&sequence);
+                    }
+                    continue;
+                }
+                // A newline cuts the sequence short; keep what was read as text
+                let sequence = mem::take(&mut self.escape);
+                self.insert(&sequence);
+            }
+            match byte {
+                b'\n' => {
+                    completed.push(String::from_utf8_lossy(&self.current).into_owned());
+                    self.current.clear();
+                    self.cursor = 0;
+                    self.changed = false;
+                }
+                b'\r' => self.cursor = 0,
+                ESC => self.escape.push(byte),
+                _ => self.write(byte),
+            }
+        }
+        completed
+    }
+
+    /// Check whether the unterminated line changed since it was last taken
+    pub const fn has_partial(&self) -> bool {
+        self.changed
+    }
+
+    /// The unterminated line, if it changed since the last call. A character split
+    /// across chunks is left out until the rest of it arrives.
+    pub fn take_partial(&mut self) -> Option<String> {
+        if !mem::take(&mut self.changed) {
+            return None;
+        }
+        let complete = match std::str::from_utf8(&self.current) {
+            Err(e) if e.error_len().is_none() => &self.current[..e.valid_up_to()],
+            _ => &self.current,
+        };
+        Some(String::from_utf8_lossy(complete).into_owned())
+    }
+
+    /// The unterminated line at the end of the stream (if any)
+    pub fn finish(&mut self) -> Option<String> {
+        let sequence = mem::take(&mut self.escape);
+        self.insert(&sequence);
+        self.changed = false;
+        self.cursor = 0;
+        if self.current.is_empty() {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&self.current).into_owned();
+        self.current.clear();
+        Some(line)
+    }
+
+    /// Write a byte of text at the cursor. The first byte of a character replaces the
+    /// character under the cursor; the styling in front of that character is kept.
+    fn write(&mut self, byte: u8) {
+        if !is_continuation(byte) {
+            while let Some(len) = escape_len(&self.current[self.cursor..]) {
+                self.cursor += len;
+            }
+            if self.cursor < self.current.len() {
+                let end = self.cursor
+                    + 1
+                    + self.current[self.cursor + 1..]
+                        .iter()
+                        .take_while(|&&byte| is_continuation(byte))
+                        .count();
+                self.current.drain(self.cursor..end);
+            }
+        }
+        self.insert(&[byte]);
+    }
+
+    /// Insert `bytes` at the cursor without overwriting anything
+    fn insert(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        self.current
+            .splice(self.cursor..self.cursor, bytes.iter().copied());
+        self.cursor += bytes.len();
+        self.changed = true;
+    }
+
+    /// Apply a complete escape sequence. Erase-in-line is carried out; other sequences
+    /// are kept in the text for the styling parser.
+    fn apply_escape(&mut self, sequence: &[u8]) {
+        match sequence {
+            [ESC, b'[', mode @ .., b'K'] => self.erase_in_line(mode),
+            _ => self.insert(sequence),
+        }
+    }
+
+    /// Erase from the cursor to the end of the line (mode 0), from the start of the line
+    /// to the cursor (1) or the whole line (2). Erased text before the cursor turns into
+    /// spaces so the cursor stays in the same column.
+    fn erase_in_line(&mut self, mode: &[u8]) {
+        let (to_end, from_start) = match mode {
+            b"" | b"0" => (true, false),
+            b"1" => (false, true),
+            b"2" => (true, true),
+            _ => return,
+        };
+        if to_end {
+            self.current.truncate(self.cursor);
+        }
+        if from_start {
+            let blank = " ".repeat(columns(&self.current[..self.cursor]));
+            self.current.splice(..self.cursor, blank.bytes());
+            self.cursor = blank.len();
+        }
+        self.changed = true;
+    }
+}
+
+const ESC: u8 = 0x1b;
+
+/// Check whether `byte` continues a multi-byte UTF-8 character
+const fn is_continuation(byte: u8) -> bool {
+    byte & 0xc0 == 0x80
+}
+
+/// Length of the escape sequence `bytes` starts with, or `None` if they do not start
+/// with a complete one. Control sequences end at their final byte, operating system
+/// commands at BEL or `ESC \`; other escapes are two bytes long.
+fn escape_len(bytes: &[u8]) -> Option<usize> {
+    match bytes {
+        [ESC, b'[', rest @ ..] => rest
+            .iter()
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .map(|end| end + 3),
+        [ESC, b']', rest @ ..] => rest
+            .iter()
+            .enumerate()
+            .position(|(i, &byte)| byte == 0x07 || (byte == b'\\' && i > 0 && rest[i - 1] == ESC))
+            .map(|end| end + 3),
+        [ESC, _, ..] => Some(2),
+        _ => None,
+    }
+}
+
+/// Number of characters in `bytes`, not counting escape sequences
+fn columns(bytes: &[u8]) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(len) = escape_len(&bytes[i..]) {
+            i += len;
+            continue;
+        }
+        if !is_continuation(bytes[i]) {
+            count += 1;
+        }
+        i += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_lines_across_chunks() {
+        let mut lines = LineAssembler::new();
+        assert_eq!(lines.push(b"one\ntw"), vec!["one"]);
+        assert_eq!(lines.push(b"o\r\nthree\n"), vec!["two", "three"]);
+        assert!(!lines.has_partial());
+        assert_eq!(lines.finish(), None);
+    }
+
+    #[test]
+    fn test_partial_line_is_taken_once() {
+        let mut lines = LineAssembler::new();
+        assert!(lines.push(b"Password: ").is_empty());
+        assert!(lines.has_partial());
+        assert_eq!(lines.take_partial().as_deref(), Some("Password: "));
+        assert_eq!(lines.take_partial(), None);
+
+        lines.push(b"x");
+        assert_eq!(lines.take_partial().as_deref(), Some("Password: x"));
+    }
+
+    #[test]
+    fn test_carriage_return_replaces_line() {
+        let mut lines = LineAssembler::new();
+        lines.push(b" 10%\r 50%");
+        assert_eq!(lines.take_partial().as_deref(), Some(" 50%"));
+
+        // A carriage return alone changes nothing shown
+        lines.push(b"\r");
+        assert_eq!(lines.take_partial(), None);
+        assert_eq!(lines.push(b"100%\ndone\n"), vec!["100%", "done"]);
+    }
+
+    #[test]
+    fn test_carriage_return_overwrites_in_place() {
+        let mut lines = LineAssembler::new();
+        assert_eq!(lines.push(b"abc\rX\n"), vec!["Xbc"]);
+
+        // Characters are replaced whole, and styling in front of them is kept
+        lines.push("é\x1b[1m✓ ok\r".as_bytes());
+        lines.push(b"ab");
+        assert_eq!(lines.take_partial().as_deref(), Some("a\x1b[1mb ok"));
+    }
+
+    #[test]
+    fn test_erase_in_line() {
+        let mut lines = LineAssembler::new();
+        lines.push(b"Downloading 10%\r\x1b[");
+        // The sequence is applied once complete
+        assert_eq!(lines.take_partial().as_deref(), Some("Downloading 10%"));
+        lines.push(b"KDone");
+        assert_eq!(lines.take_partial().as_deref(), Some("Done"));
+
+        lines.push(b"\rAB\x1b[1K!");
+        assert_eq!(lines.take_partial().as_deref(), Some("  !e"));
+        lines.push(b"\x1b[2K");
+        assert_eq!(lines.finish().as_deref(), Some("   "));
+    }
+
+    #[test]
+    fn test_split_utf8_is_held_back() {
+        let mut lines = LineAssembler::new();
+        let bytes = "✓ ok".as_bytes();
+        lines.push(&bytes[..2]);
+        assert_eq!(lines.take_partial().as_deref(), Some(""));
+        lines.push(&bytes[2..]);
+        assert_eq!(lines.take_partial().as_deref(), Some("✓ ok"));
+        assert_eq!(lines.finish().as_deref(), Some("✓ ok"));
+    }
+}
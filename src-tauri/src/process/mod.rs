@@ -0,0 +1,11 @@
+pub mod ansi;
+pub mod builtins;
+pub mod completion;
+pub mod emulator;
+pub mod lines;
+#[cfg(unix)]
+pub mod persistent;
+#[cfg(unix)]
+pub mod pty;
+pub mod shell;
+pub mod signals;
@@ -0,0 +1,829 @@
+use std::fmt::Write as _;
+use std::io;
+use std::mem;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStderr, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+use super::shell::{Shell, ShellKind};
+use crate::state::shell_manager::SharedStdin;
+use crate::state::SessionEnv;
+
+/// Prefix of the sentinel line printed after every command.
+/// A per-shell nonce is appended so command output cannot forge completion.
+const MARKER_PREFIX: &str = "__CEPHEUS_DONE_";
+
+/// Most bytes read from a stream of the shell at once
+const READ_SIZE: usize = 8192;
+
+/// Output of a command running in the persistent shell, handed over as it is read
+/// rather than line by line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellOutput {
+    /// Bytes written to stdout
+    Stdout(Vec<u8>),
+    /// Bytes written to stderr
+    Stderr(Vec<u8>),
+    /// No output arrived for the `idle` interval given to [`PersistentShell::run`]: time
+    /// to show an unterminated line
+    Idle,
+}
+
+/// Result of running one command in the persistent shell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// Command finished; the shell is ready for the next one
+    Completed {
+        /// Exit status of the command (`$?`)
+        exit_code: i32,
+        /// Shell working directory after the command (`$PWD`)
+        cwd: String,
+    },
+    /// The shell itself exited (e.g. the user typed `exit`)
+    ShellExited {
+        /// Exit code of the shell process, if it exited normally
+        exit_code: Option<i32>,
+    },
+}
+
+/// A long-lived shell process that commands are written to one at a time.
+///
+/// POSIX shells (`sh`, `bash`, `zsh`) and `fish` are supported. Shell state (exported
+/// variables, functions, aliases, `set` options, `cd`) persists between commands.
+/// Completion is detected by a sentinel line written after each command on both stdout
+/// and stderr, carrying the exit status and working directory.
+///
+/// The running command shares the shell's stdin, so input written through
+/// [`PersistentShell::stdin`] while it runs is read by the command.
+pub struct PersistentShell {
+    child: Child,
+    kind: ShellKind,
+    stdin: SharedStdin,
+    stdout: OutputPipe<ChildStdout>,
+    stderr: OutputPipe<ChildStderr>,
+    marker: String,
+    cwd: String,
+    /// Session environment as last applied to the shell
+    env: SessionEnv,
+    /// Statements bringing the shell up to date with session environment changes
+    pending_env: String,
+}
+
+impl PersistentShell {
+    /// Start `shell` in `cwd` with the session environment applied.
+    ///
+    /// The shell leads its own process group so an interrupt can be delivered to the
+    /// running command without touching the application. Output of its startup files
+    /// (a login or interactive shell's profile and rc file) is discarded, and the
+    /// session environment is applied again on top of them.
+    pub async fn spawn(shell: &Shell, cwd: &str, env: &SessionEnv) -> io::Result<Self> {
+        if !matches!(
+            shell.kind,
+            ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh | ShellKind::Fish
+        ) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} cannot run in persistent mode", shell.program),
+            ));
+        }
+
+        let mut cmd = Command::new(&shell.program);
+        // An interactive fish insists on a terminal; it reads its config regardless
+        cmd.args(
+            shell
+                .startup_flags()
+                .into_iter()
+                .filter(|flag| shell.kind != ShellKind::Fish || *flag != "-i"),
+        );
+        env.apply(&mut cmd);
+        let mut child = cmd
+            .current_dir(cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin not captured");
+        let stdout = child.stdout.take().expect("stdout not captured");
+        let stderr = child.stderr.take().expect("stderr not captured");
+
+        let nonce = format!(
+            "{}_{}",
+            child.id().unwrap_or_default(),
+            crate::state::current_timestamp_ms()
+        );
+
+        let mut persistent = Self {
+            child,
+            kind: shell.kind,
+            stdin: Arc::new(Mutex::new(stdin)),
+            stdout: OutputPipe::new(stdout),
+            stderr: OutputPipe::new(stderr),
+            marker: format!("{MARKER_PREFIX}{nonce}__"),
+            cwd: cwd.to_string(),
+            env: env.clone(),
+            pending_env: String::new(),
+        };
+
+        // A trapped (rather than ignored) SIGINT keeps the shell alive on Ctrl+C while
+        // commands it starts still get the default disposition and terminate.
+        let mut script = match shell.kind {
+            ShellKind::Fish => "function __cepheus_interrupt --on-signal INT; end\n",
+            _ => "trap : INT\n",
+        }
+        .to_string();
+        if shell.login || shell.interactive {
+            if shell.interactive {
+                script.push_str(quiet_script(shell.kind));
+            }
+            // Startup files may have overridden session variables
+            script.push_str(&env_script(shell.kind, env, &SessionEnv::default()));
+            persistent.write_script(&script).await?;
+            persistent.skip_startup_output().await?;
+        } else {
+            persistent.write_script(&script).await?;
+        }
+
+        Ok(persistent)
+    }
+
+    /// Discard everything the shell printed so far (profile output, prompts, job control
+    /// warnings) by reading both streams up to a sentinel.
+    async fn skip_startup_output(&mut self) -> io::Result<()> {
+        let script = format!(
+            "printf '%s\\n' '{marker}'; printf '%s\\n' '{marker}' >&2\n",
+            marker = self.marker
+        );
+        self.write_script(&script).await?;
+
+        tokio::try_join!(
+            self.stdout.skip_through(&self.marker),
+            self.stderr.skip_through(&self.marker)
+        )?;
+        Ok(())
+    }
+
+    /// Process ID of the shell (also its process group ID).
+    pub fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Stdin of the shell, which the running command reads from.
+    pub fn stdin(&self) -> SharedStdin {
+        Arc::clone(&self.stdin)
+    }
+
+    /// Working directory reported by the shell after the last command.
+    pub fn cwd(&self) -> &str {
+        &self.cwd
+    }
+
+    /// Apply session environment changes (e.g. from `set_env`) before the next command.
+    /// Variables exported inside the shell itself are left alone unless overridden.
+    pub fn sync_env(&mut self, env: &SessionEnv) {
+        if *env != self.env {
+            self.pending_env
+                .push_str(&env_script(self.kind, env, &self.env));
+            self.env = env.clone();
+        }
+    }
+
+    /// Run `command`, forwarding its output to `on_output` as it is read, until the
+    /// sentinel arrives on both streams. Once output stops for `idle`, `on_output` gets
+    /// [`ShellOutput::Idle`].
+    ///
+    /// If `working_dir` differs from the shell's current directory (e.g. it was changed
+    /// through `change_directory`), the shell changes into it first.
+    pub async fn run<F>(
+        &mut self,
+        command: &str,
+        working_dir: &str,
+        idle: Duration,
+        mut on_output: F,
+    ) -> io::Result<RunOutcome>
+    where
+        F: FnMut(ShellOutput) + Send,
+    {
+        let script = self.build_script(command, working_dir);
+        self.write_script(&script).await?;
+
+        let mut completion: Option<(i32, String)> = None;
+        let mut stdout_done = false;
+        let mut stderr_done = false;
+        let mut quiet = true;
+
+        while !(stdout_done && stderr_done) {
+            tokio::select! {
+                read = self.stdout.read(&self.marker), if !stdout_done => {
+                    let (output, end) = read?;
+                    if !output.is_empty() {
+                        on_output(ShellOutput::Stdout(output));
+                        quiet = false;
+                    }
+                    if let Some(end) = end {
+                        if let StreamEnd::Sentinel(rest) = end {
+                            completion = parse_status(&rest);
+                        }
+                        stdout_done = true;
+                    }
+                }
+                read = self.stderr.read(&self.marker), if !stderr_done => {
+                    let (output, end) = read?;
+                    if !output.is_empty() {
+                        on_output(ShellOutput::Stderr(output));
+                        quiet = false;
+                    }
+                    stderr_done = end.is_some();
+                }
+                () = tokio::time::sleep(idle), if !quiet => {
+                    on_output(ShellOutput::Idle);
+                    quiet = true;
+                }
+            }
+        }
+
+        if let Some((exit_code, cwd)) = completion {
+            self.cwd.clone_from(&cwd);
+            return Ok(RunOutcome::Completed { exit_code, cwd });
+        }
+
+        // Both streams closed without a sentinel: the shell is gone
+        let status = self.child.wait().await?;
+        Ok(RunOutcome::ShellExited {
+            exit_code: status.code(),
+        })
+    }
+
+    async fn write_script(&self, script: &str) -> io::Result<()> {
+        let mut stdin = self.stdin.lock().await;
+        stdin.write_all(script.as_bytes()).await?;
+        stdin.flush().await
+    }
+
+    fn build_script(&mut self, command: &str, working_dir: &str) -> String {
+        let mut script = std::mem::take(&mut self.pending_env);
+        if working_dir != self.cwd {
+            let _ = match self.kind {
+                ShellKind::Fish => writeln!(script, "cd {} 2>/dev/null", quote_fish(working_dir)),
+                _ => writeln!(script, "cd -- {} 2>/dev/null", quote(working_dir)),
+            };
+        }
+        // `command eval` turns a syntax error (e.g. an unbalanced quote) into a failed
+        // status instead of exiting the shell or swallowing the sentinel; zsh's `command`
+        // only runs external commands, but its `eval` does not exit on errors, nor does
+        // fish's. The sentinel is on the same line so the shell has read all of it before
+        // the command starts reading stdin, leaving only user input for the command.
+        let _ = match self.kind {
+            ShellKind::Fish => writeln!(
+                script,
+                "eval {command}; set __cepheus_status $status; \
+                 printf '%s %d %s\\n' '{marker}' $__cepheus_status \"$PWD\"; \
+                 printf '%s\\n' '{marker}' >&2",
+                command = quote_fish(command),
+                marker = self.marker
+            ),
+            kind => writeln!(
+                script,
+                "{eval} {command}; __cepheus_status=$?; \
+                 printf '%s %d %s\\n' '{marker}' \"$__cepheus_status\" \"$PWD\"; \
+                 printf '%s\\n' '{marker}' >&2",
+                eval = if kind == ShellKind::Zsh {
+                    "eval"
+                } else {
+                    "command eval"
+                },
+                command = quote(command),
+                marker = self.marker
+            ),
+        };
+        script
+    }
+}
+
+/// How a stream of the shell stopped producing a command's output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StreamEnd {
+    /// The sentinel arrived, followed on its line by this text
+    Sentinel(String),
+    /// The stream closed: the shell exited
+    Closed,
+}
+
+/// One of the shell's output streams, read in chunks up to the sentinel.
+struct OutputPipe<R> {
+    reader: R,
+    /// Bytes read but not handed over yet: what may be the start of the sentinel, or
+    /// output following it
+    held: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> OutputPipe<R> {
+    const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            held: Vec::new(),
+        }
+    }
+
+    /// Read the next chunk, returning the output in it and, if the sentinel or the end
+    /// of the stream was reached, how the stream ended. Cancel safe: nothing is lost if
+    /// the read is abandoned before it completes.
+    async fn read(&mut self, marker: &str) -> io::Result<(Vec<u8>, Option<StreamEnd>)> {
+        self.held.reserve(READ_SIZE);
+        if self.reader.read_buf(&mut self.held).await? == 0 {
+            return Ok((mem::take(&mut self.held), Some(StreamEnd::Closed)));
+        }
+        Ok(self.scan(marker))
+    }
+
+    /// Take the output held before the sentinel, and the text after the sentinel once
+    /// its line is complete. Output that may be the start of the sentinel is held back
+    /// until the next read tells.
+    fn scan(&mut self, marker: &str) -> (Vec<u8>, Option<StreamEnd>) {
+        let marker = marker.as_bytes();
+        if let Some(start) = self
+            .held
+            .windows(marker.len())
+            .position(|window| window == marker)
+        {
+            // Output without a trailing newline ends up on the same line as the sentinel
+            let output = self.held.drain(..start).collect();
+            let Some(newline) = self.held.iter().position(|&byte| byte == b'\n') else {
+                return (output, None);
+            };
+            let line: Vec<u8> = self.held.drain(..=newline).collect();
+            let rest = String::from_utf8_lossy(&line[marker.len()..newline]).into_owned();
+            return (output, Some(StreamEnd::Sentinel(rest)));
+        }
+
+        let possible_start = (1..marker.len())
+            .rev()
+            .find(|&len| self.held.ends_with(&marker[..len]))
+            .unwrap_or(0);
+        let output = self
+            .held
+            .drain(..self.held.len() - possible_start)
+            .collect();
+        (output, None)
+    }
+
+    /// Read up to and including the sentinel, discarding the output before it.
+    async fn skip_through(&mut self, marker: &str) -> io::Result<()> {
+        loop {
+            match self.read(marker).await?.1 {
+                Some(StreamEnd::Sentinel(_)) => return Ok(()),
+                Some(StreamEnd::Closed) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "shell exited while starting",
+                    ))
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Quote `value` as a single POSIX shell word.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Quote `value` as a single fish word.
+fn quote_fish(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// Statements keeping an interactive shell's prompts and history out of the output and
+/// out of the user's history file.
+const fn quiet_script(kind: ShellKind) -> &'static str {
+    match kind {
+        // Line editing would echo every line read
+        ShellKind::Bash => "set +o emacs +o vi; unset HISTFILE; PS1=; PS2=; PROMPT_COMMAND=\n",
+        ShellKind::Zsh => {
+            "unsetopt zle; unset HISTFILE; PS1=; PS2=; RPROMPT=; precmd_functions=(); \
+             unset -f precmd 2>/dev/null\n"
+        }
+        ShellKind::Sh => "unset HISTFILE; PS1=; PS2=\n",
+        ShellKind::Fish | ShellKind::Cmd | ShellKind::PowerShell => "",
+    }
+}
+
+/// Statements turning a shell environment shaped like `previous` into `current`.
+fn env_script(kind: ShellKind, current: &SessionEnv, previous: &SessionEnv) -> String {
+    let export = |script: &mut String, name: &str, value: &str| {
+        let _ = match kind {
+            ShellKind::Fish => writeln!(script, "set -gx {name} {}", quote_fish(value)),
+            _ => writeln!(script, "export {name}={}", quote(value)),
+        };
+    };
+    let unset = |script: &mut String, name: &str| {
+        let _ = match kind {
+            ShellKind::Fish => writeln!(script, "set -e {name}"),
+            _ => writeln!(script, "unset {name}"),
+        };
+    };
+
+    let mut script = String::new();
+    for name in current.removed().difference(previous.removed()) {
+        unset(&mut script, name);
+    }
+    for name in previous.vars().keys() {
+        if !current.vars().contains_key(name) && !current.removed().contains(name) {
+            // Restore the inherited value (or absence) the variable was overriding
+            match std::env::var(name) {
+                Ok(value) => export(&mut script, name, &value),
+                Err(_) => unset(&mut script, name),
+            }
+        }
+    }
+    for (name, value) in current.vars() {
+        if previous.vars().get(name) != Some(value) {
+            export(&mut script, name, value);
+        }
+    }
+    script
+}
+
+/// Parse `" <status> <cwd>"` following the stdout sentinel.
+fn parse_status(rest: &str) -> Option<(i32, String)> {
+    let rest = rest.strip_prefix(' ')?;
+    let (code, cwd) = rest.split_once(' ').unwrap_or((rest, ""));
+    Some((code.parse().ok()?, cwd.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ShellConfig;
+    use crate::process::lines::LineAssembler;
+
+    const IDLE: Duration = Duration::from_millis(50);
+
+    /// A line of a command's output
+    #[derive(Debug, PartialEq, Eq)]
+    enum Line {
+        Stdout(String),
+        Stderr(String),
+    }
+
+    async fn run_collect(shell: &mut PersistentShell, command: &str) -> (RunOutcome, Vec<Line>) {
+        let mut stdout = LineAssembler::new();
+        let mut stderr = LineAssembler::new();
+        let mut lines = Vec::new();
+        let cwd = shell.cwd().to_string();
+        let outcome = shell
+            .run(command, &cwd, IDLE, |output| match output {
+                ShellOutput::Stdout(chunk) => {
+                    lines.extend(stdout.push(&chunk).into_iter().map(Line::Stdout));
+                }
+                ShellOutput::Stderr(chunk) => {
+                    lines.extend(stderr.push(&chunk).into_iter().map(Line::Stderr));
+                }
+                ShellOutput::Idle => {}
+            })
+            .await
+            .unwrap();
+        lines.extend(stdout.finish().map(Line::Stdout));
+        lines.extend(stderr.finish().map(Line::Stderr));
+        (outcome, lines)
+    }
+
+    fn temp_dir() -> String {
+        std::env::temp_dir()
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn test_quote_escapes_single_quotes() {
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("plain"), "'plain'");
+    }
+
+    #[test]
+    fn test_sentinel_is_found_across_reads() {
+        let marker = "__CEPHEUS_DONE_1__";
+        let mut pipe = OutputPipe::new(tokio::io::empty());
+        pipe.held.extend_from_slice(b"ordinary output\n");
+        assert_eq!(pipe.scan(marker), (b"ordinary output\n".to_vec(), None));
+
+        // What may be the start of the sentinel is held back
+        pipe.held.extend_from_slice(b"partial__CEPHEUS_DO");
+        assert_eq!(pipe.scan(marker), (b"partial".to_vec(), None));
+        pipe.held.extend_from_slice(b"NE_1__ 3 /tmp/so");
+        assert_eq!(pipe.scan(marker), (Vec::new(), None));
+        pipe.held.extend_from_slice(b"me dir\nlater");
+        let (output, end) = pipe.scan(marker);
+        assert!(output.is_empty());
+        let Some(StreamEnd::Sentinel(rest)) = end else {
+            panic!("sentinel not found: {end:?}");
+        };
+        assert_eq!(parse_status(&rest), Some((3, "/tmp/some dir".to_string())));
+        // Output after the sentinel is left for the next command
+        assert_eq!(pipe.held, b"later");
+    }
+
+    #[test]
+    fn test_env_script() {
+        let previous = SessionEnv::default();
+        let mut current = SessionEnv::default();
+        current.set("PROFILE", "it's").unwrap();
+        current.unset("CEPHEUS_UNSET_ME").unwrap();
+
+        assert_eq!(
+            env_script(ShellKind::Sh, &current, &previous),
+            "unset CEPHEUS_UNSET_ME\nexport PROFILE='it'\\''s'\n"
+        );
+        assert_eq!(env_script(ShellKind::Sh, &current, &current), "");
+        // Dropping an override restores the inherited state
+        assert_eq!(
+            env_script(ShellKind::Sh, &previous, &current),
+            "unset PROFILE\n"
+        );
+    }
+
+    #[test]
+    fn test_fish_statements() {
+        assert_eq!(quote_fish(r"it's a \ path"), r"'it\'s a \\ path'");
+
+        let mut current = SessionEnv::default();
+        current.set("PROFILE", "dev").unwrap();
+        current.unset("CEPHEUS_UNSET_ME").unwrap();
+        assert_eq!(
+            env_script(ShellKind::Fish, &current, &SessionEnv::default()),
+            "set -e CEPHEUS_UNSET_ME\nset -gx PROFILE 'dev'\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interactive_login_bash_output_is_clean() {
+        let bash = Shell::resolve(&ShellConfig {
+            program: Some("bash".to_string()),
+            login: true,
+            interactive: true,
+        });
+        if bash.locate().is_none() {
+            return;
+        }
+        let mut env = SessionEnv::default();
+        env.set("CEPHEUS_PROFILE", "dev").unwrap();
+        let mut shell = PersistentShell::spawn(&bash, &temp_dir(), &env)
+            .await
+            .unwrap();
+
+        // No prompts or job control warnings around the output
+        let (outcome, lines) =
+            run_collect(&mut shell, "echo $- | grep -q i && echo $CEPHEUS_PROFILE").await;
+        assert!(matches!(
+            outcome,
+            RunOutcome::Completed { exit_code: 0, .. }
+        ));
+        assert_eq!(lines, vec![Line::Stdout("dev".to_string())]);
+        let (_, lines) = run_collect(&mut shell, "[[ -n $BASH_VERSION ]] && echo bash").await;
+        assert_eq!(lines, vec![Line::Stdout("bash".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_session_env_applied_and_synced() {
+        let mut env = SessionEnv::default();
+        env.set("CEPHEUS_PROFILE", "dev").unwrap();
+        let mut shell = PersistentShell::spawn(&Shell::default(), &temp_dir(), &env)
+            .await
+            .unwrap();
+
+        let (_, lines) = run_collect(&mut shell, "echo $CEPHEUS_PROFILE").await;
+        assert_eq!(lines, vec![Line::Stdout("dev".to_string())]);
+
+        env.set("CEPHEUS_PROFILE", "prod").unwrap();
+        shell.sync_env(&env);
+        let (_, lines) = run_collect(&mut shell, "echo $CEPHEUS_PROFILE").await;
+        assert_eq!(lines, vec![Line::Stdout("prod".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_state_persists_between_commands() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+
+        let (outcome, _) =
+            run_collect(&mut shell, "export FOO=persisted; greet() { echo hi $1; }").await;
+        assert!(matches!(
+            outcome,
+            RunOutcome::Completed { exit_code: 0, .. }
+        ));
+
+        let (_, lines) = run_collect(&mut shell, "echo $FOO; greet there").await;
+        assert_eq!(
+            lines,
+            vec![
+                Line::Stdout("persisted".to_string()),
+                Line::Stdout("hi there".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exit_code_and_cwd_are_recovered() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+
+        let (outcome, lines) = run_collect(&mut shell, "cd / && echo oops >&2; false").await;
+        assert_eq!(
+            outcome,
+            RunOutcome::Completed {
+                exit_code: 1,
+                cwd: "/".to_string()
+            }
+        );
+        assert_eq!(lines, vec![Line::Stderr("oops".to_string())]);
+        assert_eq!(shell.cwd(), "/");
+    }
+
+    #[tokio::test]
+    async fn test_output_without_trailing_newline() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+
+        let (outcome, lines) = run_collect(&mut shell, "printf 'no newline'").await;
+        assert!(matches!(
+            outcome,
+            RunOutcome::Completed { exit_code: 0, .. }
+        ));
+        assert_eq!(lines, vec![Line::Stdout("no newline".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8_output_keeps_shell() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+
+        run_collect(&mut shell, "export FOO=kept; cd /").await;
+        let (outcome, lines) = run_collect(&mut shell, "printf '\\377\\n'").await;
+        assert!(matches!(
+            outcome,
+            RunOutcome::Completed { exit_code: 0, .. }
+        ));
+        assert_eq!(lines, vec![Line::Stdout("\u{fffd}".to_string())]);
+
+        let (_, lines) = run_collect(&mut shell, "echo $FOO; pwd").await;
+        assert_eq!(
+            lines,
+            vec![
+                Line::Stdout("kept".to_string()),
+                Line::Stdout("/".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unterminated_output_is_handed_over() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+
+        let mut events = Vec::new();
+        let cwd = shell.cwd().to_string();
+        shell
+            .run(
+                "printf 'Password: '; sleep 0.3; echo",
+                &cwd,
+                IDLE,
+                |output| {
+                    events.push(output);
+                },
+            )
+            .await
+            .unwrap();
+        // The prompt arrives without waiting for its newline, then output goes quiet
+        assert_eq!(
+            events[..2],
+            [
+                ShellOutput::Stdout(b"Password: ".to_vec()),
+                ShellOutput::Idle
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_syntax_error_does_not_hang() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+
+        let (outcome, _) = run_collect(&mut shell, "echo 'unbalanced").await;
+        assert!(matches!(outcome, RunOutcome::Completed { exit_code, .. } if exit_code != 0));
+
+        let (_, lines) = run_collect(&mut shell, "echo still alive").await;
+        assert_eq!(lines, vec![Line::Stdout("still alive".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_stops_command_but_not_shell() {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+        let pgid = i32::try_from(shell.id().unwrap()).unwrap();
+
+        let interrupter = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            killpg(Pid::from_raw(pgid), Signal::SIGINT).unwrap();
+        });
+
+        let (outcome, _) = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            run_collect(&mut shell, "sleep 30"),
+        )
+        .await
+        .expect("interrupted command should finish quickly");
+        interrupter.await.unwrap();
+
+        assert!(matches!(outcome, RunOutcome::Completed { exit_code, .. } if exit_code != 0));
+
+        let (_, lines) = run_collect(&mut shell, "echo after").await;
+        assert_eq!(lines, vec![Line::Stdout("after".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_command_reads_input_written_while_running() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+        let stdin = shell.stdin();
+
+        let writer = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            stdin
+                .lock()
+                .await
+                .write_all(b"typed answer\n")
+                .await
+                .unwrap();
+        });
+
+        let (outcome, lines) = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            run_collect(&mut shell, "read answer; echo \"got $answer\""),
+        )
+        .await
+        .expect("command should finish once input arrives");
+        writer.await.unwrap();
+
+        assert!(matches!(
+            outcome,
+            RunOutcome::Completed { exit_code: 0, .. }
+        ));
+        assert_eq!(lines, vec![Line::Stdout("got typed answer".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_exit_reports_shell_exit() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+
+        let (outcome, _) = run_collect(&mut shell, "exit 7").await;
+        assert_eq!(outcome, RunOutcome::ShellExited { exit_code: Some(7) });
+    }
+
+    #[tokio::test]
+    async fn test_changes_into_requested_directory() {
+        let mut shell =
+            PersistentShell::spawn(&Shell::default(), &temp_dir(), &SessionEnv::default())
+                .await
+                .unwrap();
+
+        let outcome = shell.run("pwd", "/", IDLE, |_| {}).await.unwrap();
+        assert_eq!(
+            outcome,
+            RunOutcome::Completed {
+                exit_code: 0,
+                cwd: "/".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::process::Stdio;
+
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg, FdFlag, OFlag};
+use nix::pty::{openpty, Winsize};
+use tokio::io::unix::AsyncFd;
+use tokio::process::{Child, Command};
+
+use crate::models::PtySize;
+
+nix::ioctl_write_ptr_bad!(set_window_size, nix::libc::TIOCSWINSZ, Winsize);
+nix::ioctl_write_int_bad!(set_controlling_terminal, nix::libc::TIOCSCTTY);
+
+const fn to_winsize(size: PtySize) -> Winsize {
+    Winsize {
+        ws_row: size.rows,
+        ws_col: size.cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+/// Master side of a pseudo-terminal.
+///
+/// Shared between the output reader task, resize requests, and cancellation
+/// (which writes Ctrl+C to the terminal instead of signalling a single PID).
+pub struct PtyMaster {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl PtyMaster {
+    /// Read the next chunk of raw output.
+    /// Returns `Ok(0)` once every slave descriptor has been closed.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let mut guard = self.fd.readable().await?;
+            match guard
+                .try_io(|inner| nix::unistd::read(inner.get_ref(), buf).map_err(io::Error::from))
+            {
+                Ok(Ok(n)) => return Ok(n),
+                // Linux reports EIO on the master once the slave side has hung up
+                Ok(Err(e)) if e.raw_os_error() == Some(Errno::EIO as i32) => return Ok(0),
+                Ok(Err(e)) => return Err(e),
+                Err(_would_block) => {}
+            }
+        }
+    }
+
+    /// Write all bytes to the terminal, as if typed by the user.
+    pub async fn write_all(&self, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let mut guard = self.fd.writable().await?;
+            match guard
+                .try_io(|inner| nix::unistd::write(inner.get_ref(), data).map_err(io::Error::from))
+            {
+                Ok(Ok(n)) => data = &data[n..],
+                Ok(Err(e)) => return Err(e),
+                Err(_would_block) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the terminal window size; the foreground process receives `SIGWINCH`.
+    pub fn resize(&self, size: PtySize) -> io::Result<()> {
+        let winsize = to_winsize(size);
+        // SAFETY: the descriptor is an open PTY master owned by `self`, and `winsize`
+        // outlives the call.
+        #[allow(unsafe_code)]
+        unsafe {
+            set_window_size(self.fd.get_ref().as_raw_fd(), std::ptr::from_ref(&winsize))?;
+        }
+        Ok(())
+    }
+}
+
+/// Spawn `cmd` attached to a new pseudo-terminal of the given size.
+///
+/// The child becomes a session leader with the PTY as its controlling terminal, so
+/// programs see a real TTY (`isatty` succeeds, colors and column layout are enabled).
+/// `cmd` is consumed so the parent's copies of the slave descriptor are closed once
+/// the child is running; otherwise the master would never observe end-of-output.
+pub fn spawn(mut cmd: Command, size: PtySize) -> io::Result<(Child, PtyMaster)> {
+    let pty = openpty(&to_winsize(size), None)?;
+
+    // Neither end should leak into unrelated children; stdio dup2 clears the flag on 0/1/2.
+    fcntl(&pty.master, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+    fcntl(&pty.slave, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+    fcntl(&pty.master, FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+
+    let slave = pty.slave;
+    cmd.stdin(Stdio::from(slave.try_clone()?))
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+
+    // SAFETY: only async-signal-safe calls (setsid, ioctl) run between fork and exec.
+    #[allow(unsafe_code)]
+    unsafe {
+        cmd.pre_exec(|| {
+            nix::unistd::setsid()?;
+            set_controlling_terminal(0, 0)?;
+            Ok(())
+        });
+    }
+
+    let child = cmd.spawn()?;
+    drop(cmd);
+
+    let master = PtyMaster {
+        fd: AsyncFd::new(pty.master)?,
+    };
+    Ok((child, master))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read_to_end(master: &PtyMaster) -> String {
+        let mut output = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = master.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_pty_child_sees_a_tty() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("if [ -t 1 ]; then echo tty; else echo notty; fi");
+
+        let (mut child, master) = spawn(cmd, PtySize::default()).unwrap();
+        let output = read_to_end(&master).await;
+        let status = child.wait().await.unwrap();
+
+        assert!(status.success());
+        assert!(output.contains("tty"));
+        assert!(!output.contains("notty"));
+    }
+
+    #[tokio::test]
+    async fn test_pty_reports_window_size() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("stty size");
+
+        let (mut child, master) = spawn(cmd, PtySize::new(33, 101)).unwrap();
+        let output = read_to_end(&master).await;
+        child.wait().await.unwrap();
+
+        assert!(
+            output.contains("33 101"),
+            "unexpected stty output: {output:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pty_resize_is_visible_to_child() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("read _ ; stty size");
+
+        let (mut child, master) = spawn(cmd, PtySize::default()).unwrap();
+        master.resize(PtySize::new(50, 120)).unwrap();
+        master.write_all(b"go\n").await.unwrap();
+        let output = read_to_end(&master).await;
+        child.wait().await.unwrap();
+
+        assert!(
+            output.contains("50 120"),
+            "unexpected stty output: {output:?}"
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use tokio::process::Command;
+
+use crate::models::ShellConfig;
+
+/// Shell used when neither the configuration nor `$SHELL` names one
+#[cfg(not(windows))]
+const FALLBACK_PROGRAM: &str = "sh";
+#[cfg(windows)]
+const FALLBACK_PROGRAM: &str = "cmd";
+
+/// Command language of a shell, which decides how commands and flags are passed to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    /// POSIX `sh` and compatibles (`dash`, `ksh`)
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    Cmd,
+    PowerShell,
+}
+
+impl ShellKind {
+    /// Identify a shell from its program name or path (`/bin/zsh`, `pwsh.exe`)
+    pub fn of(program: &str) -> Self {
+        let name = Path::new(program)
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or(program);
+        // Login shells are sometimes named with a leading dash (`-zsh`)
+        match name.trim_start_matches('-').to_ascii_lowercase().as_str() {
+            "bash" | "rbash" => Self::Bash,
+            "zsh" => Self::Zsh,
+            "fish" => Self::Fish,
+            "cmd" => Self::Cmd,
+            "powershell" | "pwsh" => Self::PowerShell,
+            _ => Self::Sh,
+        }
+    }
+
+    /// Does the shell take `-l`/`-i` startup flags and `-c <command>`?
+    pub const fn is_unix_style(self) -> bool {
+        matches!(self, Self::Sh | Self::Bash | Self::Zsh | Self::Fish)
+    }
+}
+
+/// The shell a session runs its commands with, resolved from a [`ShellConfig`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shell {
+    /// Program name or path, as configured
+    pub program: String,
+    pub kind: ShellKind,
+    /// Started as a login shell
+    pub login: bool,
+    /// Started as an interactive shell
+    pub interactive: bool,
+}
+
+impl Shell {
+    /// Resolve `config`, using `$SHELL` (Unix) when it names no program
+    pub fn resolve(config: &ShellConfig) -> Self {
+        #[cfg(unix)]
+        let login_shell = std::env::var("SHELL").ok();
+        #[cfg(not(unix))]
+        let login_shell = None;
+        Self::resolve_with(config, login_shell.as_deref())
+    }
+
+    /// Resolve `config` with `env_shell` standing in for `$SHELL`
+    fn resolve_with(config: &ShellConfig, env_shell: Option<&str>) -> Self {
+        let program = config
+            .program
+            .as_deref()
+            .map(str::trim)
+            .filter(|program| !program.is_empty())
+            .or_else(|| env_shell.map(str::trim).filter(|shell| !shell.is_empty()))
+            .unwrap_or(FALLBACK_PROGRAM)
+            .to_string();
+        Self {
+            kind: ShellKind::of(&program),
+            program,
+            login: config.login,
+            interactive: config.interactive,
+        }
+    }
+
+    /// Flags selecting login and interactive startup (Unix-style shells only)
+    pub fn startup_flags(&self) -> Vec<&'static str> {
+        let mut flags = Vec::new();
+        if self.kind.is_unix_style() {
+            if self.login {
+                flags.push("-l");
+            }
+            if self.interactive {
+                flags.push("-i");
+            }
+        }
+        flags
+    }
+
+    /// Build the process running `command` with this shell
+    pub fn command(&self, command: &str) -> Command {
+        let mut cmd = Command::new(&self.program);
+        match self.kind {
+            ShellKind::Cmd => {
+                cmd.arg("/C");
+            }
+            ShellKind::PowerShell => {
+                cmd.arg("-NoLogo");
+                // The profile plays the part of an rc file
+                if !self.interactive {
+                    cmd.arg("-NoProfile");
+                }
+                cmd.arg("-Command");
+            }
+            ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh | ShellKind::Fish => {
+                cmd.args(self.startup_flags()).arg("-c");
+            }
+        }
+        cmd.arg(command);
+        cmd
+    }
+
+    /// Find the shell's executable, searching `PATH` for a bare name
+    pub fn locate(&self) -> Option<PathBuf> {
+        find_program(&self.program, std::env::var_os("PATH").as_deref())
+    }
+}
+
+impl Default for Shell {
+    /// Non-login, non-interactive `sh` (`cmd` on Windows), regardless of `$SHELL`
+    fn default() -> Self {
+        Self::resolve_with(&ShellConfig::default(), None)
+    }
+}
+
+/// Find `program` on `path` unless it is already a path
+fn find_program(program: &str, path: Option<&OsStr>) -> Option<PathBuf> {
+    let candidate = Path::new(program);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+    std::env::split_paths(path?).find_map(|dir| {
+        let full = dir.join(program);
+        if full.is_file() {
+            return Some(full);
+        }
+        #[cfg(windows)]
+        {
+            let exe = full.with_extension("exe");
+            if exe.is_file() {
+                return Some(exe);
+            }
+        }
+        None
+    })
+}
+
+/// Shells installed on this system: those listed in `/etc/shells` that exist, in the
+/// order listed (`cmd` and PowerShell on Windows).
+pub fn available_shells() -> Vec<String> {
+    #[cfg(unix)]
+    let listed = parse_shells(&std::fs::read_to_string("/etc/shells").unwrap_or_default());
+    #[cfg(not(unix))]
+    let listed = ["cmd", "powershell", "pwsh"].map(String::from).to_vec();
+
+    let path = std::env::var_os("PATH");
+    listed
+        .into_iter()
+        .filter(|program| find_program(program, path.as_deref()).is_some())
+        .collect()
+}
+
+/// Shell paths listed in an `/etc/shells` file, without comments or duplicates
+#[cfg(any(unix, test))]
+fn parse_shells(contents: &str) -> Vec<String> {
+    let mut shells: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if !line.is_empty() && !shells.iter().any(|shell| shell == line) {
+            shells.push(line.to_string());
+        }
+    }
+    shells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(program: Option<&str>) -> ShellConfig {
+        ShellConfig {
+            program: program.map(String::from),
+            ..ShellConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_kind_of_program() {
+        assert_eq!(ShellKind::of("/usr/bin/zsh"), ShellKind::Zsh);
+        assert_eq!(ShellKind::of("bash"), ShellKind::Bash);
+        assert_eq!(ShellKind::of("-fish"), ShellKind::Fish);
+        assert_eq!(ShellKind::of("/bin/dash"), ShellKind::Sh);
+        assert_eq!(ShellKind::of("pwsh.exe"), ShellKind::PowerShell);
+        assert_eq!(ShellKind::of("CMD.EXE"), ShellKind::Cmd);
+    }
+
+    #[test]
+    fn test_resolve_prefers_config_then_env_shell() {
+        let shell = Shell::resolve_with(&config(Some("fish")), Some("/bin/zsh"));
+        assert_eq!(shell.program, "fish");
+        assert_eq!(shell.kind, ShellKind::Fish);
+
+        let shell = Shell::resolve_with(&config(None), Some("/bin/zsh"));
+        assert_eq!(shell.program, "/bin/zsh");
+        assert_eq!(shell.kind, ShellKind::Zsh);
+
+        // A blank program or `$SHELL` counts as unset
+        let shell = Shell::resolve_with(&config(Some(" ")), Some(""));
+        assert_eq!(shell.program, FALLBACK_PROGRAM);
+        assert_eq!(Shell::default().program, FALLBACK_PROGRAM);
+    }
+
+    #[test]
+    fn test_command_flags() {
+        let shell = Shell::resolve_with(
+            &ShellConfig {
+                program: Some("bash".to_string()),
+                login: true,
+                interactive: true,
+            },
+            None,
+        );
+        let cmd = shell.command("echo hi");
+        let std_cmd = cmd.as_std();
+        assert_eq!(std_cmd.get_program(), "bash");
+        let args: Vec<_> = std_cmd.get_args().collect();
+        assert_eq!(args, ["-l", "-i", "-c", "echo hi"]);
+
+        let shell = Shell::resolve_with(&config(Some("pwsh")), None);
+        let cmd = shell.command("Get-Date");
+        let args: Vec<_> = cmd.as_std().get_args().collect();
+        assert_eq!(args, ["-NoLogo", "-NoProfile", "-Command", "Get-Date"]);
+
+        let shell = Shell::resolve_with(&config(Some("cmd")), None);
+        assert!(shell.startup_flags().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_one_shot_command_runs() {
+        let output = Shell::default()
+            .command("echo $((1 + 2))")
+            .output()
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_locate() {
+        assert!(Shell::default().locate().is_some());
+        assert!(Shell::resolve_with(&config(Some("/bin/sh")), None)
+            .locate()
+            .is_some());
+        assert!(
+            Shell::resolve_with(&config(Some("cepheus-no-such-shell")), None)
+                .locate()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_shells() {
+        let contents =
+            "# /etc/shells: valid login shells\n/bin/sh\n\n/bin/zsh # default\n/bin/sh\n";
+        assert_eq!(parse_shells(contents), ["/bin/sh", "/bin/zsh"]);
+    }
+}
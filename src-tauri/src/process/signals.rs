@@ -0,0 +1,304 @@
+use std::time::Duration;
+
+/// Signals sent to a command's process group. The first three form the cancel
+/// escalation ladder, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobSignal {
+    /// Ask the command to stop (Ctrl+C)
+    Interrupt,
+    /// Ask the command to terminate
+    Terminate,
+    /// Stop the command without giving it a chance to clean up
+    Kill,
+    /// Suspend the command (Ctrl+Z)
+    Stop,
+    /// Suspend the command even if it ignores Ctrl+Z
+    ForceStop,
+    /// Resume a suspended command
+    Continue,
+    /// The command's terminal went away (its session was closed)
+    Hangup,
+}
+
+impl JobSignal {
+    /// Conventional name of the signal, for notifications and logs
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Interrupt => "SIGINT",
+            Self::Terminate => "SIGTERM",
+            Self::Kill => "SIGKILL",
+            Self::Stop => "SIGTSTP",
+            Self::ForceStop => "SIGSTOP",
+            Self::Continue => "SIGCONT",
+            Self::Hangup => "SIGHUP",
+        }
+    }
+
+    /// Signal to send if a cancelled command is still running after this one
+    pub const fn next(self) -> Option<Self> {
+        match self {
+            Self::Interrupt => Some(Self::Terminate),
+            Self::Terminate => Some(Self::Kill),
+            Self::Kill | Self::Stop | Self::ForceStop | Self::Continue | Self::Hangup => None,
+        }
+    }
+
+    #[cfg(unix)]
+    const fn unix_signal(self) -> nix::sys::signal::Signal {
+        use nix::sys::signal::Signal;
+
+        match self {
+            Self::Interrupt => Signal::SIGINT,
+            Self::Terminate => Signal::SIGTERM,
+            Self::Kill => Signal::SIGKILL,
+            Self::Stop => Signal::SIGTSTP,
+            Self::ForceStop => Signal::SIGSTOP,
+            Self::Continue => Signal::SIGCONT,
+            Self::Hangup => Signal::SIGHUP,
+        }
+    }
+}
+
+/// Conventional name of signal number `signal` (`SIGSEGV`), for reporting how a process
+/// ended.
+#[cfg(unix)]
+pub fn signal_name(signal: i32) -> String {
+    nix::sys::signal::Signal::try_from(signal).map_or_else(
+        |_| format!("signal {signal}"),
+        |signal| signal.as_str().to_string(),
+    )
+}
+
+/// Conventional name of signal number `signal`, for reporting how a process ended.
+#[cfg(not(unix))]
+pub fn signal_name(signal: i32) -> String {
+    format!("signal {signal}")
+}
+
+/// How long a cancelled command may keep running before the next, harsher signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EscalationPolicy {
+    /// Wait after `SIGINT` before sending `SIGTERM`
+    pub terminate_after: Duration,
+    /// Wait after `SIGTERM` before sending `SIGKILL`
+    pub kill_after: Duration,
+}
+
+impl EscalationPolicy {
+    /// Delay before escalating from `signal` to the next one
+    pub const fn delay_after(&self, signal: JobSignal) -> Option<Duration> {
+        match signal {
+            JobSignal::Interrupt => Some(self.terminate_after),
+            JobSignal::Terminate => Some(self.kill_after),
+            JobSignal::Kill
+            | JobSignal::Stop
+            | JobSignal::ForceStop
+            | JobSignal::Continue
+            | JobSignal::Hangup => None,
+        }
+    }
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        Self {
+            terminate_after: Duration::from_secs(3),
+            kill_after: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Send `signal` to process group `group`, or to `pid` alone if it has no group of its own.
+/// A process that already exited is not an error.
+#[cfg(unix)]
+#[allow(clippy::unused_async)]
+pub async fn send(pid: u32, group: Option<u32>, signal: JobSignal) -> Result<(), String> {
+    use nix::errno::Errno;
+    use nix::sys::signal;
+    use nix::unistd::Pid;
+
+    let name = signal.name();
+    let result = if let Some(group) = group {
+        let group = i32::try_from(group)
+            .map_err(|_| "PGID out of range for Unix signal delivery".to_string())?;
+        signal::killpg(Pid::from_raw(group), signal.unix_signal())
+    } else {
+        let pid = i32::try_from(pid)
+            .map_err(|_| "PID out of range for Unix signal delivery".to_string())?;
+        signal::kill(Pid::from_raw(pid), signal.unix_signal())
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(Errno::ESRCH) => {
+            tracing::warn!("{} skipped: process {} no longer exists (ESRCH)", name, pid);
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to send {name}: {e}")),
+    }
+}
+
+/// Send `signal` to every process in group `group` except `spared`, such as a shell
+/// waiting for the command it started. If `spared` has the group to itself, the whole
+/// group is signalled.
+#[cfg(unix)]
+pub async fn send_sparing(group: u32, spared: u32, signal: JobSignal) -> Result<(), String> {
+    let targets: Vec<u32> = group_members(group)
+        .await?
+        .into_iter()
+        .filter(|&pid| pid != spared)
+        .collect();
+    if targets.is_empty() {
+        return send(spared, Some(group), signal).await;
+    }
+    for pid in targets {
+        send(pid, None, signal).await?;
+    }
+    Ok(())
+}
+
+/// Processes in process group `group`, as listed by `ps`
+#[cfg(unix)]
+async fn group_members(group: u32) -> Result<Vec<u32>, String> {
+    let output = tokio::process::Command::new("ps")
+        .args(["-A", "-o", "pid=", "-o", "pgid="])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to list processes: {e}"))?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().map(str::parse::<u32>);
+            match (fields.next(), fields.next()) {
+                (Some(Ok(pid)), Some(Ok(member_of))) if member_of == group => Some(pid),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// Stop process `pid` and its descendants with `taskkill`; `Kill` and `Hangup` force
+/// them (`/F`). Suspending and resuming are not supported.
+#[cfg(windows)]
+pub async fn send(pid: u32, _group: Option<u32>, signal: JobSignal) -> Result<(), String> {
+    let mut taskkill = tokio::process::Command::new("taskkill");
+    taskkill.arg("/PID").arg(pid.to_string()).arg("/T");
+    match signal {
+        JobSignal::Interrupt | JobSignal::Terminate => {}
+        JobSignal::Kill | JobSignal::Hangup => {
+            taskkill.arg("/F");
+        }
+        JobSignal::Stop | JobSignal::ForceStop | JobSignal::Continue => {
+            return Err("Suspending jobs is not supported on this platform".to_string());
+        }
+    }
+
+    let output = taskkill
+        .output()
+        .await
+        .map_err(|e| format!("Failed to spawn taskkill: {e}"))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "taskkill failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+#[allow(clippy::unused_async)]
+pub async fn send(_pid: u32, _group: Option<u32>, _signal: JobSignal) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalation_order() {
+        let policy = EscalationPolicy::default();
+        let mut signal = JobSignal::Interrupt;
+        let mut ladder = vec![signal.name()];
+        while let Some(next) = signal.next() {
+            assert!(policy.delay_after(signal).is_some());
+            signal = next;
+            ladder.push(signal.name());
+        }
+        assert_eq!(ladder, ["SIGINT", "SIGTERM", "SIGKILL"]);
+        assert_eq!(policy.delay_after(JobSignal::Kill), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signal_name() {
+        assert_eq!(signal_name(11), "SIGSEGV");
+        assert_eq!(signal_name(9), "SIGKILL");
+        assert_eq!(signal_name(-1), "signal -1");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_signal_reaches_whole_group() {
+        use std::process::Stdio;
+        use tokio::io::AsyncReadExt;
+
+        // The background sleep would survive a signal sent to the shell alone, keeping
+        // stdout open
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg("sleep 30 & wait")
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        send(pid, Some(pid), JobSignal::Kill).await.unwrap();
+        child.wait().await.unwrap();
+
+        let mut rest = Vec::new();
+        tokio::time::timeout(Duration::from_secs(2), stdout.read_to_end(&mut rest))
+            .await
+            .expect("Background child should be killed with its group")
+            .unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sparing_signal_leaves_shell_running() {
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg("sleep 30; exit 3")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        send_sparing(pid, pid, JobSignal::Kill).await.unwrap();
+        let status = tokio::time::timeout(Duration::from_secs(2), child.wait())
+            .await
+            .expect("Only the sleep should be killed")
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_signal_to_exited_process_is_ok() {
+        let mut child = tokio::process::Command::new("true")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        child.wait().await.unwrap();
+
+        assert!(send(pid, Some(pid), JobSignal::Interrupt).await.is_ok());
+    }
+}
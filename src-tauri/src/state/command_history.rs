@@ -0,0 +1,399 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::models::{CommandHistoryEntry, HistoryQuery};
+
+/// Command lines entered in all sessions, most recent last, for recall and search.
+///
+/// Each command appears once: running it again moves it to the end. When opened from a
+/// file, every recorded command is appended to it as a JSON line, and the file is
+/// rewritten without duplicates once it holds twice as many lines as entries.
+pub struct CommandHistory {
+    capacity: usize,
+    /// Backing file, if the history is persisted
+    path: Option<PathBuf>,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    entries: Vec<CommandHistoryEntry>,
+    /// Lines in the backing file, including superseded duplicates
+    lines_on_disk: usize,
+}
+
+impl CommandHistory {
+    /// Number of commands kept
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    /// Create an empty history kept in memory only
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            path: None,
+            inner: Mutex::new(Inner {
+                entries: Vec::new(),
+                lines_on_disk: 0,
+            }),
+        }
+    }
+
+    /// Default location of the history file, `~/.cepheus/command_history`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs_next::home_dir().map(|home| home.join(".cepheus").join("command_history"))
+    }
+
+    /// Load the history saved in `path` (creating its directory if needed) and record
+    /// new commands to it. A line that is not valid JSON is skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created or the file cannot be read.
+    pub fn open(path: impl Into<PathBuf>, capacity: usize) -> std::io::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut history = Self::new(capacity);
+        let mut entries = Vec::new();
+        let mut lines_on_disk = 0;
+        match File::open(&path) {
+            Ok(file) => {
+                for text in BufReader::new(file).lines() {
+                    let text = text?;
+                    lines_on_disk += 1;
+                    match serde_json::from_str(&text) {
+                        Ok(entry) => insert(&mut entries, entry, history.capacity),
+                        Err(e) => {
+                            tracing::warn!("Skipping corrupt history line in {:?}: {}", path, e);
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        history.path = Some(path);
+        history.inner = Mutex::new(Inner {
+            entries,
+            lines_on_disk,
+        });
+        let mut inner = history
+            .inner
+            .lock()
+            .expect("CommandHistory::open failed to acquire lock on entries");
+        if inner.lines_on_disk > inner.entries.len() {
+            history.compact(&mut inner);
+        }
+        drop(inner);
+        Ok(history)
+    }
+
+    /// Record a command, moving an earlier run of the same command line to the end.
+    /// Blank commands are ignored.
+    pub fn record(&self, entry: CommandHistoryEntry) {
+        if entry.command.trim().is_empty() {
+            return;
+        }
+
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("CommandHistory::record failed to acquire lock on entries");
+        self.append(&mut inner, &entry);
+        insert(&mut inner.entries, entry, self.capacity);
+        if inner.lines_on_disk >= inner.entries.len() * 2 {
+            self.compact(&mut inner);
+        }
+    }
+
+    /// The last `limit` commands (all when `None`), oldest first
+    pub fn recent(&self, limit: Option<usize>) -> Vec<CommandHistoryEntry> {
+        let inner = self
+            .inner
+            .lock()
+            .expect("CommandHistory::recent failed to acquire lock on entries");
+        let skip = limit.map_or(0, |limit| inner.entries.len().saturating_sub(limit));
+        inner.entries[skip..].to_vec()
+    }
+
+    /// Commands matching `query`: those containing its text first, then those matching
+    /// it fuzzily, each most recent first
+    pub fn search(&self, query: &HistoryQuery) -> Vec<CommandHistoryEntry> {
+        let text = query.text.to_lowercase();
+        let inner = self
+            .inner
+            .lock()
+            .expect("CommandHistory::search failed to acquire lock on entries");
+        let mut matches: Vec<_> = inner
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| query.cwd.as_ref().is_none_or(|cwd| entry.cwd == *cwd))
+            .filter(|entry| {
+                query
+                    .status
+                    .is_none_or(|status| status.matches(entry.exit_code))
+            })
+            .filter_map(|entry| Some((match_rank(&entry.command, &text)?, entry.clone())))
+            .collect();
+        drop(inner);
+        // Stable, so recency order is kept within a rank
+        matches.sort_by_key(|(rank, _)| *rank);
+        matches
+            .into_iter()
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    /// Number of commands in the history
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .expect("CommandHistory::len failed to acquire lock on entries")
+            .entries
+            .len()
+    }
+
+    /// Check if no command was recorded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn append(&self, inner: &mut Inner, entry: &CommandHistoryEntry) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut text = match serde_json::to_string(entry) {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!("Failed to serialize history entry: {}", e);
+                return;
+            }
+        };
+        text.push('\n');
+
+        // One write per line, so a crash loses at most the line being written
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(text.as_bytes()));
+        match written {
+            Ok(()) => inner.lines_on_disk += 1,
+            Err(e) => tracing::warn!("Failed to write history file {:?}: {}", path, e),
+        }
+    }
+
+    /// Rewrite the backing file with the current entries only. The new file replaces
+    /// the old one once complete, so a crash leaves one or the other.
+    fn compact(&self, inner: &mut Inner) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut text = String::new();
+        for entry in &inner.entries {
+            match serde_json::to_string(entry) {
+                Ok(line) => {
+                    text.push_str(&line);
+                    text.push('\n');
+                }
+                Err(e) => tracing::warn!("Failed to serialize history entry: {}", e),
+            }
+        }
+
+        let temp_path = path.with_extension("tmp");
+        match fs::write(&temp_path, text).and_then(|()| fs::rename(&temp_path, path)) {
+            Ok(()) => inner.lines_on_disk = inner.entries.len(),
+            Err(e) => tracing::warn!("Failed to rewrite history file {:?}: {}", path, e),
+        }
+    }
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+/// Add `entry` to `entries` in place of an earlier run of the same command, dropping the
+/// oldest entries beyond `capacity`
+fn insert(entries: &mut Vec<CommandHistoryEntry>, entry: CommandHistoryEntry, capacity: usize) {
+    entries.retain(|existing| existing.command != entry.command);
+    entries.push(entry);
+    let excess = entries.len().saturating_sub(capacity);
+    entries.drain(..excess);
+}
+
+/// How well `command` matches the lowercase `text`: 0 if it contains it, 1 if it has its
+/// characters in order, `None` if neither
+fn match_rank(command: &str, text: &str) -> Option<u8> {
+    let command = command.to_lowercase();
+    if command.contains(text) {
+        return Some(0);
+    }
+    let mut chars = command.chars();
+    text.chars()
+        .all(|wanted| chars.any(|c| c == wanted))
+        .then_some(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ExitStatusFilter;
+
+    fn entry(command: &str, timestamp: u64) -> CommandHistoryEntry {
+        CommandHistoryEntry {
+            command: command.to_string(),
+            cwd: "/tmp".to_string(),
+            exit_code: Some(0),
+            timestamp,
+        }
+    }
+
+    fn commands(entries: &[CommandHistoryEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.command.as_str()).collect()
+    }
+
+    fn test_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cepheus-history-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("command_history")
+    }
+
+    #[test]
+    fn test_rerun_moves_command_to_end() {
+        let history = CommandHistory::new(10);
+        history.record(entry("ls", 1));
+        history.record(entry("git status", 2));
+        history.record(entry("ls", 3));
+        history.record(entry("   ", 4));
+
+        let recent = history.recent(None);
+        assert_eq!(commands(&recent), ["git status", "ls"]);
+        assert_eq!(recent[1].timestamp, 3);
+        assert_eq!(commands(&history.recent(Some(1))), ["ls"]);
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let history = CommandHistory::new(2);
+        for (i, command) in ["a", "b", "c"].into_iter().enumerate() {
+            history.record(entry(command, i as u64));
+        }
+        assert_eq!(commands(&history.recent(None)), ["b", "c"]);
+    }
+
+    fn query(text: &str) -> HistoryQuery {
+        HistoryQuery {
+            text: text.to_string(),
+            ..HistoryQuery::default()
+        }
+    }
+
+    #[test]
+    fn test_search_is_newest_first_and_ignores_case() {
+        let history = CommandHistory::new(10);
+        history.record(entry("git status", 1));
+        history.record(entry("ls -la", 2));
+        history.record(entry("git Log", 3));
+
+        assert_eq!(
+            commands(&history.search(&query("GIT"))),
+            ["git Log", "git status"]
+        );
+        let limited = HistoryQuery {
+            limit: Some(1),
+            ..query("git")
+        };
+        assert_eq!(commands(&history.search(&limited)), ["git Log"]);
+        assert!(history.search(&query("cargo")).is_empty());
+        assert_eq!(history.search(&query("")).len(), 3);
+    }
+
+    #[test]
+    fn test_search_ranks_substring_before_fuzzy() {
+        let history = CommandHistory::new(10);
+        history.record(entry("echo gco", 1));
+        history.record(entry("git checkout main", 2));
+        history.record(entry("grep -c foo", 3));
+
+        assert_eq!(
+            commands(&history.search(&query("gco"))),
+            ["echo gco", "grep -c foo", "git checkout main"]
+        );
+        assert!(history.search(&query("ocg")).is_empty());
+    }
+
+    #[test]
+    fn test_search_filters_by_cwd_and_status() {
+        let history = CommandHistory::new(10);
+        history.record(CommandHistoryEntry {
+            cwd: "/src".to_string(),
+            ..entry("make", 1)
+        });
+        history.record(CommandHistoryEntry {
+            exit_code: Some(2),
+            ..entry("make test", 2)
+        });
+        history.record(CommandHistoryEntry {
+            exit_code: None,
+            ..entry("make bench", 3)
+        });
+
+        let in_src = HistoryQuery {
+            cwd: Some("/src".to_string()),
+            ..query("make")
+        };
+        assert_eq!(commands(&history.search(&in_src)), ["make"]);
+
+        let failed = HistoryQuery {
+            status: Some(ExitStatusFilter::Failed),
+            ..query("make")
+        };
+        assert_eq!(
+            commands(&history.search(&failed)),
+            ["make bench", "make test"]
+        );
+    }
+
+    #[test]
+    fn test_history_persists_and_compacts() {
+        let path = test_path("persist");
+        let history = CommandHistory::open(&path, 10).unwrap();
+        assert!(history.is_empty());
+        history.record(entry("make", 1));
+        history.record(entry("make test", 2));
+        history.record(entry("make", 3));
+
+        let reopened = CommandHistory::open(&path, 10).unwrap();
+        assert_eq!(commands(&reopened.recent(None)), ["make test", "make"]);
+        // Superseded lines are dropped when the file is reopened
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        for i in 0..10 {
+            reopened.record(entry("make", 10 + i));
+        }
+        assert!(fs::read_to_string(&path).unwrap().lines().count() <= 4);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_corrupt_line_is_skipped() {
+        let path = test_path("corrupt");
+        CommandHistory::open(&path, 10)
+            .unwrap()
+            .record(entry("kept", 1));
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"command\":\"cut").unwrap();
+
+        let reopened = CommandHistory::open(&path, 10).unwrap();
+        assert_eq!(commands(&reopened.recent(None)), ["kept"]);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::models::{Config, ConfigStatus};
+
+/// What identifies a version of the config file: its modification time and size
+type FileStamp = Option<(SystemTime, u64)>;
+
+/// The user configuration, loaded from a TOML file and reloaded when the file changes.
+///
+/// A file that cannot be read, parsed or validated leaves the previous settings (the
+/// defaults at startup) in effect and is reported in [`ConfigStore::status`]. A missing
+/// file means the defaults.
+pub struct ConfigStore {
+    /// Config file, if there is a home directory to keep it in
+    path: Option<PathBuf>,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    config: Config,
+    error: Option<String>,
+    /// Version of the file last loaded
+    stamp: FileStamp,
+}
+
+impl ConfigStore {
+    /// Default location of the config file, `~/.cepheus/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs_next::home_dir().map(|home| home.join(".cepheus").join("config.toml"))
+    }
+
+    /// Load the configuration from `path`
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let store = Self {
+            path: Some(path.into()),
+            inner: Mutex::new(Inner {
+                config: Config::default(),
+                error: None,
+                stamp: None,
+            }),
+        };
+        store.reload();
+        store
+    }
+
+    /// Settings in effect
+    pub fn config(&self) -> Config {
+        self.inner
+            .lock()
+            .expect("ConfigStore::config failed to acquire lock on inner")
+            .config
+            .clone()
+    }
+
+    /// Settings in effect, with the file location and its problem, if any
+    pub fn status(&self) -> ConfigStatus {
+        let inner = self
+            .inner
+            .lock()
+            .expect("ConfigStore::status failed to acquire lock on inner");
+        ConfigStatus {
+            config: inner.config.clone(),
+            path: self
+                .path
+                .as_ref()
+                .map(|path| path.to_string_lossy().to_string()),
+            error: inner.error.clone(),
+        }
+    }
+
+    /// Reload the file if it changed since it was last loaded. Returns the new status,
+    /// or `None` if the file is unchanged.
+    pub fn reload_if_changed(&self) -> Option<ConfigStatus> {
+        let path = self.path.as_ref()?;
+        let stamp = file_stamp(path);
+        let unchanged = self
+            .inner
+            .lock()
+            .expect("ConfigStore::reload_if_changed failed to acquire lock on inner")
+            .stamp
+            == stamp;
+        if unchanged {
+            return None;
+        }
+        self.reload();
+        Some(self.status())
+    }
+
+    /// Read, parse and apply the file
+    fn reload(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        // Taken before reading, so a write racing the read is picked up next time
+        let stamp = file_stamp(path);
+        let loaded = match fs::read_to_string(path) {
+            Ok(text) => Config::from_toml(&text),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("cannot read file: {e}")),
+        };
+
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("ConfigStore::reload failed to acquire lock on inner");
+        inner.stamp = stamp;
+        match loaded {
+            Ok(config) => {
+                inner.config = config;
+                inner.error = None;
+            }
+            Err(e) => {
+                tracing::warn!("Invalid config file {:?}: {}", path, e);
+                inner.error = Some(e);
+            }
+        }
+    }
+}
+
+impl Default for ConfigStore {
+    /// Default settings, not backed by a file
+    fn default() -> Self {
+        Self {
+            path: None,
+            inner: Mutex::new(Inner {
+                config: Config::default(),
+                error: None,
+                stamp: None,
+            }),
+        }
+    }
+}
+
+fn file_stamp(path: &Path) -> FileStamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cepheus-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("config.toml")
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let store = ConfigStore::load(temp_path("missing"));
+        let status = store.status();
+        assert_eq!(status.config, Config::default());
+        assert!(status.path.unwrap().ends_with("config.toml"));
+        assert_eq!(status.error, None);
+        assert!(store.reload_if_changed().is_none());
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_settings_on_error() {
+        let path = temp_path("reload");
+        fs::write(&path, "[scrollback]\nlines = 500\n").unwrap();
+        let store = ConfigStore::load(&path);
+        assert_eq!(store.config().scrollback.lines, 500);
+
+        fs::write(&path, "[scrollback]\nlines = 0\n").unwrap();
+        let status = store.reload_if_changed().unwrap();
+        assert_eq!(status.config.scrollback.lines, 500);
+        assert!(status.error.unwrap().contains("scrollback.lines"));
+        assert!(store.reload_if_changed().is_none());
+
+        // Different size, so the change is seen even within the mtime resolution
+        fs::write(&path, "[scrollback]\nlines = 2000\n").unwrap();
+        let status = store.reload_if_changed().unwrap();
+        assert_eq!(status.config.scrollback.lines, 2000);
+        assert_eq!(status.error, None);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(store.reload_if_changed().unwrap().config, Config::default());
+    }
+
+    #[test]
+    fn test_default_store_has_no_file() {
+        let store = ConfigStore::default();
+        assert_eq!(store.status(), ConfigStatus::default());
+        assert!(store.reload_if_changed().is_none());
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::VecDeque;
 use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::models::{NotificationLevel, OutputLine};
+use crate::models::{HistoryLine, NotificationLevel, OutputLine};
 
 /// Get current timestamp in milliseconds since Unix epoch
 pub fn current_timestamp_ms() -> u64 {
@@ -18,6 +18,9 @@ pub fn current_timestamp_ms() -> u64 {
 /// Manages the circular buffer of terminal output (max 10,000 lines).
 pub struct HistoryBuffer {
     lines: Arc<RwLock<VecDeque<OutputLine>>>,
+    /// Number of lines ever pushed; the sequence number of a line is the count before it
+    /// was pushed
+    pushed: Arc<RwLock<u64>>,
     max_capacity: usize,
     truncation_warning_shown: Arc<RwLock<bool>>,
 }
@@ -30,6 +33,7 @@ impl HistoryBuffer {
     pub fn new(max_capacity: usize) -> Self {
         Self {
             lines: Arc::new(RwLock::new(VecDeque::with_capacity(max_capacity))),
+            pushed: Arc::new(RwLock::new(0)),
             max_capacity,
             truncation_warning_shown: Arc::new(RwLock::new(false)),
         }
@@ -37,10 +41,20 @@ impl HistoryBuffer {
 
     /// Add line to buffer; evict oldest if at capacity
     pub fn push(&self, line: OutputLine) -> Option<OutputLine> {
+        self.push_with_seq(line).1.map(|warning| warning.line)
+    }
+
+    /// Add line to buffer like `push`, also returning the line's sequence number (for
+    /// `replace`) and the truncation warning with its own
+    pub fn push_with_seq(&self, line: OutputLine) -> (u64, Option<HistoryLine>) {
         let mut lines = self
             .lines
             .write()
             .expect("HistoryBuffer::push failed to acquire write lock on lines");
+        let mut pushed = self
+            .pushed
+            .write()
+            .expect("HistoryBuffer::push failed to acquire write lock on pushed");
         let mut warning_shown = self
             .truncation_warning_shown
             .write()
@@ -66,15 +80,46 @@ impl HistoryBuffer {
                 timestamp: current_timestamp_ms(),
             };
             lines.push_back(warning.clone());
+            let seq = *pushed;
+            *pushed += 1;
             *warning_shown = true;
-            Some(warning)
+            Some(HistoryLine { seq, line: warning })
         } else {
             None
         };
 
         drop(warning_shown);
         lines.push_back(line);
-        warning_line
+        drop(lines);
+        let seq = *pushed;
+        *pushed += 1;
+        drop(pushed);
+        (seq, warning_line)
+    }
+
+    /// Replace the line with the given sequence number (e.g. a partial line being
+    /// rewritten). Returns false if the line has already been evicted.
+    pub fn replace(&self, seq: u64, line: OutputLine) -> bool {
+        let mut lines = self
+            .lines
+            .write()
+            .expect("HistoryBuffer::replace failed to acquire write lock on lines");
+        let pushed = *self
+            .pushed
+            .read()
+            .expect("HistoryBuffer::replace failed to acquire read lock on pushed");
+
+        let first_seq = pushed - lines.len() as u64;
+        let Some(slot) = seq
+            .checked_sub(first_seq)
+            .and_then(|index| usize::try_from(index).ok())
+            .and_then(|index| lines.get_mut(index))
+        else {
+            return false;
+        };
+        *slot = line;
+        drop(lines);
+        true
     }
 
     /// Get all lines for rendering (cloned)
@@ -87,6 +132,41 @@ impl HistoryBuffer {
             .collect()
     }
 
+    /// Get the lines with sequence number `seq` and later, with their sequence numbers
+    pub fn since(&self, seq: u64) -> Vec<HistoryLine> {
+        let lines = self
+            .lines
+            .read()
+            .expect("HistoryBuffer::since failed to acquire read lock on lines");
+        let first_seq = self.first_seq(lines.len());
+        let start = usize::try_from(seq.saturating_sub(first_seq)).unwrap_or(usize::MAX);
+        let since = numbered(&lines, first_seq, start, usize::MAX);
+        drop(lines);
+        since
+    }
+
+    /// Get up to `limit` lines starting `offset` lines after the oldest line kept, with
+    /// their sequence numbers
+    pub fn range(&self, offset: usize, limit: usize) -> Vec<HistoryLine> {
+        let lines = self
+            .lines
+            .read()
+            .expect("HistoryBuffer::range failed to acquire read lock on lines");
+        let first_seq = self.first_seq(lines.len());
+        let range = numbered(&lines, first_seq, offset, limit);
+        drop(lines);
+        range
+    }
+
+    /// Sequence number of the oldest of the `kept` lines
+    fn first_seq(&self, kept: usize) -> u64 {
+        *self
+            .pushed
+            .read()
+            .expect("HistoryBuffer::first_seq failed to acquire read lock on pushed")
+            - kept as u64
+    }
+
     /// Get line count
     pub fn len(&self) -> usize {
         self.lines
@@ -149,6 +229,26 @@ impl HistoryBuffer {
     }
 }
 
+/// Copy up to `limit` of `lines` starting at index `start`; the first line has sequence
+/// number `first_seq`
+fn numbered(
+    lines: &VecDeque<OutputLine>,
+    first_seq: u64,
+    start: usize,
+    limit: usize,
+) -> Vec<HistoryLine> {
+    let start = start.min(lines.len());
+    let end = start.saturating_add(limit).min(lines.len());
+    lines
+        .range(start..end)
+        .zip(first_seq + start as u64..)
+        .map(|(line, seq)| HistoryLine {
+            seq,
+            line: line.clone(),
+        })
+        .collect()
+}
+
 impl Default for HistoryBuffer {
     fn default() -> Self {
         Self::new(Self::DEFAULT_CAPACITY)
@@ -159,6 +259,7 @@ impl Clone for HistoryBuffer {
     fn clone(&self) -> Self {
         Self {
             lines: Arc::clone(&self.lines),
+            pushed: Arc::clone(&self.pushed),
             max_capacity: self.max_capacity,
             truncation_warning_shown: Arc::clone(&self.truncation_warning_shown),
         }
@@ -353,6 +454,97 @@ mod tests {
         assert_eq!(cloned.len(), 1);
         assert_eq!(cloned.first().unwrap().text(), "shared");
     }
+
+    #[test]
+    fn test_buffer_replace_by_id() {
+        let buffer = HistoryBuffer::new(10);
+        let stdout = |text: &str| OutputLine::Stdout {
+            text: text.to_string(),
+            timestamp: 1000,
+        };
+
+        buffer.push(stdout("before"));
+        let (id, _) = buffer.push_with_seq(stdout("10%"));
+        buffer.push(stdout("after"));
+
+        assert!(buffer.replace(id, stdout("100%")));
+        let texts: Vec<_> = buffer
+            .get_all()
+            .iter()
+            .map(|l| l.text().to_string())
+            .collect();
+        assert_eq!(texts, vec!["before", "100%", "after"]);
+        assert!(!buffer.replace(id + 5, stdout("unknown")));
+    }
+
+    #[test]
+    fn test_buffer_replace_evicted_line_fails() {
+        let buffer = HistoryBuffer::new(3);
+        let (id, _) = buffer.push_with_seq(OutputLine::Stdout {
+            text: "old".to_string(),
+            timestamp: 1000,
+        });
+        for i in 0..3 {
+            buffer.push(OutputLine::Stdout {
+                text: format!("line{i}"),
+                timestamp: 2000,
+            });
+        }
+
+        assert!(!buffer.replace(
+            id,
+            OutputLine::Stdout {
+                text: "new".to_string(),
+                timestamp: 3000,
+            }
+        ));
+        assert!(!buffer.get_all().iter().any(|l| l.text() == "new"));
+    }
+
+    #[test]
+    fn test_sequence_numbers_increase_and_survive_eviction() {
+        let buffer = HistoryBuffer::new(3);
+        let stdout = |text: &str| OutputLine::Stdout {
+            text: text.to_string(),
+            timestamp: 1000,
+        };
+
+        assert_eq!(buffer.push_with_seq(stdout("a")).0, 0);
+        assert_eq!(buffer.push_with_seq(stdout("b")).0, 1);
+        assert_eq!(buffer.push_with_seq(stdout("c")).0, 2);
+        // Full: the truncation warning takes the next number, before the line
+        let (seq, warning) = buffer.push_with_seq(stdout("d"));
+        assert_eq!(seq, 4);
+        assert_eq!(warning.unwrap().seq, 3);
+
+        let seqs: Vec<_> = buffer.since(0).iter().map(|l| l.seq).collect();
+        assert_eq!(seqs, vec![2, 3, 4]);
+        assert_eq!(buffer.since(4)[0].line, stdout("d"));
+        assert!(buffer.since(5).is_empty());
+
+        // Identical lines are told apart by their numbers
+        assert_eq!(buffer.push_with_seq(stdout("d")).0, 5);
+        assert_eq!(buffer.since(4).len(), 2);
+    }
+
+    #[test]
+    fn test_range_pages_through_kept_lines() {
+        let buffer = HistoryBuffer::new(10);
+        for i in 0..5 {
+            buffer.push(OutputLine::Stdout {
+                text: format!("line{i}"),
+                timestamp: 1000,
+            });
+        }
+
+        let page = buffer.range(1, 2);
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].seq, 1);
+        assert_eq!(page[1].line.text(), "line2");
+        assert_eq!(buffer.range(4, 10).len(), 1);
+        assert!(buffer.range(5, 10).is_empty());
+        assert!(buffer.range(0, 0).is_empty());
+    }
 }
 
 #[cfg(test)]
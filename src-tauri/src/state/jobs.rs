@@ -0,0 +1,524 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{watch, Mutex};
+
+#[cfg(unix)]
+use super::shell_manager::SharedEmulator;
+use super::shell_manager::SharedStdin;
+use crate::models::{JobInfo, JobStatus, OutputStream};
+#[cfg(unix)]
+use crate::process::pty::PtyMaster;
+use crate::process::signals;
+
+/// Job number applied to a command's output lines once it runs as a job.
+///
+/// Shared with the command's output readers, so a foreground command suspended with
+/// Ctrl+Z has its later output tagged too.
+#[derive(Debug, Clone, Default)]
+pub struct JobTag(Arc<AtomicU32>);
+
+impl JobTag {
+    /// The job number, or `None` while the command is not a job
+    pub fn get(&self) -> Option<u32> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            id => Some(id),
+        }
+    }
+
+    /// Tag all further output with job `id`
+    pub fn set(&self, id: u32) {
+        self.0.store(id, Ordering::Relaxed);
+    }
+}
+
+/// Lines and bytes a command wrote to each output stream.
+#[derive(Debug, Default)]
+pub struct OutputCounts {
+    stdout_lines: AtomicU64,
+    stdout_bytes: AtomicU64,
+    stderr_lines: AtomicU64,
+    stderr_bytes: AtomicU64,
+}
+
+impl OutputCounts {
+    const fn counters(&self, stream: OutputStream) -> (&AtomicU64, &AtomicU64) {
+        match stream {
+            OutputStream::Stdout => (&self.stdout_lines, &self.stdout_bytes),
+            OutputStream::Stderr => (&self.stderr_lines, &self.stderr_bytes),
+        }
+    }
+
+    /// Count `bytes` read from `stream`
+    pub fn add_bytes(&self, stream: OutputStream, bytes: usize) {
+        let bytes = u64::try_from(bytes).unwrap_or(u64::MAX);
+        self.counters(stream).1.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Count a complete line of `stream`
+    pub fn add_line(&self, stream: OutputStream) {
+        self.counters(stream).0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Lines written to `stream` so far
+    pub fn lines(&self, stream: OutputStream) -> u64 {
+        self.counters(stream).0.load(Ordering::Relaxed)
+    }
+
+    /// Bytes written to `stream` so far
+    pub fn bytes(&self, stream: OutputStream) -> u64 {
+        self.counters(stream).1.load(Ordering::Relaxed)
+    }
+}
+
+/// Input handles of a running command.
+///
+/// Held by the session while the command is in the foreground (so `write_stdin`,
+/// `resize_pty` and cancellation reach it) and by its job while it is not.
+#[derive(Clone, Default)]
+pub struct CommandIo {
+    /// Stdin pipe (piped and persistent modes)
+    pub stdin: Option<SharedStdin>,
+    /// Pseudo-terminal (PTY mode)
+    #[cfg(unix)]
+    pub pty: Option<Arc<PtyMaster>>,
+    /// Screen emulator fed by the pseudo-terminal
+    #[cfg(unix)]
+    pub screen: Option<SharedEmulator>,
+}
+
+/// A command in a session's job table.
+pub struct Job {
+    /// Job number, as used by `fg %1`
+    pub id: u32,
+    /// Command line the job runs
+    pub command: String,
+    /// Tag applied to the job's output lines
+    pub tag: JobTag,
+    /// Output written by the job so far
+    pub output: Arc<OutputCounts>,
+    /// Process group leader (0 until the command is spawned)
+    pid: AtomicU32,
+    status: watch::Sender<JobStatus>,
+    /// Is the session waiting for this job?
+    foreground: AtomicBool,
+    /// Input handles while the job is not in the foreground
+    io: std::sync::Mutex<CommandIo>,
+}
+
+impl Job {
+    /// Summarize the job for `jobs` and the frontend
+    pub fn info(&self) -> JobInfo {
+        JobInfo {
+            id: self.id,
+            command: self.command.clone(),
+            pid: self.pid(),
+            status: self.status(),
+        }
+    }
+
+    /// Get the PID of the job's process group leader
+    pub fn pid(&self) -> Option<u32> {
+        match self.pid.load(Ordering::Relaxed) {
+            0 => None,
+            pid => Some(pid),
+        }
+    }
+
+    /// Record the PID of the job's process group leader once it is spawned
+    pub fn set_pid(&self, pid: Option<u32>) {
+        self.pid.store(pid.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Describe the status as `jobs` does (`Running`, `Stopped`, `Done`, `Exit 2`), naming
+    /// the signal that killed it (`SIGSEGV (core dumped)`)
+    pub fn status_text(&self) -> String {
+        match self.status() {
+            JobStatus::Running => "Running".to_string(),
+            JobStatus::Stopped => "Stopped".to_string(),
+            JobStatus::Done {
+                signal: Some(signal),
+                core_dumped,
+                ..
+            } => {
+                let core = if core_dumped { " (core dumped)" } else { "" };
+                format!("{}{core}", signals::signal_name(signal))
+            }
+            JobStatus::Done {
+                exit_code: Some(0) | None,
+                ..
+            } => "Done".to_string(),
+            JobStatus::Done {
+                exit_code: Some(code),
+                ..
+            } => format!("Exit {code}"),
+        }
+    }
+
+    /// Get the job's current status
+    pub fn status(&self) -> JobStatus {
+        *self.status.borrow()
+    }
+
+    /// Set the job's status, waking anyone waiting for it to change
+    pub fn set_status(&self, status: JobStatus) {
+        self.status.send_replace(status);
+    }
+
+    /// Mark a running job as stopped. Returns `false` if it already exited.
+    pub fn suspend(&self) -> bool {
+        self.status.send_if_modified(|status| {
+            let running = *status == JobStatus::Running;
+            if running {
+                *status = JobStatus::Stopped;
+            }
+            running
+        })
+    }
+
+    /// Mark a stopped job as running again. Returns `false` if it was not stopped.
+    pub fn resume(&self) -> bool {
+        self.status.send_if_modified(|status| {
+            let stopped = *status == JobStatus::Stopped;
+            if stopped {
+                *status = JobStatus::Running;
+            }
+            stopped
+        })
+    }
+
+    /// Wait until the job is stopped or has exited, returning that status
+    pub async fn wait_while_running(&self) -> JobStatus {
+        let mut status = self.status.subscribe();
+        // The sender lives as long as `self`, so the channel cannot close
+        status
+            .wait_for(|status| *status != JobStatus::Running)
+            .await
+            .map_or(
+                JobStatus::Done {
+                    exit_code: None,
+                    signal: None,
+                    core_dumped: false,
+                },
+                |status| *status,
+            )
+    }
+
+    /// Check if the session is waiting for this job
+    pub fn is_foreground(&self) -> bool {
+        self.foreground.load(Ordering::Relaxed)
+    }
+
+    /// Mark the job as waited for by the session (or not)
+    pub fn set_foreground(&self, foreground: bool) {
+        self.foreground.store(foreground, Ordering::Relaxed);
+    }
+
+    /// Take the job's input handles (when it moves to the foreground)
+    pub fn take_io(&self) -> CommandIo {
+        std::mem::take(
+            &mut *self
+                .io
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        )
+    }
+
+    /// Keep the command's input handles while it is not in the foreground
+    pub fn set_io(&self, io: CommandIo) {
+        *self
+            .io
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = io;
+    }
+}
+
+/// Jobs of a session, numbered like a shell's (`%1`, `%2`, ...).
+///
+/// Every external command runs as a job; the one the session waits for is in the
+/// foreground, and only the others are listed by `jobs`.
+#[derive(Clone, Default)]
+pub struct JobTable {
+    /// Jobs from least to most recently started, stopped or backgrounded
+    jobs: Arc<Mutex<Vec<Arc<Job>>>>,
+}
+
+impl JobTable {
+    /// Add a running job, numbered one past the highest current job. Output of a
+    /// background job is tagged from the start; a foreground job's only once suspended.
+    pub async fn add(&self, command: impl Into<String>, foreground: bool) -> Arc<Job> {
+        let mut jobs = self.jobs.lock().await;
+        let id = jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        let tag = JobTag::default();
+        if !foreground {
+            tag.set(id);
+        }
+        let job = Arc::new(Job {
+            id,
+            command: command.into(),
+            tag,
+            output: Arc::default(),
+            pid: AtomicU32::new(0),
+            status: watch::Sender::new(JobStatus::Running),
+            foreground: AtomicBool::new(foreground),
+            io: std::sync::Mutex::new(CommandIo::default()),
+        });
+        jobs.push(Arc::clone(&job));
+        drop(jobs);
+        job
+    }
+
+    /// Get a job by number
+    pub async fn get(&self, id: u32) -> Option<Arc<Job>> {
+        self.jobs
+            .lock()
+            .await
+            .iter()
+            .find(|job| job.id == id)
+            .cloned()
+    }
+
+    /// Remove a job from the table, returning it
+    pub async fn remove(&self, id: u32) -> Option<Arc<Job>> {
+        let mut jobs = self.jobs.lock().await;
+        let index = jobs.iter().position(|job| job.id == id)?;
+        Some(jobs.remove(index))
+    }
+
+    /// Make job `id` the current job (after it was stopped or backgrounded)
+    pub async fn touch(&self, id: u32) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(index) = jobs.iter().position(|job| job.id == id) {
+            let job = jobs.remove(index);
+            jobs.push(job);
+        }
+    }
+
+    /// Get the job the session is waiting for (if any)
+    pub async fn foreground(&self) -> Option<Arc<Job>> {
+        self.jobs
+            .lock()
+            .await
+            .iter()
+            .find(|job| job.is_foreground())
+            .cloned()
+    }
+
+    /// Jobs not in the foreground, from least to most recent
+    async fn background(&self) -> Vec<Arc<Job>> {
+        self.jobs
+            .lock()
+            .await
+            .iter()
+            .filter(|job| !job.is_foreground())
+            .cloned()
+            .collect()
+    }
+
+    /// All jobs (including the foreground one), for closing the session
+    pub async fn all(&self) -> Vec<Arc<Job>> {
+        self.jobs.lock().await.clone()
+    }
+
+    /// Resolve a job spec: `%1` or `1`, `%+`/`%%` (or none) for the current job, `%-`
+    /// for the previous one. Errors are worded like a shell's.
+    pub async fn resolve(&self, spec: Option<&str>) -> Result<Arc<Job>, String> {
+        let background = self.background().await;
+        let spec = spec.unwrap_or("%+");
+        let found = match spec {
+            "%+" | "%%" | "%" => background.last().cloned(),
+            "%-" => background.iter().rev().nth(1).cloned(),
+            _ => {
+                let Ok(id) = spec.trim_start_matches('%').parse::<u32>() else {
+                    return Err(format!("{spec}: no such job"));
+                };
+                background.into_iter().find(|job| job.id == id)
+            }
+        };
+        found.ok_or_else(|| match spec {
+            "%+" | "%%" | "%" => "current: no such job".to_string(),
+            _ => format!("{spec}: no such job"),
+        })
+    }
+
+    /// Jobs not in the foreground, by number, formatted as `jobs` prints them
+    pub async fn describe(&self) -> Vec<String> {
+        let background = self.background().await;
+        let current = background.last().map(|job| job.id);
+        let previous = background.iter().rev().nth(1).map(|job| job.id);
+
+        let mut jobs = background;
+        jobs.sort_by_key(|job| job.id);
+        jobs.iter()
+            .map(|job| {
+                let mark = if Some(job.id) == current {
+                    '+'
+                } else if Some(job.id) == previous {
+                    '-'
+                } else {
+                    ' '
+                };
+                format!(
+                    "[{}]{mark}  {:<24}{}",
+                    job.id,
+                    job.status_text(),
+                    job.command
+                )
+            })
+            .collect()
+    }
+
+    /// Summaries of the jobs not in the foreground, by number
+    pub async fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self
+            .background()
+            .await
+            .iter()
+            .map(|job| job.info())
+            .collect();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_job_numbers() {
+        let table = JobTable::default();
+        let first = table.add("sleep 10", false).await;
+        let second = table.add("sleep 20", false).await;
+        assert_eq!((first.id, second.id), (1, 2));
+
+        // Numbers continue past the highest job, and restart once the table empties
+        table.remove(1).await;
+        assert_eq!(table.add("make", false).await.id, 3);
+        table.remove(2).await;
+        table.remove(3).await;
+        assert_eq!(table.add("make", false).await.id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_job_specs() {
+        let table = JobTable::default();
+        table.add("sleep 10", false).await;
+        table.add("sleep 20", false).await;
+        table.add("vim", true).await;
+
+        assert_eq!(table.resolve(None).await.unwrap().id, 2);
+        assert_eq!(table.resolve(Some("%-")).await.unwrap().id, 1);
+        assert_eq!(table.resolve(Some("%1")).await.unwrap().id, 1);
+        assert_eq!(table.resolve(Some("2")).await.unwrap().id, 2);
+
+        // The foreground job is not addressable
+        assert_eq!(
+            table.resolve(Some("%3")).await.err().as_deref(),
+            Some("%3: no such job")
+        );
+
+        // A stopped job becomes the current one
+        table.touch(1).await;
+        assert_eq!(table.resolve(Some("%+")).await.unwrap().id, 1);
+
+        let empty = JobTable::default();
+        assert_eq!(
+            empty.resolve(None).await.err().as_deref(),
+            Some("current: no such job")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_describe_jobs() {
+        let table = JobTable::default();
+        table.add("npm run watch", false).await;
+        let stopped = table.add("vim notes.md", false).await;
+        stopped.set_status(JobStatus::Stopped);
+
+        assert_eq!(
+            table.describe().await,
+            vec![
+                "[1]-  Running                 npm run watch",
+                "[2]+  Stopped                 vim notes.md",
+            ]
+        );
+        let statuses: Vec<JobStatus> = table.list().await.into_iter().map(|j| j.status).collect();
+        assert_eq!(statuses, vec![JobStatus::Running, JobStatus::Stopped]);
+    }
+
+    #[tokio::test]
+    async fn test_status_text_of_finished_jobs() {
+        let table = JobTable::default();
+        let job = table.add("make", false).await;
+        let done = |exit_code, signal| JobStatus::Done {
+            exit_code,
+            signal,
+            core_dumped: signal.is_some(),
+        };
+
+        job.set_status(done(Some(0), None));
+        assert_eq!(job.status_text(), "Done");
+        job.set_status(done(Some(2), None));
+        assert_eq!(job.status_text(), "Exit 2");
+        #[cfg(unix)]
+        {
+            job.set_status(done(None, Some(11)));
+            assert_eq!(job.status_text(), "SIGSEGV (core dumped)");
+        }
+    }
+
+    #[test]
+    fn test_output_counts() {
+        let counts = OutputCounts::default();
+        counts.add_bytes(OutputStream::Stdout, 12);
+        counts.add_line(OutputStream::Stdout);
+        counts.add_line(OutputStream::Stdout);
+        counts.add_bytes(OutputStream::Stderr, 5);
+
+        assert_eq!(counts.lines(OutputStream::Stdout), 2);
+        assert_eq!(counts.bytes(OutputStream::Stdout), 12);
+        assert_eq!(counts.lines(OutputStream::Stderr), 0);
+        assert_eq!(counts.bytes(OutputStream::Stderr), 5);
+    }
+
+    #[tokio::test]
+    async fn test_wait_while_running() {
+        let table = JobTable::default();
+        let job = table.add("sleep 1", true).await;
+
+        let waiter = {
+            let job = Arc::clone(&job);
+            tokio::spawn(async move { job.wait_while_running().await })
+        };
+        job.set_status(JobStatus::Stopped);
+        assert_eq!(waiter.await.unwrap(), JobStatus::Stopped);
+
+        let done = JobStatus::Done {
+            exit_code: Some(0),
+            signal: None,
+            core_dumped: false,
+        };
+        job.set_status(done);
+        assert_eq!(job.wait_while_running().await, done);
+    }
+
+    #[tokio::test]
+    async fn test_background_output_is_tagged_from_the_start() {
+        let table = JobTable::default();
+        let background = table.add("make &", false).await;
+        assert_eq!(background.tag.get(), Some(1));
+
+        // A foreground job is tagged once it leaves the foreground
+        let foreground = table.add("vim", true).await;
+        let reader_tag = foreground.tag.clone();
+        assert_eq!(reader_tag.get(), None);
+        foreground.tag.set(foreground.id);
+        assert_eq!(reader_tag.get(), Some(2));
+
+        assert_eq!(foreground.pid(), None);
+        foreground.set_pid(Some(4321));
+        assert_eq!(foreground.info().pid, Some(4321));
+    }
+}
@@ -1,5 +1,19 @@
+pub mod command_history;
+pub mod config_store;
 pub mod history_buffer;
+pub mod jobs;
+pub mod output_batcher;
+pub mod scrollback;
+pub mod session;
+pub mod session_env;
 pub mod shell_manager;
 
+pub use command_history::CommandHistory;
+pub use config_store::ConfigStore;
 pub use history_buffer::{current_timestamp_ms, HistoryBuffer};
-pub use shell_manager::{ShellManager, ShellState};
+pub use jobs::{CommandIo, Job, JobTable, JobTag, OutputCounts};
+pub use output_batcher::{OutputBatcher, Queued};
+pub use scrollback::{ScrollbackStore, ScrollbackWriter};
+pub use session::Session;
+pub use session_env::SessionEnv;
+pub use shell_manager::{CommandTimeout, ShellManager, ShellState};
@@ -0,0 +1,215 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::models::LineUpdate;
+
+/// What queueing an update asks of the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Queued {
+    /// The update started a new batch: flush it once `OutputBatcher::WINDOW` has passed
+    Started,
+    /// The update joined the batch already waiting
+    Joined,
+    /// The batch reached `OutputBatcher::MAX_LINES`: flush it now
+    Full,
+}
+
+/// Output updates of a session waiting to be sent to the frontend together.
+///
+/// Sending one event per line floods the frontend when a command prints quickly (`yes`,
+/// `cat` on a large log); gathering the lines of a short window into one `output-batch`
+/// event keeps it responsive: at 100,000 lines a second that is about 60 events a second,
+/// each applied by the frontend in one update. A partial line rewritten before its batch is sent is
+/// replaced in the batch rather than sent twice.
+///
+/// Cloning yields another handle to the same queue.
+#[derive(Clone, Default)]
+pub struct OutputBatcher {
+    pending: Arc<Mutex<Vec<LineUpdate>>>,
+}
+
+impl OutputBatcher {
+    /// Longest an update waits to be sent (about one frame)
+    pub const WINDOW: Duration = Duration::from_millis(16);
+    /// Most updates sent in one batch
+    pub const MAX_LINES: usize = 2048;
+
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `update` after those already waiting
+    pub fn push(&self, update: LineUpdate) -> Queued {
+        let mut pending = self
+            .pending
+            .lock()
+            .expect("OutputBatcher::push failed to acquire lock on pending");
+        let was_empty = pending.is_empty();
+        if let Some(update) = coalesce(&mut pending, update) {
+            pending.push(update);
+        }
+
+        if pending.len() >= Self::MAX_LINES {
+            Queued::Full
+        } else if was_empty {
+            Queued::Started
+        } else {
+            Queued::Joined
+        }
+    }
+
+    /// Hand the waiting updates, in order, to `send`; does nothing if none are waiting.
+    ///
+    /// The queue stays locked while `send` runs so that batches flushed concurrently
+    /// are sent in the order they were gathered.
+    pub fn flush(&self, send: impl FnOnce(Vec<LineUpdate>)) {
+        let mut pending = self
+            .pending
+            .lock()
+            .expect("OutputBatcher::flush failed to acquire lock on pending");
+        if !pending.is_empty() {
+            send(std::mem::take(&mut *pending));
+        }
+    }
+
+    /// Number of updates waiting
+    pub fn len(&self) -> usize {
+        self.pending
+            .lock()
+            .expect("OutputBatcher::len failed to acquire lock on pending")
+            .len()
+    }
+
+    /// Check whether no updates are waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Fold `update` into the waiting version of the partial line it replaces, if that is
+/// still waiting. Returns the update if it has to be queued on its own.
+fn coalesce(pending: &mut [LineUpdate], update: LineUpdate) -> Option<LineUpdate> {
+    if !update.replace {
+        return Some(update);
+    }
+    match pending
+        .iter_mut()
+        .rev()
+        .find(|queued| queued.seq == update.seq)
+    {
+        Some(queued) => {
+            // Whether the frontend adds the line or replaces an earlier version is unchanged
+            queued.line = update.line;
+            queued.partial = update.partial;
+            None
+        }
+        None => Some(update),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OutputLine;
+
+    fn update(seq: u64, text: &str, partial: bool, replace: bool) -> LineUpdate {
+        LineUpdate {
+            seq,
+            line: OutputLine::Stdout {
+                text: text.to_string(),
+                timestamp: 1,
+            },
+            partial,
+            replace,
+        }
+    }
+
+    fn flushed(batcher: &OutputBatcher) -> Vec<LineUpdate> {
+        let mut sent = Vec::new();
+        batcher.flush(|lines| sent = lines);
+        sent
+    }
+
+    #[test]
+    fn test_push_reports_batch_start_and_flush_empties() {
+        let batcher = OutputBatcher::new();
+        assert_eq!(batcher.push(update(0, "a", false, false)), Queued::Started);
+        assert_eq!(batcher.push(update(1, "b", false, false)), Queued::Joined);
+        assert_eq!(batcher.len(), 2);
+
+        let sent = flushed(&batcher);
+        assert_eq!(
+            sent,
+            [update(0, "a", false, false), update(1, "b", false, false)]
+        );
+        assert!(batcher.is_empty());
+
+        // Nothing waiting: `send` is not called
+        batcher.flush(|_| panic!("empty batch sent"));
+        assert_eq!(batcher.push(update(2, "c", false, false)), Queued::Started);
+    }
+
+    #[test]
+    fn test_full_batch_asks_for_flush() {
+        let batcher = OutputBatcher::new();
+        for seq in 1..OutputBatcher::MAX_LINES as u64 {
+            assert_ne!(
+                batcher.push(update(seq, &seq.to_string(), false, false)),
+                Queued::Full
+            );
+        }
+        assert_eq!(batcher.push(update(0, "last", false, false)), Queued::Full);
+        assert_eq!(flushed(&batcher).len(), OutputBatcher::MAX_LINES);
+    }
+
+    #[test]
+    fn test_rewritten_partial_line_is_coalesced() {
+        let batcher = OutputBatcher::new();
+        batcher.push(update(0, "done", false, false));
+        batcher.push(update(1, "10%", true, false));
+        batcher.push(update(1, "50%", true, true));
+        batcher.push(update(1, "100%", false, true));
+
+        // One new line: the final version of the progress bar
+        assert_eq!(
+            flushed(&batcher),
+            [
+                update(0, "done", false, false),
+                update(1, "100%", false, false)
+            ]
+        );
+
+        // Once sent, the next version replaces it in the frontend
+        batcher.push(update(2, "prompt>", true, false));
+        flushed(&batcher);
+        batcher.push(update(2, "prompt> a", true, true));
+        batcher.push(update(2, "prompt> ab", true, true));
+        assert_eq!(flushed(&batcher), [update(2, "prompt> ab", true, true)]);
+    }
+
+    #[test]
+    fn test_other_lines_are_not_coalesced() {
+        let batcher = OutputBatcher::new();
+        batcher.push(update(3, "50%", true, false));
+        batcher.push(update(4, "loading", true, false));
+        batcher.push(update(2, "prompt> ls", false, true));
+
+        assert_eq!(
+            flushed(&batcher),
+            [
+                update(3, "50%", true, false),
+                update(4, "loading", true, false),
+                update(2, "prompt> ls", false, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clone_shares_queue() {
+        let batcher = OutputBatcher::new();
+        let cloned = batcher.clone();
+        batcher.push(update(0, "a", false, false));
+        assert_eq!(cloned.len(), 1);
+    }
+}
@@ -0,0 +1,355 @@
+use std::cmp::Ordering;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::models::OutputLine;
+
+/// Extension of a session's scrollback file; the rotated file adds `.1`
+const EXTENSION: &str = "jsonl";
+
+/// On-disk scrollback: one append-only JSON Lines file of output lines per session, so
+/// output survives a restart or a crash.
+///
+/// A file that reaches `max_file_bytes` is rotated to `<session>.jsonl.1`, replacing the
+/// previous rotation. Only the `max_sessions` most recently written sessions are kept.
+pub struct ScrollbackStore {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    max_sessions: usize,
+}
+
+impl ScrollbackStore {
+    /// Size at which a session's scrollback file is rotated
+    pub const DEFAULT_MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+    /// Number of sessions whose scrollback is kept
+    pub const DEFAULT_MAX_SESSIONS: usize = 20;
+
+    /// Default location of the store, `~/.cepheus/scrollback`
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs_next::home_dir().map(|home| home.join(".cepheus").join("scrollback"))
+    }
+
+    /// Open the store in `dir`, creating it if needed
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created.
+    pub fn open(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_file_bytes: Self::DEFAULT_MAX_FILE_BYTES,
+            max_sessions: Self::DEFAULT_MAX_SESSIONS,
+        })
+    }
+
+    /// Set the rotation size and the number of sessions kept
+    #[must_use]
+    pub const fn with_limits(mut self, max_file_bytes: u64, max_sessions: usize) -> Self {
+        self.max_file_bytes = max_file_bytes;
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    fn path(&self, session_id: &str) -> PathBuf {
+        self.dir.join(format!("{session_id}.{EXTENSION}"))
+    }
+
+    fn rotated_path(&self, session_id: &str) -> PathBuf {
+        self.dir.join(format!("{session_id}.{EXTENSION}.1"))
+    }
+
+    /// Open the scrollback file of `session_id` for appending
+    pub fn writer(&self, session_id: &str) -> ScrollbackWriter {
+        let path = self.path(session_id);
+        let file = open_append(&path)
+            .map_err(|e| tracing::warn!("Failed to open scrollback file {:?}: {}", path, e))
+            .ok();
+        let written = file
+            .as_ref()
+            .and_then(|file| file.metadata().ok())
+            .map_or(0, |metadata| metadata.len());
+
+        ScrollbackWriter {
+            path,
+            rotated_path: self.rotated_path(session_id),
+            max_bytes: self.max_file_bytes,
+            file: Mutex::new(file.map(|file| OpenFile { file, written })),
+        }
+    }
+
+    /// IDs of the sessions with saved scrollback, oldest first
+    pub fn saved_sessions(&self) -> Vec<String> {
+        let mut ids: Vec<_> = self
+            .session_files()
+            .into_iter()
+            .filter_map(|(path, _)| {
+                let name = path.file_name()?.to_str()?;
+                name.strip_suffix(&format!(".{EXTENSION}"))
+                    .map(str::to_string)
+            })
+            .collect();
+        // `session-10` was created after `session-9`
+        ids.sort_by_key(|id| (session_number(id), id.clone()));
+        ids
+    }
+
+    /// Read back the last `limit` saved lines of `session_id`, oldest first. A line that is
+    /// not valid JSON (cut short by a crash) is skipped.
+    pub fn load(&self, session_id: &str, limit: usize) -> Vec<OutputLine> {
+        let mut lines = Vec::new();
+        for path in [self.rotated_path(session_id), self.path(session_id)] {
+            let Ok(file) = File::open(&path) else {
+                continue;
+            };
+            for text in BufReader::new(file).lines().map_while(Result::ok) {
+                match serde_json::from_str(&text) {
+                    Ok(line) => lines.push(line),
+                    Err(e) => {
+                        tracing::warn!("Skipping corrupt scrollback line in {:?}: {}", path, e);
+                    }
+                }
+            }
+        }
+        let excess = lines.len().saturating_sub(limit);
+        lines.drain(..excess);
+        lines
+    }
+
+    /// Delete the saved scrollback of `session_id`
+    pub fn remove(&self, session_id: &str) {
+        for path in [self.path(session_id), self.rotated_path(session_id)] {
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!("Failed to remove scrollback file {:?}: {}", path, e),
+            }
+        }
+    }
+
+    /// Delete the scrollback of all but the `max_sessions` most recently written sessions
+    pub fn cleanup(&self) {
+        let mut files = self.session_files();
+        files.sort_by(|a, b| match (a.1, b.1) {
+            (Some(a_time), Some(b_time)) => b_time.cmp(&a_time),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+
+        for (path, _) in files.into_iter().skip(self.max_sessions) {
+            let Some(id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(&format!(".{EXTENSION}")))
+            else {
+                continue;
+            };
+            tracing::info!("Removing scrollback of old session {}", id);
+            self.remove(id);
+        }
+    }
+
+    /// Current (unrotated) scrollback files with their modification times
+    fn session_files(&self) -> Vec<(PathBuf, Option<std::time::SystemTime>)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            tracing::warn!("Failed to read scrollback dir {:?}", self.dir);
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_file()))
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == EXTENSION))
+            .map(|entry| {
+                let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+                (entry.path(), modified)
+            })
+            .collect()
+    }
+}
+
+/// Number in a `session-N` ID, for ordering sessions by creation
+pub fn session_number(session_id: &str) -> Option<u64> {
+    session_id.strip_prefix("session-")?.parse().ok()
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+struct OpenFile {
+    file: File,
+    /// Size of the file, to know when to rotate it
+    written: u64,
+}
+
+/// Appends the output lines of one session to its scrollback file.
+pub struct ScrollbackWriter {
+    path: PathBuf,
+    rotated_path: PathBuf,
+    max_bytes: u64,
+    /// `None` once closed, or after a write error
+    file: Mutex<Option<OpenFile>>,
+}
+
+impl ScrollbackWriter {
+    /// Append a completed line, rotating the file first if it is full. Each line is
+    /// written with a single call, so a crash loses at most the line being written.
+    pub fn append(&self, line: &OutputLine) {
+        let mut text = match serde_json::to_string(line) {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!("Failed to serialize scrollback line: {}", e);
+                return;
+            }
+        };
+        text.push('\n');
+
+        let mut file = self
+            .file
+            .lock()
+            .expect("ScrollbackWriter::append failed to acquire lock on file");
+        let Some(open) = file.as_mut() else {
+            return;
+        };
+
+        if open.written > 0 && open.written + text.len() as u64 > self.max_bytes {
+            match self.rotate() {
+                Ok(rotated) => *open = rotated,
+                Err(e) => {
+                    tracing::warn!("Failed to rotate scrollback file {:?}: {}", self.path, e);
+                    *file = None;
+                    return;
+                }
+            }
+        }
+
+        match open.file.write_all(text.as_bytes()) {
+            Ok(()) => open.written += text.len() as u64,
+            Err(e) => {
+                tracing::warn!("Failed to write scrollback file {:?}: {}", self.path, e);
+                *file = None;
+            }
+        }
+    }
+
+    fn rotate(&self) -> std::io::Result<OpenFile> {
+        fs::rename(&self.path, &self.rotated_path)?;
+        Ok(OpenFile {
+            file: open_append(&self.path)?,
+            written: 0,
+        })
+    }
+
+    /// Stop writing; later lines are dropped
+    pub fn close(&self) {
+        *self
+            .file
+            .lock()
+            .expect("ScrollbackWriter::close failed to acquire lock on file") = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_store(name: &str) -> ScrollbackStore {
+        let dir =
+            std::env::temp_dir().join(format!("cepheus-scrollback-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ScrollbackStore::open(dir).unwrap()
+    }
+
+    fn stdout(text: &str) -> OutputLine {
+        OutputLine::Stdout {
+            text: text.to_string(),
+            timestamp: 1,
+        }
+    }
+
+    #[test]
+    fn test_lines_round_trip() {
+        let store = test_store("round-trip");
+        let writer = store.writer("session-1");
+        writer.append(&stdout("one"));
+        writer.append(&stdout("two"));
+
+        assert_eq!(store.saved_sessions(), ["session-1"]);
+        assert_eq!(
+            store.load("session-1", 100),
+            vec![stdout("one"), stdout("two")]
+        );
+        assert_eq!(store.load("session-1", 1), vec![stdout("two")]);
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_file() {
+        let store = test_store("rotation").with_limits(64, 20);
+        let writer = store.writer("session-1");
+        for i in 0..10 {
+            writer.append(&stdout(&format!("line {i}")));
+        }
+
+        assert!(fs::metadata(store.path("session-1")).unwrap().len() <= 64);
+        let lines = store.load("session-1", 100);
+        assert!(lines.len() < 10);
+        assert_eq!(lines.last(), Some(&stdout("line 9")));
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_corrupt_line_is_skipped() {
+        let store = test_store("corrupt");
+        store.writer("session-1").append(&stdout("kept"));
+        let mut file = open_append(&store.path("session-1")).unwrap();
+        file.write_all(b"{\"type\":\"Std").unwrap();
+
+        assert_eq!(store.load("session-1", 100), vec![stdout("kept")]);
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_cleanup_and_remove() {
+        let store = test_store("cleanup").with_limits(1024, 1);
+        store.writer("session-1").append(&stdout("old"));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        store.writer("session-2").append(&stdout("new"));
+
+        store.cleanup();
+        assert_eq!(store.saved_sessions(), ["session-2"]);
+
+        store.remove("session-2");
+        assert!(store.saved_sessions().is_empty());
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_closed_writer_drops_lines() {
+        let store = test_store("closed");
+        let writer = store.writer("session-1");
+        writer.close();
+        store.remove("session-1");
+        writer.append(&stdout("late"));
+
+        assert!(store.saved_sessions().is_empty());
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_saved_sessions_in_creation_order() {
+        let store = test_store("order");
+        for id in ["session-10", "session-9", "session-2"] {
+            store.writer(id).append(&stdout(id));
+        }
+        assert_eq!(
+            store.saved_sessions(),
+            ["session-2", "session-9", "session-10"]
+        );
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use super::{HistoryBuffer, OutputBatcher, ScrollbackWriter, ShellState};
+use crate::models::{OutputLine, SessionInfo};
+use crate::process::shell::Shell;
+
+/// A terminal session: shell state plus its output history.
+///
+/// Cloning yields another handle to the same session.
+pub struct Session {
+    /// Session identifier
+    pub id: String,
+    /// Shell state (process, cwd, busy flag)
+    pub shell_state: ShellState,
+    /// History buffer for terminal output
+    pub history_buffer: HistoryBuffer,
+    /// Output waiting to be sent to the frontend
+    pub output: OutputBatcher,
+    /// On-disk copy of the history, if scrollback is persisted
+    pub scrollback: Option<Arc<ScrollbackWriter>>,
+    /// Shell that runs the session's commands
+    pub shell: Shell,
+}
+
+impl Session {
+    /// Create a session with the given ID, working directory and history capacity
+    pub fn new(id: impl Into<String>, initial_cwd: String, buffer_capacity: usize) -> Self {
+        Self {
+            id: id.into(),
+            shell_state: ShellState::new(initial_cwd),
+            history_buffer: HistoryBuffer::new(buffer_capacity),
+            output: OutputBatcher::new(),
+            scrollback: None,
+            shell: Shell::default(),
+        }
+    }
+
+    /// Run the session's commands with `shell`
+    #[must_use]
+    pub fn with_shell(mut self, shell: Shell) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Apply `timeout` to commands run without one of their own
+    #[must_use]
+    pub fn with_command_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.shell_state.command_timeout = Arc::new(Mutex::new(timeout));
+        self
+    }
+
+    /// Save the session's completed output lines with `writer`
+    #[must_use]
+    pub fn with_scrollback(mut self, writer: ScrollbackWriter) -> Self {
+        self.scrollback = Some(Arc::new(writer));
+        self
+    }
+
+    /// Save a completed line to the on-disk scrollback, if the session has one
+    pub fn persist(&self, line: &OutputLine) {
+        if let Some(scrollback) = &self.scrollback {
+            scrollback.append(line);
+        }
+    }
+
+    /// Get the current working directory
+    pub async fn get_cwd(&self) -> String {
+        self.shell_state.get_cwd().await
+    }
+
+    /// Check if shell is currently busy
+    pub async fn is_busy(&self) -> bool {
+        self.shell_state.is_busy().await
+    }
+
+    /// Atomically try to set busy state from false to true.
+    /// Returns true if successfully transitioned from false to true,
+    /// false if already busy.
+    pub async fn try_set_busy(&self) -> bool {
+        self.shell_state.try_set_busy().await
+    }
+
+    /// Get the current process ID (if a command is running).
+    /// This is atomic - avoids TOCTOU race between busy check and PID retrieval.
+    pub async fn get_running_pid(&self) -> Option<u32> {
+        self.shell_state.get_pid_if_busy().await
+    }
+
+    /// Summarize the session for the frontend
+    pub async fn info(&self) -> SessionInfo {
+        SessionInfo {
+            id: self.id.clone(),
+            cwd: self.get_cwd().await,
+            is_busy: self.is_busy().await,
+        }
+    }
+}
+
+impl Clone for Session {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            shell_state: self.shell_state.clone(),
+            history_buffer: self.history_buffer.clone(),
+            output: self.output.clone(),
+            scrollback: self.scrollback.clone(),
+            shell: self.shell.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_creation() {
+        let session = Session::new("test", "/tmp".to_string(), 100);
+        assert!(!session.is_busy().await);
+        assert!(session.history_buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_with_custom_capacity() {
+        let session = Session::new("test", "/tmp".to_string(), 100);
+        assert_eq!(session.history_buffer.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_clone_shares_state() {
+        let session = Session::new("test", "/tmp".to_string(), 100);
+        let cloned = session.clone();
+
+        session.shell_state.set_busy(true).await;
+        assert!(cloned.is_busy().await);
+    }
+
+    #[tokio::test]
+    async fn test_get_running_pid_atomic() {
+        let session = Session::new("test", "/tmp".to_string(), 100);
+        // Set PID but not busy
+        *session.shell_state.pid.lock().await = Some(9999);
+        assert!(session.get_running_pid().await.is_none());
+
+        // Now set busy
+        session.shell_state.set_busy(true).await;
+        assert_eq!(session.get_running_pid().await, Some(9999));
+    }
+
+    #[tokio::test]
+    async fn test_try_set_busy_session_delegates_correctly() {
+        let session = Session::new("test", "/tmp".to_string(), 100);
+        assert!(!session.is_busy().await);
+
+        let result = session.try_set_busy().await;
+        assert!(result);
+        assert!(session.is_busy().await);
+
+        // Try again when already busy
+        let result2 = session.try_set_busy().await;
+        assert!(!result2);
+        assert!(session.is_busy().await);
+
+        // Clean up
+        session.shell_state.set_busy(false).await;
+        assert!(!session.is_busy().await);
+    }
+
+    #[tokio::test]
+    async fn test_session_info() {
+        let session = Session::new("session-7", "/tmp".to_string(), 100);
+        session.shell_state.set_busy(true).await;
+        assert_eq!(
+            session.info().await,
+            SessionInfo {
+                id: "session-7".to_string(),
+                cwd: "/tmp".to_string(),
+                is_busy: true,
+            }
+        );
+    }
+}
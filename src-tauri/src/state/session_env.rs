@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use tokio::process::Command;
+
+/// Environment changes made in a session, layered over the inherited process environment.
+///
+/// Applied to every spawned command, so variables set with `export` or `set_env`
+/// persist across `execute_command` calls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionEnv {
+    /// Variables set in this session
+    vars: BTreeMap<String, String>,
+    /// Inherited variables removed in this session
+    removed: BTreeSet<String>,
+}
+
+impl SessionEnv {
+    /// Check that `name` is a valid environment variable name (`[A-Za-z_][A-Za-z0-9_]*`).
+    pub fn validate_name(name: &str) -> Result<(), String> {
+        let mut chars = name.chars();
+        let valid_start = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+        if valid_start && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            Ok(())
+        } else {
+            Err(format!("Invalid environment variable name: {name:?}"))
+        }
+    }
+
+    /// Set a variable for the session.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        Self::validate_name(name)?;
+        if value.contains('\0') {
+            return Err(format!("Value of {name} contains a NUL byte"));
+        }
+        self.removed.remove(name);
+        self.vars.insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+
+    /// Remove a variable from the session (including inherited ones).
+    pub fn unset(&mut self, name: &str) -> Result<(), String> {
+        Self::validate_name(name)?;
+        self.vars.remove(name);
+        self.removed.insert(name.to_string());
+        Ok(())
+    }
+
+    /// Look up a variable as a spawned command would see it.
+    pub fn get(&self, name: &str) -> Option<String> {
+        if let Some(value) = self.vars.get(name) {
+            return Some(value.clone());
+        }
+        if self.removed.contains(name) {
+            return None;
+        }
+        std::env::var(name).ok()
+    }
+
+    /// Names of all variables a spawned command would see, sorted.
+    pub fn names(&self) -> BTreeSet<String> {
+        let mut names: BTreeSet<String> = std::env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .filter(|name| !self.removed.contains(name))
+            .collect();
+        names.extend(self.vars.keys().cloned());
+        names
+    }
+
+    /// Variables set in this session.
+    pub const fn vars(&self) -> &BTreeMap<String, String> {
+        &self.vars
+    }
+
+    /// Inherited variables removed in this session.
+    pub const fn removed(&self) -> &BTreeSet<String> {
+        &self.removed
+    }
+
+    /// Apply the session's changes to a command about to be spawned.
+    pub fn apply(&self, cmd: &mut Command) {
+        for name in &self.removed {
+            cmd.env_remove(name);
+        }
+        cmd.envs(&self.vars);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(SessionEnv::validate_name("RUST_LOG").is_ok());
+        assert!(SessionEnv::validate_name("_private1").is_ok());
+        assert!(SessionEnv::validate_name("").is_err());
+        assert!(SessionEnv::validate_name("1ABC").is_err());
+        assert!(SessionEnv::validate_name("A-B").is_err());
+    }
+
+    #[test]
+    fn test_set_get_unset() {
+        let mut env = SessionEnv::default();
+        env.set("CEPHEUS_TEST_VAR", "debug").unwrap();
+        assert_eq!(env.get("CEPHEUS_TEST_VAR").as_deref(), Some("debug"));
+
+        env.unset("CEPHEUS_TEST_VAR").unwrap();
+        assert!(env.get("CEPHEUS_TEST_VAR").is_none());
+        assert!(env.vars().is_empty());
+    }
+
+    #[test]
+    fn test_unset_hides_inherited_variable() {
+        let mut env = SessionEnv::default();
+        assert!(env.get("PATH").is_some());
+        env.unset("PATH").unwrap();
+        assert!(env.get("PATH").is_none());
+    }
+
+    #[test]
+    fn test_names_include_set_and_hide_unset() {
+        let mut env = SessionEnv::default();
+        env.set("CEPHEUS_NAMED", "1").unwrap();
+        env.unset("PATH").unwrap();
+        let names = env.names();
+        assert!(names.contains("CEPHEUS_NAMED"));
+        assert!(!names.contains("PATH"));
+    }
+
+    #[test]
+    fn test_set_rejects_nul() {
+        let mut env = SessionEnv::default();
+        assert!(env.set("A", "x\0y").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_apply_to_command() {
+        let mut env = SessionEnv::default();
+        env.set("CEPHEUS_APPLIED", "yes").unwrap();
+        env.unset("HOME").unwrap();
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("echo \"$CEPHEUS_APPLIED:${HOME-none}\"");
+        env.apply(&mut cmd);
+        let output = cmd.output().await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "yes:none");
+    }
+}
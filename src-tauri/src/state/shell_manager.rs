@@ -1,29 +1,115 @@
-use std::sync::Arc;
-use tokio::process::Child;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
-use super::HistoryBuffer;
+use super::scrollback::session_number;
+use super::{
+    current_timestamp_ms, CommandHistory, CommandIo, HistoryBuffer, Job, JobTable, ScrollbackStore,
+    Session, SessionEnv,
+};
+use crate::models::{Config, NotificationLevel, OutputLine, PtySize, ShellConfig};
+use crate::process::completion::Completer;
+#[cfg(unix)]
+use crate::process::emulator::Emulator;
+#[cfg(unix)]
+use crate::process::persistent::PersistentShell;
+#[cfg(unix)]
+use crate::process::pty::PtyMaster;
+use crate::process::shell::Shell;
+use crate::process::signals::{self, EscalationPolicy, JobSignal};
+
+/// Stdin of a running command, shared so input can be written while the command runs.
+pub type SharedStdin = Arc<Mutex<ChildStdin>>;
+
+/// Screen emulator of a PTY command, shared by its output reader and resize requests.
+#[cfg(unix)]
+pub type SharedEmulator = Arc<std::sync::Mutex<Emulator>>;
+
+/// Timer cancelling a command that runs longer than its timeout.
+pub struct CommandTimeout {
+    timer: JoinHandle<()>,
+    fired: Arc<AtomicBool>,
+}
+
+impl CommandTimeout {
+    /// Stop the timer once the command finished. Returns `true` if the command timed out.
+    pub fn finish(self) -> bool {
+        self.timer.abort();
+        self.fired.load(Ordering::Relaxed)
+    }
+}
 
 /// Tracks the current state of the shell process.
 pub struct ShellState {
-    /// Current running process (if any)
-    pub process: Arc<Mutex<Option<Child>>>,
     /// Process ID of running command
     pub pid: Arc<Mutex<Option<u32>>>,
+    /// Process group of running command; signalled as a whole when set
+    pub pgid: Arc<Mutex<Option<u32>>>,
+    /// Stdin of the running command (piped and persistent modes)
+    pub stdin: Arc<Mutex<Option<SharedStdin>>>,
     /// Current working directory
     pub cwd: Arc<Mutex<String>>,
+    /// Working directory before the last `cd` (target of `cd -`)
+    pub previous_cwd: Arc<Mutex<Option<String>>>,
+    /// `pushd`/`popd` directory stack, most recently pushed first
+    pub dir_stack: Arc<Mutex<Vec<String>>>,
     /// Is shell currently executing a command?
     pub is_busy: Arc<Mutex<bool>>,
+    /// ID of the running (or last) command; incremented as each command starts
+    pub command_id: Arc<AtomicU64>,
+    /// Command whose cancellation is being escalated (at most one ladder per command)
+    pub escalating: Arc<Mutex<Option<u64>>>,
+    /// Commands started in the background or suspended, plus the foreground command
+    pub jobs: JobTable,
+    /// Timeout applied to commands run without one of their own
+    pub command_timeout: Arc<Mutex<Option<Duration>>>,
+    /// Environment variables set or unset in this session
+    pub env: Arc<Mutex<SessionEnv>>,
+    /// Pseudo-terminal of the running command (PTY mode only)
+    #[cfg(unix)]
+    pub pty: Arc<Mutex<Option<Arc<PtyMaster>>>>,
+    /// Screen emulator fed by the PTY of the running command
+    #[cfg(unix)]
+    pub screen: Arc<Mutex<Option<SharedEmulator>>>,
+    /// Window size applied to PTY-backed commands
+    pub pty_size: Arc<Mutex<PtySize>>,
+    /// Long-lived shell used by `Persistent` execution mode (started on first use)
+    #[cfg(unix)]
+    pub persistent_shell: Arc<Mutex<Option<PersistentShell>>>,
+    /// PID of the persistent shell running the command, spared by `SIGTERM` and `SIGKILL`
+    #[cfg(unix)]
+    pub shell_pid: Arc<Mutex<Option<u32>>>,
 }
 
 impl ShellState {
     /// Create a new shell state with the given initial working directory
     pub fn new(initial_cwd: String) -> Self {
         Self {
-            process: Arc::new(Mutex::new(None)),
             pid: Arc::new(Mutex::new(None)),
+            pgid: Arc::new(Mutex::new(None)),
+            stdin: Arc::new(Mutex::new(None)),
             cwd: Arc::new(Mutex::new(initial_cwd)),
+            previous_cwd: Arc::new(Mutex::new(None)),
+            dir_stack: Arc::new(Mutex::new(Vec::new())),
             is_busy: Arc::new(Mutex::new(false)),
+            command_id: Arc::new(AtomicU64::new(0)),
+            escalating: Arc::new(Mutex::new(None)),
+            jobs: JobTable::default(),
+            command_timeout: Arc::new(Mutex::new(None)),
+            env: Arc::new(Mutex::new(SessionEnv::default())),
+            #[cfg(unix)]
+            pty: Arc::new(Mutex::new(None)),
+            #[cfg(unix)]
+            screen: Arc::new(Mutex::new(None)),
+            pty_size: Arc::new(Mutex::new(PtySize::default())),
+            #[cfg(unix)]
+            persistent_shell: Arc::new(Mutex::new(None)),
+            #[cfg(unix)]
+            shell_pid: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -37,6 +123,42 @@ impl ShellState {
         *self.cwd.lock().await = cwd;
     }
 
+    /// Change the working directory, remembering the old one for `cd -`
+    pub async fn change_cwd(&self, cwd: String) {
+        let old = std::mem::replace(&mut *self.cwd.lock().await, cwd);
+        *self.previous_cwd.lock().await = Some(old);
+    }
+
+    /// Get the working directory before the last change (if any)
+    pub async fn get_previous_cwd(&self) -> Option<String> {
+        self.previous_cwd.lock().await.clone()
+    }
+
+    /// Get the directory stack, most recently pushed first
+    pub async fn get_dir_stack(&self) -> Vec<String> {
+        self.dir_stack.lock().await.clone()
+    }
+
+    /// Replace the directory stack
+    pub async fn set_dir_stack(&self, stack: Vec<String>) {
+        *self.dir_stack.lock().await = stack;
+    }
+
+    /// Get a snapshot of the session environment
+    pub async fn get_env(&self) -> SessionEnv {
+        self.env.lock().await.clone()
+    }
+
+    /// Set an environment variable for subsequent commands
+    pub async fn set_env_var(&self, name: &str, value: &str) -> Result<(), String> {
+        self.env.lock().await.set(name, value)
+    }
+
+    /// Unset an environment variable for subsequent commands
+    pub async fn unset_env_var(&self, name: &str) -> Result<(), String> {
+        self.env.lock().await.unset(name)
+    }
+
     /// Check if shell is currently busy
     pub async fn is_busy(&self) -> bool {
         *self.is_busy.lock().await
@@ -53,11 +175,22 @@ impl ShellState {
     pub async fn try_set_busy(&self) -> bool {
         let mut is_busy = self.is_busy.lock().await;
         if *is_busy {
-            false
-        } else {
-            *is_busy = true;
-            true
+            return false;
         }
+        self.command_id.fetch_add(1, Ordering::Relaxed);
+        *is_busy = true;
+        drop(is_busy);
+        true
+    }
+
+    /// Get the ID of the running command (or of the last one, once it finished)
+    pub fn current_command_id(&self) -> u64 {
+        self.command_id.load(Ordering::Relaxed)
+    }
+
+    /// Check if command `command_id` is still running
+    pub async fn is_running(&self, command_id: u64) -> bool {
+        self.is_busy().await && self.current_command_id() == command_id
     }
 
     /// Get the current process ID (if any)
@@ -79,108 +212,582 @@ impl ShellState {
         }
     }
 
-    /// Set the current process and its PID
-    pub async fn set_process(&self, child: Child) {
-        let pid = child.id();
-        *self.process.lock().await = Some(child);
+    /// Get the process group of the running command (if it runs in its own group)
+    pub async fn get_pgid(&self) -> Option<u32> {
+        *self.pgid.lock().await
+    }
+
+    /// Record a running process group leader (its PID doubles as the group ID)
+    pub async fn set_process_group(&self, pid: Option<u32>) {
         *self.pid.lock().await = pid;
+        *self.pgid.lock().await = pid;
     }
 
-    /// Take the current process (removing it from state)
-    pub async fn take_process(&self) -> Option<Child> {
-        let process = self.process.lock().await.take();
-        *self.pid.lock().await = None;
-        process
+    /// Record the persistent shell running a command in its process group. Cancelling
+    /// the command leaves the shell, and with it the session's shell state, alive.
+    #[cfg(unix)]
+    pub async fn set_persistent_shell(&self, pid: Option<u32>) {
+        self.set_process_group(pid).await;
+        *self.shell_pid.lock().await = pid;
+    }
+
+    /// Set the stdin of the running command
+    pub async fn set_stdin(&self, stdin: SharedStdin) {
+        *self.stdin.lock().await = Some(stdin);
+    }
+
+    /// Attach the input handles of the command entering the foreground
+    pub async fn set_io(&self, io: CommandIo) {
+        *self.stdin.lock().await = io.stdin;
+        #[cfg(unix)]
+        {
+            *self.pty.lock().await = io.pty;
+            *self.screen.lock().await = io.screen;
+        }
+    }
+
+    /// Detach the input handles of the command leaving the foreground
+    pub async fn take_io(&self) -> CommandIo {
+        CommandIo {
+            stdin: self.stdin.lock().await.take(),
+            #[cfg(unix)]
+            pty: self.pty.lock().await.take(),
+            #[cfg(unix)]
+            screen: self.screen.lock().await.take(),
+        }
+    }
+
+    /// Send input to the running command: through its terminal in PTY mode, otherwise
+    /// to its stdin pipe.
+    pub async fn write_stdin(&self, data: &[u8]) -> Result<(), String> {
+        #[cfg(unix)]
+        if let Some(master) = self.get_pty().await {
+            return master
+                .write_all(data)
+                .await
+                .map_err(|e| format!("Failed to write to PTY: {e}"));
+        }
+
+        let stdin = self.stdin.lock().await.clone();
+        let Some(stdin) = stdin else {
+            return Err("Running command does not accept input".to_string());
+        };
+        let mut stdin = stdin.lock().await;
+        stdin
+            .write_all(data)
+            .await
+            .map_err(|e| format!("Failed to write to stdin: {e}"))?;
+        stdin
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to write to stdin: {e}"))
+    }
+
+    /// Send `signal` to the running command's process group.
+    ///
+    /// In PTY mode an interrupt is typed as Ctrl+C instead, so the terminal's line
+    /// discipline interrupts the whole foreground job. A PTY command leads its own
+    /// session, where the kernel discards `SIGTSTP`, so it is stopped with `SIGSTOP`.
+    /// A persistent shell traps the interrupt; `SIGTERM` and `SIGKILL` are sent to the
+    /// processes it started instead, or to the shell as well if it runs the command
+    /// itself (a builtin or a shell loop).
+    pub async fn signal_command(&self, signal: JobSignal) -> Result<(), String> {
+        let Some(pid) = self.get_pid_if_busy().await else {
+            return Err("No command currently running".to_string());
+        };
+
+        #[cfg(unix)]
+        if let Some(master) = self.get_pty().await {
+            match signal {
+                JobSignal::Interrupt => {
+                    tracing::info!("Sending Ctrl+C to PTY of PID: {}", pid);
+                    return master
+                        .write_all(&[0x03])
+                        .await
+                        .map_err(|e| format!("Failed to send Ctrl+C to PTY: {e}"));
+                }
+                JobSignal::Stop => {
+                    return signals::send(pid, Some(pid), JobSignal::ForceStop).await;
+                }
+                _ => {}
+            }
+        }
+
+        #[cfg(unix)]
+        if matches!(signal, JobSignal::Terminate | JobSignal::Kill) {
+            let shell = *self.shell_pid.lock().await;
+            if let Some(shell) = shell {
+                tracing::info!(
+                    "Sending {} to the command of shell {}",
+                    signal.name(),
+                    shell
+                );
+                return signals::send_sparing(shell, shell, signal).await;
+            }
+        }
+
+        tracing::info!("Sending {} to PID: {}", signal.name(), pid);
+        signals::send(pid, self.get_pgid().await, signal).await
+    }
+
+    /// Suspend the foreground job (Ctrl+Z), detaching its input, and release the command
+    /// waiting for it. Returns the suspended job.
+    pub async fn suspend_foreground(&self) -> Result<Arc<Job>, String> {
+        let Some(job) = self.jobs.foreground().await else {
+            return Err("No job to suspend".to_string());
+        };
+
+        self.signal_command(JobSignal::Stop).await?;
+        job.tag.set(job.id);
+        job.set_io(self.take_io().await);
+        job.set_foreground(false);
+        self.jobs.touch(job.id).await;
+        // Last, as it releases the command waiting for the job
+        if !job.suspend() {
+            return Err("Command already finished".to_string());
+        }
+        Ok(job)
+    }
+
+    /// Escalate the cancellation of command `command_id`: while it keeps running, send
+    /// the next signal of the ladder after the delay set by `policy`. `on_escalate` is
+    /// called with each signal before it is sent.
+    ///
+    /// Returns immediately if the command's cancellation is already being escalated.
+    pub async fn escalate_cancel(
+        &self,
+        command_id: u64,
+        policy: EscalationPolicy,
+        mut on_escalate: impl FnMut(JobSignal) + Send,
+    ) {
+        {
+            let mut escalating = self.escalating.lock().await;
+            if *escalating == Some(command_id) {
+                return;
+            }
+            *escalating = Some(command_id);
+        }
+
+        let mut signal = JobSignal::Interrupt;
+        while let (Some(delay), Some(next)) = (policy.delay_after(signal), signal.next()) {
+            tokio::time::sleep(delay).await;
+            if !self.is_running(command_id).await {
+                break;
+            }
+            on_escalate(next);
+            if let Err(e) = self.signal_command(next).await {
+                tracing::warn!("Cancel escalation stopped: {}", e);
+                break;
+            }
+            signal = next;
+        }
+
+        let mut escalating = self.escalating.lock().await;
+        if *escalating == Some(command_id) {
+            *escalating = None;
+        }
+    }
+
+    /// Get the session's default command timeout (if any)
+    pub async fn get_command_timeout(&self) -> Option<Duration> {
+        *self.command_timeout.lock().await
+    }
+
+    /// Set the session's default command timeout; `None` lets commands run indefinitely
+    pub async fn set_command_timeout(&self, timeout: Option<Duration>) {
+        *self.command_timeout.lock().await = timeout;
+    }
+
+    /// Start a timer that cancels command `command_id` as Ctrl+C would if it is still
+    /// running after `timeout`, escalating according to `policy`. `on_signal` is called
+    /// with each signal (starting with `SIGINT`) before it is sent.
+    pub fn start_timeout(
+        &self,
+        command_id: u64,
+        timeout: Duration,
+        policy: EscalationPolicy,
+        mut on_signal: impl FnMut(JobSignal) + Send + 'static,
+    ) -> CommandTimeout {
+        let state = self.clone();
+        let fired = Arc::new(AtomicBool::new(false));
+        let timer = tokio::spawn({
+            let fired = Arc::clone(&fired);
+            async move {
+                tokio::time::sleep(timeout).await;
+                if !state.is_running(command_id).await {
+                    return;
+                }
+                tracing::warn!("Command timed out after {:?}", timeout);
+                fired.store(true, Ordering::Relaxed);
+                on_signal(JobSignal::Interrupt);
+                if let Err(e) = state.signal_command(JobSignal::Interrupt).await {
+                    tracing::warn!("Failed to cancel timed out command: {}", e);
+                    return;
+                }
+                state.escalate_cancel(command_id, policy, on_signal).await;
+            }
+        });
+        CommandTimeout { timer, fired }
     }
 
     /// Clear the current process reference
     pub async fn clear_process(&self) {
-        *self.process.lock().await = None;
         *self.pid.lock().await = None;
+        *self.pgid.lock().await = None;
+        *self.stdin.lock().await = None;
+        #[cfg(unix)]
+        {
+            *self.shell_pid.lock().await = None;
+            self.clear_pty().await;
+        }
+    }
+
+    /// Get the PTY of the running command (if it was started in PTY mode)
+    #[cfg(unix)]
+    pub async fn get_pty(&self) -> Option<Arc<PtyMaster>> {
+        self.pty.lock().await.clone()
+    }
+
+    /// Set the PTY of the running command
+    #[cfg(unix)]
+    pub async fn set_pty(&self, master: Arc<PtyMaster>) {
+        *self.pty.lock().await = Some(master);
+    }
+
+    /// Clear the PTY and screen emulator references
+    #[cfg(unix)]
+    pub async fn clear_pty(&self) {
+        *self.pty.lock().await = None;
+        *self.screen.lock().await = None;
+    }
+
+    /// Get the screen emulator of the running PTY command
+    #[cfg(unix)]
+    pub async fn get_screen(&self) -> Option<SharedEmulator> {
+        self.screen.lock().await.clone()
+    }
+
+    /// Set the screen emulator of the running PTY command
+    #[cfg(unix)]
+    pub async fn set_screen(&self, emulator: SharedEmulator) {
+        *self.screen.lock().await = Some(emulator);
+    }
+
+    /// Get the window size used for PTY-backed commands
+    pub async fn get_pty_size(&self) -> PtySize {
+        *self.pty_size.lock().await
+    }
+
+    /// Set the window size used for PTY-backed commands
+    pub async fn set_pty_size(&self, size: PtySize) {
+        *self.pty_size.lock().await = size;
     }
 }
 
+/// Working directory used when none is given: the process's, or `/` if unavailable
+fn process_cwd() -> String {
+    std::env::current_dir().map_or_else(|_| "/".to_string(), |p| p.to_string_lossy().to_string())
+}
+
 impl Default for ShellState {
     fn default() -> Self {
-        let initial_cwd = std::env::current_dir()
-            .map_or_else(|_| "/".to_string(), |p| p.to_string_lossy().to_string());
-        Self::new(initial_cwd)
+        Self::new(process_cwd())
     }
 }
 
 impl Clone for ShellState {
     fn clone(&self) -> Self {
         Self {
-            process: Arc::clone(&self.process),
             pid: Arc::clone(&self.pid),
+            pgid: Arc::clone(&self.pgid),
+            stdin: Arc::clone(&self.stdin),
             cwd: Arc::clone(&self.cwd),
+            previous_cwd: Arc::clone(&self.previous_cwd),
+            dir_stack: Arc::clone(&self.dir_stack),
             is_busy: Arc::clone(&self.is_busy),
+            command_id: Arc::clone(&self.command_id),
+            escalating: Arc::clone(&self.escalating),
+            jobs: self.jobs.clone(),
+            command_timeout: Arc::clone(&self.command_timeout),
+            env: Arc::clone(&self.env),
+            #[cfg(unix)]
+            pty: Arc::clone(&self.pty),
+            #[cfg(unix)]
+            screen: Arc::clone(&self.screen),
+            pty_size: Arc::clone(&self.pty_size),
+            #[cfg(unix)]
+            persistent_shell: Arc::clone(&self.persistent_shell),
+            #[cfg(unix)]
+            shell_pid: Arc::clone(&self.shell_pid),
         }
     }
 }
 
-/// Manages shell process lifecycle and history buffer
+/// Registry of terminal sessions, each with its own shell state and history buffer.
+///
+/// Sessions run independently, so a long-running command in one does not block
+/// commands in another.
 pub struct ShellManager {
-    /// Shell state (process, cwd, busy flag)
-    pub shell_state: ShellState,
-    /// History buffer for terminal output
-    pub history_buffer: HistoryBuffer,
+    /// Open sessions in creation order
+    sessions: Arc<RwLock<Vec<Session>>>,
+    /// Counter used to generate session IDs
+    next_id: Arc<AtomicU64>,
+    /// Working directory for sessions created without one
+    default_cwd: String,
+    /// History buffer capacity of sessions created from now on
+    buffer_capacity: Arc<AtomicUsize>,
+    /// Delays between the signals sent to a cancelled command
+    cancel_policy: Arc<RwLock<EscalationPolicy>>,
+    /// On-disk scrollback of each session, if enabled
+    scrollback: Option<Arc<ScrollbackStore>>,
+    /// Command lines entered in any session
+    command_history: Arc<CommandHistory>,
+    /// Tab completion engine shared by all sessions
+    completer: Arc<Completer>,
+    /// Shell of sessions created without one of their own
+    shell_config: Arc<RwLock<ShellConfig>>,
+    /// Command timeout of sessions created from now on
+    command_timeout: Arc<RwLock<Option<Duration>>>,
 }
 
 impl ShellManager {
     /// Create a new shell manager
     pub fn new() -> Self {
-        Self {
-            shell_state: ShellState::default(),
-            history_buffer: HistoryBuffer::default(),
-        }
+        Self::with_capacity(HistoryBuffer::DEFAULT_CAPACITY)
     }
 
     /// Create a new shell manager with an explicit initial working directory
     pub fn new_with_cwd(initial_cwd: String) -> Self {
-        Self {
-            shell_state: ShellState::new(initial_cwd),
-            history_buffer: HistoryBuffer::default(),
-        }
+        Self::with_capacity_and_cwd(HistoryBuffer::DEFAULT_CAPACITY, initial_cwd)
     }
 
-    /// Create a new shell manager with custom capacity
+    /// Create a new shell manager with custom per-session buffer capacity
     pub fn with_capacity(buffer_capacity: usize) -> Self {
-        Self {
-            shell_state: ShellState::default(),
-            history_buffer: HistoryBuffer::new(buffer_capacity),
-        }
+        Self::with_capacity_and_cwd(buffer_capacity, process_cwd())
     }
 
-    /// Create a new shell manager with custom capacity and explicit CWD
+    /// Create a new shell manager with custom per-session buffer capacity and explicit CWD
     pub fn with_capacity_and_cwd(buffer_capacity: usize, initial_cwd: String) -> Self {
         Self {
-            shell_state: ShellState::new(initial_cwd),
-            history_buffer: HistoryBuffer::new(buffer_capacity),
+            sessions: Arc::new(RwLock::new(Vec::new())),
+            next_id: Arc::new(AtomicU64::new(1)),
+            default_cwd: initial_cwd,
+            buffer_capacity: Arc::new(AtomicUsize::new(buffer_capacity)),
+            cancel_policy: Arc::new(RwLock::new(EscalationPolicy::default())),
+            scrollback: None,
+            command_history: Arc::new(CommandHistory::default()),
+            completer: Arc::new(Completer::default()),
+            shell_config: Arc::new(RwLock::new(ShellConfig::default())),
+            command_timeout: Arc::new(RwLock::new(None)),
         }
     }
 
-    /// Get the current working directory
-    pub async fn get_cwd(&self) -> String {
-        self.shell_state.get_cwd().await
+    /// Complete command lines with `completer` (to add completion providers)
+    #[must_use]
+    pub fn with_completer(mut self, completer: Completer) -> Self {
+        self.completer = Arc::new(completer);
+        self
     }
 
-    /// Check if shell is currently busy
-    pub async fn is_busy(&self) -> bool {
-        self.shell_state.is_busy().await
+    /// Tab completion engine shared by all sessions
+    pub fn completer(&self) -> Arc<Completer> {
+        Arc::clone(&self.completer)
     }
 
-    /// Atomically try to set busy state from false to true.
-    /// Returns true if successfully transitioned from false to true,
-    /// false if already busy.
-    pub async fn try_set_busy(&self) -> bool {
-        self.shell_state.try_set_busy().await
+    /// Record the commands entered in every session to `history`
+    #[must_use]
+    pub fn with_command_history(mut self, history: CommandHistory) -> Self {
+        self.command_history = Arc::new(history);
+        self
+    }
+
+    /// Command lines entered in any session
+    pub fn command_history(&self) -> &CommandHistory {
+        &self.command_history
+    }
+
+    /// Persist the output of every session created from now on to `store`
+    #[must_use]
+    pub fn with_scrollback(mut self, store: ScrollbackStore) -> Self {
+        self.scrollback = Some(Arc::new(store));
+        self
+    }
+
+    /// Recreate the sessions saved in the scrollback store, with their output, so a
+    /// restart or crash does not lose it. Returns the number of sessions restored.
+    pub fn restore_sessions(&self) -> usize {
+        let Some(store) = &self.scrollback else {
+            return 0;
+        };
+        store.cleanup();
+
+        let mut restored = 0;
+        for id in store.saved_sessions() {
+            // Leave room for the notification below
+            let capacity = self.buffer_capacity();
+            let lines = store.load(&id, capacity.saturating_sub(1));
+            if lines.is_empty() {
+                store.remove(&id);
+                continue;
+            }
+
+            let session = Session::new(id.clone(), self.default_cwd.clone(), capacity)
+                .with_scrollback(store.writer(&id))
+                .with_shell(Shell::resolve(&self.shell_config()))
+                .with_command_timeout(self.command_timeout());
+            let count = lines.len();
+            for line in lines {
+                session.history_buffer.push(line);
+            }
+            session.history_buffer.push(OutputLine::Notification {
+                message: format!("Restored session: {count} lines from a previous run"),
+                level: NotificationLevel::Info,
+                timestamp: current_timestamp_ms(),
+            });
+
+            if let Some(number) = session_number(&id) {
+                self.next_id.fetch_max(number + 1, Ordering::Relaxed);
+            }
+            self.sessions
+                .write()
+                .expect("ShellManager::restore_sessions failed to acquire write lock on sessions")
+                .push(session);
+            restored += 1;
+        }
+        restored
+    }
+
+    /// Create a session, starting in `cwd` or the manager's default directory and
+    /// running commands with `shell` or the default shell
+    pub fn create_session(&self, cwd: Option<String>, shell: Option<ShellConfig>) -> Session {
+        let id = format!("session-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        let shell = shell.unwrap_or_else(|| self.shell_config());
+        let mut session = Session::new(
+            id,
+            cwd.unwrap_or_else(|| self.default_cwd.clone()),
+            self.buffer_capacity(),
+        )
+        .with_shell(Shell::resolve(&shell))
+        .with_command_timeout(self.command_timeout());
+        if let Some(store) = &self.scrollback {
+            let writer = store.writer(&session.id);
+            session = session.with_scrollback(writer);
+        }
+        self.sessions
+            .write()
+            .expect("ShellManager::create_session failed to acquire write lock on sessions")
+            .push(session.clone());
+        session
+    }
+
+    /// Look up a session by ID
+    pub fn get_session(&self, id: &str) -> Result<Session, String> {
+        self.sessions
+            .read()
+            .expect("ShellManager::get_session failed to acquire read lock on sessions")
+            .iter()
+            .find(|session| session.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown session: {id}"))
+    }
+
+    /// Remove a session from the registry, returning it
+    pub fn close_session(&self, id: &str) -> Option<Session> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .expect("ShellManager::close_session failed to acquire write lock on sessions");
+        let index = sessions.iter().position(|session| session.id == id)?;
+        let session = sessions.remove(index);
+        drop(sessions);
+
+        // A closed session's output is not restored
+        if let Some(scrollback) = &session.scrollback {
+            scrollback.close();
+        }
+        if let Some(store) = &self.scrollback {
+            store.remove(id);
+        }
+        Some(session)
+    }
+
+    /// Get the delays between the signals sent to a cancelled command
+    pub fn cancel_policy(&self) -> EscalationPolicy {
+        *self
+            .cancel_policy
+            .read()
+            .expect("ShellManager::cancel_policy failed to acquire read lock on cancel_policy")
+    }
+
+    /// Set the delays between the signals sent to a cancelled command
+    pub fn set_cancel_policy(&self, policy: EscalationPolicy) {
+        *self.cancel_policy.write().expect(
+            "ShellManager::set_cancel_policy failed to acquire write lock on cancel_policy",
+        ) = policy;
+    }
+
+    /// History buffer capacity of sessions created from now on
+    pub fn buffer_capacity(&self) -> usize {
+        self.buffer_capacity.load(Ordering::Relaxed)
+    }
+
+    /// Set the history buffer capacity of sessions created from now on.
+    /// Existing sessions keep theirs.
+    pub fn set_buffer_capacity(&self, capacity: usize) {
+        self.buffer_capacity
+            .store(capacity.max(1), Ordering::Relaxed);
+    }
+
+    /// Apply the user configuration to sessions created from now on. A changed command
+    /// timeout also replaces the timeout of every open session.
+    pub async fn apply_config(&self, config: &Config) {
+        self.set_buffer_capacity(config.scrollback.lines);
+        self.set_shell_config(config.shell.clone());
+
+        let timeout = config.commands.timeout();
+        let previous = std::mem::replace(
+            &mut *self.command_timeout.write().expect(
+                "ShellManager::apply_config failed to acquire write lock on command_timeout",
+            ),
+            timeout,
+        );
+        if previous != timeout {
+            for session in self.sessions() {
+                session.shell_state.set_command_timeout(timeout).await;
+            }
+        }
+    }
+
+    /// Command timeout of sessions created from now on
+    pub fn command_timeout(&self) -> Option<Duration> {
+        *self
+            .command_timeout
+            .read()
+            .expect("ShellManager::command_timeout failed to acquire read lock on command_timeout")
     }
 
-    /// Get the current process ID (if a command is running).
-    /// This is atomic - avoids TOCTOU race between busy check and PID retrieval.
-    pub async fn get_running_pid(&self) -> Option<u32> {
-        self.shell_state.get_pid_if_busy().await
+    /// Get the shell of sessions created without one of their own
+    pub fn shell_config(&self) -> ShellConfig {
+        self.shell_config
+            .read()
+            .expect("ShellManager::shell_config failed to acquire read lock on shell_config")
+            .clone()
+    }
+
+    /// Set the shell of sessions created from now on without one of their own.
+    /// Existing sessions keep their shell.
+    pub fn set_shell_config(&self, config: ShellConfig) {
+        *self.shell_config.write().expect(
+            "ShellManager::set_shell_config failed to acquire write lock on shell_config",
+        ) = config;
+    }
+
+    /// All open sessions in creation order
+    pub fn sessions(&self) -> Vec<Session> {
+        self.sessions
+            .read()
+            .expect("ShellManager::sessions failed to acquire read lock on sessions")
+            .clone()
     }
 }
 
@@ -193,8 +800,16 @@ impl Default for ShellManager {
 impl Clone for ShellManager {
     fn clone(&self) -> Self {
         Self {
-            shell_state: self.shell_state.clone(),
-            history_buffer: self.history_buffer.clone(),
+            sessions: Arc::clone(&self.sessions),
+            next_id: Arc::clone(&self.next_id),
+            default_cwd: self.default_cwd.clone(),
+            buffer_capacity: Arc::clone(&self.buffer_capacity),
+            cancel_policy: Arc::clone(&self.cancel_policy),
+            scrollback: self.scrollback.clone(),
+            command_history: Arc::clone(&self.command_history),
+            completer: Arc::clone(&self.completer),
+            shell_config: Arc::clone(&self.shell_config),
+            command_timeout: Arc::clone(&self.command_timeout),
         }
     }
 }
@@ -219,6 +834,29 @@ mod tests {
         assert_eq!(state.get_cwd().await, "/home/user");
     }
 
+    #[tokio::test]
+    async fn test_shell_state_change_cwd_tracks_previous() {
+        let state = ShellState::new("/tmp".to_string());
+        assert!(state.get_previous_cwd().await.is_none());
+
+        state.change_cwd("/var".to_string()).await;
+        assert_eq!(state.get_cwd().await, "/var");
+        assert_eq!(state.get_previous_cwd().await.as_deref(), Some("/tmp"));
+    }
+
+    #[tokio::test]
+    async fn test_shell_state_env_shared_between_clones() {
+        let state = ShellState::default();
+        let cloned = state.clone();
+
+        state.set_env_var("AWS_PROFILE", "staging").await.unwrap();
+        assert_eq!(
+            cloned.get_env().await.get("AWS_PROFILE").as_deref(),
+            Some("staging")
+        );
+        assert!(state.set_env_var("NOT-VALID", "x").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_shell_state_busy_flag() {
         let state = ShellState::default();
@@ -232,25 +870,25 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_shell_manager_creation() {
-        let manager = ShellManager::new();
-        assert!(!manager.is_busy().await);
-        assert!(manager.history_buffer.is_empty());
-    }
+    async fn test_shell_state_pty_size() {
+        let state = ShellState::default();
+        assert_eq!(state.get_pty_size().await, PtySize::default());
 
-    #[tokio::test]
-    async fn test_shell_manager_with_custom_capacity() {
-        let manager = ShellManager::with_capacity(100);
-        assert_eq!(manager.history_buffer.len(), 0);
+        let cloned = state.clone();
+        state.set_pty_size(PtySize::new(40, 132)).await;
+        assert_eq!(cloned.get_pty_size().await, PtySize::new(40, 132));
     }
 
     #[tokio::test]
-    async fn test_shell_manager_clone_shares_state() {
-        let manager = ShellManager::new();
-        let cloned = manager.clone();
+    async fn test_process_group_tracking() {
+        let state = ShellState::default();
+        state.set_process_group(Some(4321)).await;
+        assert_eq!(state.get_pid().await, Some(4321));
+        assert_eq!(state.get_pgid().await, Some(4321));
 
-        manager.shell_state.set_busy(true).await;
-        assert!(cloned.is_busy().await);
+        state.clear_process().await;
+        assert!(state.get_pid().await.is_none());
+        assert!(state.get_pgid().await.is_none());
     }
 
     #[tokio::test]
@@ -270,18 +908,6 @@ mod tests {
         assert_eq!(state.get_pid_if_busy().await, Some(5678));
     }
 
-    #[tokio::test]
-    async fn test_get_running_pid_atomic() {
-        let manager = ShellManager::new();
-        // Set PID but not busy
-        *manager.shell_state.pid.lock().await = Some(9999);
-        assert!(manager.get_running_pid().await.is_none());
-
-        // Now set busy
-        manager.shell_state.set_busy(true).await;
-        assert_eq!(manager.get_running_pid().await, Some(9999));
-    }
-
     #[tokio::test]
     async fn test_try_set_busy_returns_true_when_not_busy() {
         let state = ShellState::default();
@@ -304,21 +930,331 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_try_set_busy_manager_delegates_correctly() {
+    async fn test_command_ids_advance_per_command() {
+        let state = ShellState::default();
+        assert!(state.try_set_busy().await);
+        let first = state.current_command_id();
+        assert!(state.is_running(first).await);
+
+        state.set_busy(false).await;
+        assert!(!state.is_running(first).await);
+
+        assert!(state.try_set_busy().await);
+        assert_ne!(state.current_command_id(), first);
+        assert!(!state.is_running(first).await);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_escalation_stops_command_ignoring_interrupt() {
+        use std::time::Duration;
+
+        let state = ShellState::default();
+        assert!(state.try_set_busy().await);
+        let command_id = state.current_command_id();
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg("trap '' INT; sleep 30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        state.set_process_group(child.id()).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        state.signal_command(JobSignal::Interrupt).await.unwrap();
+        let policy = EscalationPolicy {
+            terminate_after: Duration::from_millis(100),
+            kill_after: Duration::from_millis(100),
+        };
+        let mut sent = Vec::new();
+        let waiter = async {
+            let status = child.wait().await.unwrap();
+            state.set_busy(false).await;
+            status
+        };
+        let (status, ()) = tokio::join!(
+            waiter,
+            state.escalate_cancel(command_id, policy, |signal| sent.push(signal))
+        );
+
+        assert!(!status.success());
+        assert_eq!(sent.first(), Some(&JobSignal::Terminate));
+        assert!(state.escalating.lock().await.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_cancels_command() {
+        use std::time::Duration;
+
+        let state = ShellState::default();
+        assert!(state.try_set_busy().await);
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        state.set_process_group(child.id()).await;
+
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let timeout = state.start_timeout(
+            state.current_command_id(),
+            Duration::from_millis(100),
+            EscalationPolicy::default(),
+            {
+                let sent = Arc::clone(&sent);
+                move |signal| sent.lock().unwrap().push(signal)
+            },
+        );
+        let status = tokio::time::timeout(Duration::from_secs(2), child.wait())
+            .await
+            .expect("Timed out command should be interrupted")
+            .unwrap();
+        state.set_busy(false).await;
+
+        assert!(!status.success());
+        assert!(timeout.finish());
+        assert_eq!(*sent.lock().unwrap(), vec![JobSignal::Interrupt]);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_not_fired_for_finished_command() {
+        use std::time::Duration;
+
+        let state = ShellState::default();
+        assert!(state.try_set_busy().await);
+        let timeout = state.start_timeout(
+            state.current_command_id(),
+            Duration::from_millis(20),
+            EscalationPolicy::default(),
+            |_| {},
+        );
+        state.set_busy(false).await;
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(!timeout.finish());
+
+        state
+            .set_command_timeout(Some(Duration::from_secs(5)))
+            .await;
+        assert_eq!(
+            state.clone().get_command_timeout().await,
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_cancel_policy_is_shared() {
         let manager = ShellManager::new();
-        assert!(!manager.is_busy().await);
+        let cloned = manager.clone();
+        let policy = EscalationPolicy {
+            terminate_after: std::time::Duration::from_secs(1),
+            kill_after: std::time::Duration::from_secs(10),
+        };
+        manager.set_cancel_policy(policy);
+        assert_eq!(cloned.cancel_policy(), policy);
+    }
 
-        let result = manager.try_set_busy().await;
-        assert!(result);
-        assert!(manager.is_busy().await);
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_policy_sets_escalation_delays() {
+        use std::time::{Duration, Instant};
+
+        let manager = ShellManager::new();
+        manager.set_cancel_policy(EscalationPolicy {
+            terminate_after: Duration::from_millis(100),
+            kill_after: Duration::from_millis(200),
+        });
+        let session = manager.create_session(None, None);
+        let state = &session.shell_state;
+        assert!(state.try_set_busy().await);
+        let command_id = state.current_command_id();
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg("trap '' INT TERM; sleep 30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        state.set_process_group(child.id()).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        state.signal_command(JobSignal::Interrupt).await.unwrap();
+        let started = Instant::now();
+        let mut sent = Vec::new();
+        let waiter = async {
+            child.wait().await.unwrap();
+            state.set_busy(false).await;
+        };
+        tokio::join!(
+            waiter,
+            state.escalate_cancel(command_id, manager.cancel_policy(), |signal| {
+                sent.push((signal, started.elapsed()));
+            })
+        );
+
+        // The default policy waits 3 s before each signal
+        let [(JobSignal::Terminate, terminate), (JobSignal::Kill, kill)] = sent[..] else {
+            panic!("Unexpected escalation: {sent:?}");
+        };
+        assert!(terminate >= Duration::from_millis(100));
+        assert!(kill >= terminate + Duration::from_millis(200));
+        assert!(kill < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_independent() {
+        let manager = ShellManager::with_capacity_and_cwd(100, "/tmp".to_string());
+        let first = manager.create_session(None, None);
+        let second = manager.create_session(Some("/".to_string()), None);
+        assert_ne!(first.id, second.id);
+        assert_eq!(second.get_cwd().await, "/");
+
+        // A busy session does not block another one
+        assert!(first.try_set_busy().await);
+        assert!(second.try_set_busy().await);
+
+        first.shell_state.set_cwd("/var".to_string()).await;
+        assert_eq!(second.get_cwd().await, "/");
+        assert_eq!(first.history_buffer.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_lookup_and_close() {
+        let manager = ShellManager::new();
+        let cloned = manager.clone();
+        let first = manager.create_session(None, None);
+        let second = manager.create_session(None, None);
+
+        // Lookups return handles sharing the session's state
+        first.shell_state.set_busy(true).await;
+        assert!(cloned.get_session(&first.id).unwrap().is_busy().await);
+
+        let ids: Vec<String> = manager.sessions().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![first.id.clone(), second.id.clone()]);
+
+        assert!(manager.close_session(&first.id).is_some());
+        assert!(manager.close_session(&first.id).is_none());
+        assert!(manager.get_session(&first.id).is_err());
+        assert_eq!(manager.sessions().len(), 1);
+
+        // IDs are never reused
+        let third = manager.create_session(None, None);
+        assert_ne!(third.id, first.id);
+    }
+
+    #[test]
+    fn test_session_shell_from_default_or_override() {
+        let manager = ShellManager::new();
+        manager.set_shell_config(ShellConfig {
+            program: Some("/bin/bash".to_string()),
+            login: true,
+            interactive: false,
+        });
+        let default = manager.create_session(None, None);
+        assert_eq!(default.shell.program, "/bin/bash");
+        assert!(default.shell.login);
+
+        let fish = manager.create_session(
+            None,
+            Some(ShellConfig {
+                program: Some("fish".to_string()),
+                ..ShellConfig::default()
+            }),
+        );
+        assert_eq!(fish.shell.kind, crate::process::shell::ShellKind::Fish);
+        assert!(!fish.shell.login);
+        // Existing sessions keep their shell
+        manager.set_shell_config(ShellConfig::default());
+        assert_eq!(
+            manager.get_session(&default.id).unwrap().shell.program,
+            "/bin/bash"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_affects_new_sessions() {
+        let manager = ShellManager::with_capacity(100);
+        let before = manager.create_session(None, None);
+
+        let mut config = Config::default();
+        config.scrollback.lines = 2;
+        config.shell.login = true;
+        manager.apply_config(&config).await;
+        assert_eq!(manager.buffer_capacity(), 2);
+
+        let after = manager.create_session(None, None);
+        assert!(after.shell.login);
+        assert!(!before.shell.login);
+        for text in ["a", "b", "c"] {
+            after.history_buffer.push(OutputLine::Stdout {
+                text: text.to_string(),
+                timestamp: 0,
+            });
+        }
+        assert_eq!(after.history_buffer.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_sets_command_timeout() {
+        let manager = ShellManager::new();
+        let open = manager.create_session(None, None);
+
+        let mut config = Config::default();
+        config.commands.timeout_ms = 5000;
+        manager.apply_config(&config).await;
+        let timeout = Some(Duration::from_secs(5));
+        assert_eq!(open.shell_state.get_command_timeout().await, timeout);
+        let created = manager.create_session(None, None);
+        assert_eq!(created.shell_state.get_command_timeout().await, timeout);
+
+        // A session's own timeout survives a reload that leaves the setting alone
+        open.shell_state.set_command_timeout(None).await;
+        config.scrollback.lines = 50;
+        manager.apply_config(&config).await;
+        assert_eq!(open.shell_state.get_command_timeout().await, None);
+    }
+
+    #[test]
+    fn test_command_history_is_shared() {
+        let manager = ShellManager::new().with_command_history(CommandHistory::new(10));
+        let cloned = manager.clone();
+        manager
+            .command_history()
+            .record(crate::models::CommandHistoryEntry {
+                command: "ls".to_string(),
+                cwd: "/".to_string(),
+                exit_code: Some(0),
+                timestamp: 1,
+            });
+        assert_eq!(cloned.command_history().len(), 1);
+    }
+
+    #[test]
+    fn test_restore_sessions_from_scrollback() {
+        let dir = std::env::temp_dir().join(format!("cepheus-restore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let line = OutputLine::Stdout {
+            text: "before restart".to_string(),
+            timestamp: 1,
+        };
+
+        let manager =
+            ShellManager::with_capacity(100).with_scrollback(ScrollbackStore::open(&dir).unwrap());
+        let kept = manager.create_session(None, None);
+        let closed = manager.create_session(None, None);
+        kept.persist(&line);
+        closed.persist(&line);
+        manager.close_session(&closed.id);
 
-        // Try again when already busy
-        let result2 = manager.try_set_busy().await;
-        assert!(!result2);
-        assert!(manager.is_busy().await);
+        let restarted =
+            ShellManager::with_capacity(100).with_scrollback(ScrollbackStore::open(&dir).unwrap());
+        assert_eq!(restarted.restore_sessions(), 1);
+        let restored = restarted.get_session(&kept.id).unwrap();
+        let history = restored.history_buffer.get_all();
+        assert_eq!(history[0], line);
+        assert!(restored.history_buffer.contains_warning("Restored session"));
 
-        // Clean up
-        manager.shell_state.set_busy(false).await;
-        assert!(!manager.is_busy().await);
+        // New sessions do not reuse a restored ID
+        assert_eq!(restarted.create_session(None, None).id, "session-2");
+        let _ = std::fs::remove_dir_all(&dir);
     }
 }
@@ -7,11 +7,12 @@ use std::time::Duration;
 use tokio::time::timeout;
 
 use cepheus_lib::models::{CommandResponse, OutputLine};
-use cepheus_lib::state::ShellManager;
+use cepheus_lib::process::signals::{EscalationPolicy, JobSignal};
+use cepheus_lib::state::{Session, ShellManager};
 
-/// Helper to create a test shell manager
-fn create_test_manager() -> ShellManager {
-    ShellManager::with_capacity(100)
+/// Helper to create a test session
+fn create_test_session() -> Session {
+    ShellManager::with_capacity(100).create_session(None, None)
 }
 
 fn build_shell_command_test(command: &str) -> tokio::process::Command {
@@ -33,7 +34,7 @@ fn build_shell_command_test(command: &str) -> tokio::process::Command {
 // T017: Integration test for execute_command with echo
 #[tokio::test]
 async fn test_execute_echo_command() {
-    let manager = create_test_manager();
+    let session = create_test_session();
 
     // Execute a simple echo command
     #[cfg(windows)]
@@ -43,7 +44,7 @@ async fn test_execute_echo_command() {
 
     let result = timeout(
         Duration::from_secs(5),
-        execute_command_test(&manager, cmd, None),
+        execute_command_test(&session, cmd, None),
     )
     .await
     .expect("Command timed out");
@@ -54,7 +55,7 @@ async fn test_execute_echo_command() {
     assert_eq!(response.exit_code, Some(0));
 
     // Check that output was captured in history
-    let history = manager.history_buffer.get_all();
+    let history = session.history_buffer.get_all();
     assert!(!history.is_empty(), "History should have output");
 
     // Find stdout line containing "hello world"
@@ -70,7 +71,7 @@ async fn test_execute_echo_command() {
 
 #[tokio::test]
 async fn test_execute_pwd_command() {
-    let manager = create_test_manager();
+    let session = create_test_session();
 
     #[cfg(windows)]
     let cmd = "cd";
@@ -79,7 +80,7 @@ async fn test_execute_pwd_command() {
 
     let result = timeout(
         Duration::from_secs(5),
-        execute_command_test(&manager, cmd, None),
+        execute_command_test(&session, cmd, None),
     )
     .await
     .expect("Command timed out");
@@ -89,13 +90,13 @@ async fn test_execute_pwd_command() {
     assert!(response.success);
 
     // History should contain current directory output
-    let history = manager.history_buffer.get_all();
+    let history = session.history_buffer.get_all();
     assert!(!history.is_empty());
 }
 
 #[tokio::test]
 async fn test_execute_command_with_stderr() {
-    let manager = create_test_manager();
+    let session = create_test_session();
 
     // Command that writes to stderr
     #[cfg(windows)]
@@ -105,14 +106,14 @@ async fn test_execute_command_with_stderr() {
 
     let result = timeout(
         Duration::from_secs(5),
-        execute_command_test(&manager, cmd, None),
+        execute_command_test(&session, cmd, None),
     )
     .await
     .expect("Command timed out");
 
     assert!(result.is_ok());
 
-    let history = manager.history_buffer.get_all();
+    let history = session.history_buffer.get_all();
     let has_stderr = history
         .iter()
         .any(|line| matches!(line, OutputLine::Stderr { .. }));
@@ -121,7 +122,7 @@ async fn test_execute_command_with_stderr() {
 
 #[tokio::test]
 async fn test_execute_failing_command() {
-    let manager = create_test_manager();
+    let session = create_test_session();
 
     // Command that exits with non-zero status
     #[cfg(windows)]
@@ -131,7 +132,7 @@ async fn test_execute_failing_command() {
 
     let result = timeout(
         Duration::from_secs(5),
-        execute_command_test(&manager, cmd, None),
+        execute_command_test(&session, cmd, None),
     )
     .await
     .expect("Command timed out");
@@ -145,7 +146,7 @@ async fn test_execute_failing_command() {
 // T018: Integration test for cancel_command with sleep
 #[tokio::test]
 async fn test_cancel_command() {
-    let manager = create_test_manager();
+    let session = create_test_session();
 
     // Start a long-running command
     #[cfg(windows)]
@@ -153,17 +154,17 @@ async fn test_cancel_command() {
     #[cfg(not(windows))]
     let cmd = "sleep 30";
 
-    let manager_clone = manager.clone();
-    let handle = tokio::spawn(async move { execute_command_test(&manager_clone, cmd, None).await });
+    let session_clone = session.clone();
+    let handle = tokio::spawn(async move { execute_command_test(&session_clone, cmd, None).await });
 
     // Wait a bit for the command to start
     tokio::time::sleep(Duration::from_millis(200)).await;
 
     // Verify command is running
-    assert!(manager.is_busy().await, "Command should be running (busy)");
+    assert!(session.is_busy().await, "Command should be running (busy)");
 
     // Cancel the command
-    let cancel_result = cancel_command_test(&manager).await;
+    let cancel_result = cancel_command_test(&session, EscalationPolicy::default()).await;
     assert!(cancel_result.is_ok(), "Cancel should succeed");
 
     // Wait for the spawned task to complete
@@ -176,35 +177,221 @@ async fn test_cancel_command() {
     // (didn't hang for 30 seconds), so no additional assertion is needed
 }
 
+#[cfg(not(windows))]
+#[tokio::test]
+async fn test_write_stdin_to_running_command() {
+    let session = create_test_session();
+
+    let session_clone = session.clone();
+    let handle = tokio::spawn(async move {
+        execute_command_test(&session_clone, "read name; echo \"hello $name\"", None).await
+    });
+
+    // Wait a bit for the command to start
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(session.is_busy().await, "Command should wait for input");
+
+    session
+        .shell_state
+        .write_stdin(b"cepheus\n")
+        .await
+        .expect("Input should be delivered");
+
+    let result = timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("Command should finish once input arrives")
+        .expect("Task should not panic");
+    assert_eq!(result.unwrap().exit_code, Some(0));
+
+    let has_greeting = session
+        .history_buffer
+        .get_all()
+        .iter()
+        .any(|line| matches!(line, OutputLine::Stdout { text, .. } if text == "hello cepheus"));
+    assert!(has_greeting, "Command should echo the input it read");
+
+    // Nothing is running any more
+    assert!(session.shell_state.write_stdin(b"late\n").await.is_err());
+}
+
+// Cancelling signals the whole process group, so the shell's children stop too
+#[cfg(not(windows))]
+#[tokio::test]
+async fn test_cancel_stops_pipeline() {
+    let session = create_test_session();
+
+    let session_clone = session.clone();
+    let handle = tokio::spawn(async move {
+        execute_command_test(&session_clone, "sleep 30 | cat; sleep 30", None).await
+    });
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(session.is_busy().await, "Command should be running (busy)");
+
+    cancel_command_test(&session, EscalationPolicy::default())
+        .await
+        .expect("Cancel should succeed");
+
+    timeout(Duration::from_secs(2), handle)
+        .await
+        .expect("Every process of the pipeline should stop")
+        .expect("Task should not panic")
+        .unwrap();
+}
+
+// A command ignoring SIGINT is stopped by the escalation ladder
+#[cfg(not(windows))]
+#[tokio::test]
+async fn test_cancel_escalates_when_interrupt_ignored() {
+    let session = create_test_session();
+
+    let session_clone = session.clone();
+    let handle = tokio::spawn(async move {
+        execute_command_test(&session_clone, "trap '' INT; sleep 30", None).await
+    });
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let policy = EscalationPolicy {
+        terminate_after: Duration::from_millis(200),
+        kill_after: Duration::from_millis(200),
+    };
+    cancel_command_test(&session, policy)
+        .await
+        .expect("Cancel should succeed");
+
+    // SIGINT alone does not stop it
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(session.is_busy().await, "Command should ignore SIGINT");
+
+    let result = timeout(Duration::from_secs(2), handle)
+        .await
+        .expect("Escalation should stop the command")
+        .expect("Task should not panic")
+        .unwrap();
+    assert!(!result.success);
+
+    // Nothing left to cancel
+    assert!(cancel_command_test(&session, policy).await.is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_suspended_job_resumes_with_bg() {
+    use cepheus_lib::process::builtins::{self, Builtin, BuiltinKind};
+    use std::time::Instant;
+
+    let session = create_test_session();
+    let started = Instant::now();
+
+    let session_clone = session.clone();
+    let handle =
+        tokio::spawn(async move { execute_command_test(&session_clone, "sleep 0.3", None).await });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Suspend it the way Ctrl+Z does
+    let job = session
+        .shell_state
+        .suspend_foreground()
+        .await
+        .expect("Suspend should succeed");
+    assert_eq!(job.command, "sleep 0.3");
+
+    tokio::time::sleep(Duration::from_millis(700)).await;
+    assert!(!handle.is_finished(), "Stopped command should not finish");
+
+    let bg = Builtin {
+        kind: BuiltinKind::Bg,
+        args: Vec::new(),
+    };
+    let output = builtins::run(&bg, &session.shell_state).await;
+    assert_eq!(output.stdout, vec!["[1]+ sleep 0.3 &"]);
+
+    let result = timeout(Duration::from_secs(2), handle)
+        .await
+        .expect("Resumed command should finish")
+        .expect("Task should not panic")
+        .unwrap();
+    assert!(result.success);
+    assert!(started.elapsed() >= Duration::from_millis(800));
+}
+
+// Cancelling a persistent shell command stops the command but keeps the shell, so
+// variables exported earlier are still set
+#[cfg(unix)]
+#[tokio::test]
+async fn test_cancel_keeps_persistent_shell_state() {
+    let session = create_test_session();
+    execute_persistent_test(&session, "export FOO=1")
+        .await
+        .unwrap();
+
+    // Only SIGKILL stops it, which would take the shell down with it
+    let session_clone = session.clone();
+    let handle = tokio::spawn(async move {
+        execute_persistent_test(&session_clone, "sh -c \"trap '' INT TERM; sleep 30\"").await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let policy = EscalationPolicy {
+        terminate_after: Duration::from_millis(200),
+        kill_after: Duration::from_millis(200),
+    };
+    cancel_command_test(&session, policy)
+        .await
+        .expect("Cancel should succeed");
+
+    let result = timeout(Duration::from_secs(3), handle)
+        .await
+        .expect("Escalation should stop the command")
+        .expect("Task should not panic")
+        .unwrap();
+    assert!(!result.success);
+
+    session.history_buffer.clear();
+    execute_persistent_test(&session, "echo \"$FOO\"")
+        .await
+        .unwrap();
+    let output: Vec<_> = session
+        .history_buffer
+        .get_all()
+        .into_iter()
+        .filter_map(|line| match line {
+            OutputLine::Stdout { text, .. } => Some(text),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(output, ["1"]);
+}
+
 // T019: Integration test for shell crash detection
 #[cfg(not(windows))]
 #[tokio::test]
 async fn test_shell_crash_detection() {
-    let manager = create_test_manager();
+    let session = create_test_session();
 
     // Command that kills itself (simulates crash)
     let _result = timeout(
         Duration::from_secs(5),
-        execute_command_test(&manager, "kill -9 $$", None),
+        execute_command_test(&session, "kill -9 $$", None),
     )
     .await
     .expect("Command timed out");
 
-    // After command completes, manager should not be busy
+    // After command completes, session should not be busy
     assert!(
-        !manager.is_busy().await,
-        "Manager should not be busy after crash"
+        !session.is_busy().await,
+        "Session should not be busy after crash"
     );
 }
 
 #[tokio::test]
 async fn test_command_not_found() {
-    let manager = create_test_manager();
+    let session = create_test_session();
 
     let result = timeout(
         Duration::from_secs(5),
         execute_command_test(
-            &manager,
+            &session,
             "this_command_definitely_does_not_exist_12345",
             None,
         ),
@@ -221,8 +408,8 @@ async fn test_command_not_found() {
 // T072: Test output truncation at capacity limit
 #[tokio::test]
 async fn test_output_truncation() {
-    // Create manager with small capacity to test truncation
-    let manager = ShellManager::with_capacity(50);
+    // Create session with small capacity to test truncation
+    let session = ShellManager::with_capacity(50).create_session(None, None);
 
     // Generate output that exceeds capacity
     // seq 1 100 will generate 100 lines of output
@@ -233,14 +420,14 @@ async fn test_output_truncation() {
 
     let result = timeout(
         Duration::from_secs(5),
-        execute_command_test(&manager, cmd, None),
+        execute_command_test(&session, cmd, None),
     )
     .await
     .expect("Command timed out");
 
     assert!(result.is_ok(), "Command should succeed");
 
-    let history = manager.history_buffer.get_all();
+    let history = session.history_buffer.get_all();
 
     assert_eq!(
         history.len(),
@@ -251,7 +438,7 @@ async fn test_output_truncation() {
 
     // Truncation warning should have been shown (via has_truncation_warning flag)
     assert!(
-        manager.history_buffer.has_truncation_warning(),
+        session.history_buffer.has_truncation_warning(),
         "Truncation warning should be shown"
     );
 
@@ -273,11 +460,11 @@ async fn test_output_truncation() {
 // T075: Verify sub-100ms latency for simple commands
 #[tokio::test]
 async fn test_command_latency_under_100ms() {
-    let manager = create_test_manager();
+    let session = create_test_session();
 
     let start = std::time::Instant::now();
 
-    let result = execute_command_test(&manager, "echo test", None).await;
+    let result = execute_command_test(&session, "echo test", None).await;
     assert!(result.is_ok(), "Command should succeed");
 
     let elapsed = start.elapsed();
@@ -299,9 +486,39 @@ async fn test_command_latency_under_100ms() {
     println!("Simple echo command latency: {:?}", elapsed);
 }
 
+// A long-running command in one session does not block another session
+#[cfg(not(windows))]
+#[tokio::test]
+async fn test_sessions_run_concurrently() {
+    let manager = ShellManager::with_capacity(100);
+    let server = manager.create_session(None, None);
+    let prompt = manager.create_session(None, None);
+
+    let server_clone = server.clone();
+    let handle =
+        tokio::spawn(async move { execute_command_test(&server_clone, "sleep 1", None).await });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(server.is_busy().await, "First session should be busy");
+
+    let result = timeout(
+        Duration::from_millis(500),
+        execute_command_test(&prompt, "echo other", None),
+    )
+    .await
+    .expect("Second session should not wait for the first");
+    assert!(result.unwrap().success);
+
+    let is_other = |line: &OutputLine| line.text() == "other";
+    assert!(prompt.history_buffer.get_all().iter().any(is_other));
+    assert!(!server.history_buffer.get_all().iter().any(is_other));
+
+    handle.await.expect("Task should not panic").unwrap();
+}
+
 // Helper function to execute command (simulates what the Tauri command does)
 async fn execute_command_test(
-    manager: &ShellManager,
+    session: &Session,
     command: &str,
     cwd: Option<String>,
 ) -> Result<CommandResponse, String> {
@@ -310,12 +527,12 @@ async fn execute_command_test(
     use tokio::io::{AsyncBufReadExt, BufReader};
 
     // Try to set busy state atomically
-    if !manager.shell_state.try_set_busy().await {
+    if !session.shell_state.try_set_busy().await {
         return Err("Command already running".to_string());
     }
 
     // Add command to history
-    manager.history_buffer.push(OutputLine::Command {
+    session.history_buffer.push(OutputLine::Command {
         text: command.to_string(),
         timestamp: current_timestamp_ms(),
     });
@@ -323,12 +540,16 @@ async fn execute_command_test(
     // Determine working directory
     let working_dir = match cwd {
         Some(path) => path,
-        None => manager.get_cwd().await,
+        None => session.get_cwd().await,
     };
 
-    // Spawn the process
-    let child_result = build_shell_command_test(command)
+    // Spawn the process, leading its own process group
+    let mut shell_cmd = build_shell_command_test(command);
+    #[cfg(unix)]
+    shell_cmd.process_group(0);
+    let child_result = shell_cmd
         .current_dir(&working_dir)
+        .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn();
@@ -336,20 +557,27 @@ async fn execute_command_test(
     let mut child = match child_result {
         Ok(c) => c,
         Err(e) => {
-            manager.shell_state.set_busy(false).await;
+            session.shell_state.set_busy(false).await;
             return Err(format!("Failed to spawn process: {}", e));
         }
     };
 
-    // Store the child process
-    *manager.shell_state.pid.lock().await = child.id();
+    // Store the child process group and its stdin, and run it as the foreground job
+    session.shell_state.set_process_group(child.id()).await;
+    let job = session.shell_state.jobs.add(command, true).await;
+    job.set_pid(child.id());
+    let stdin = child.stdin.take().expect("stdin not captured");
+    session
+        .shell_state
+        .set_stdin(std::sync::Arc::new(tokio::sync::Mutex::new(stdin)))
+        .await;
 
     // Take stdout and stderr
     let stdout = child.stdout.take().expect("stdout not captured");
     let stderr = child.stderr.take().expect("stderr not captured");
 
     // Spawn tasks to read stdout and stderr
-    let manager_stdout = manager.clone();
+    let session_stdout = session.clone();
     let stdout_handle = tokio::spawn(async move {
         let reader = BufReader::new(stdout);
         let mut lines = reader.lines();
@@ -357,7 +585,7 @@ async fn execute_command_test(
         loop {
             match lines.next_line().await {
                 Ok(Some(line)) => {
-                    manager_stdout.history_buffer.push(OutputLine::Stdout {
+                    session_stdout.history_buffer.push(OutputLine::Stdout {
                         text: line,
                         timestamp: current_timestamp_ms(),
                     });
@@ -371,7 +599,7 @@ async fn execute_command_test(
         }
     });
 
-    let manager_stderr = manager.clone();
+    let session_stderr = session.clone();
     let stderr_handle = tokio::spawn(async move {
         let reader = BufReader::new(stderr);
         let mut lines = reader.lines();
@@ -379,7 +607,7 @@ async fn execute_command_test(
         loop {
             match lines.next_line().await {
                 Ok(Some(line)) => {
-                    manager_stderr.history_buffer.push(OutputLine::Stderr {
+                    session_stderr.history_buffer.push(OutputLine::Stderr {
                         text: line,
                         timestamp: current_timestamp_ms(),
                     });
@@ -404,8 +632,9 @@ async fn execute_command_test(
     stderr_handle.await.expect("stderr reader task panicked");
 
     // Clear busy state
-    manager.shell_state.set_busy(false).await;
-    *manager.shell_state.pid.lock().await = None;
+    session.shell_state.jobs.remove(job.id).await;
+    session.shell_state.set_busy(false).await;
+    session.shell_state.clear_process().await;
 
     let exit_code = status.code();
 
@@ -418,41 +647,76 @@ async fn execute_command_test(
     }
 }
 
-// Helper function to cancel running command
-async fn cancel_command_test(manager: &ShellManager) -> Result<(), String> {
-    let pid = manager.shell_state.get_pid().await;
+// Helper function to run a command in the session's persistent shell (simulates what
+// the Tauri command does in persistent mode)
+#[cfg(unix)]
+async fn execute_persistent_test(
+    session: &Session,
+    command: &str,
+) -> Result<CommandResponse, String> {
+    use cepheus_lib::process::persistent::{PersistentShell, RunOutcome, ShellOutput};
+    use cepheus_lib::state::current_timestamp_ms;
 
-    match pid {
-        Some(pid) => {
-            #[cfg(unix)]
-            {
-                use nix::sys::signal::{self, Signal};
-                use nix::unistd::Pid;
+    if !session.shell_state.try_set_busy().await {
+        return Err("Command already running".to_string());
+    }
 
-                signal::kill(Pid::from_raw(pid as i32), Signal::SIGINT)
-                    .map_err(|e| format!("Failed to send SIGINT: {}", e))
+    let working_dir = session.get_cwd().await;
+    let env = session.shell_state.get_env().await;
+    let mut slot = session.shell_state.persistent_shell.lock().await;
+    if slot.is_none() {
+        let shell = PersistentShell::spawn(&session.shell, &working_dir, &env)
+            .await
+            .map_err(|e| format!("Failed to start persistent shell: {}", e))?;
+        *slot = Some(shell);
+    }
+    let shell = slot.as_mut().expect("persistent shell was just started");
+    session.shell_state.set_persistent_shell(shell.id()).await;
+
+    let mut stdout = Vec::new();
+    let outcome = shell
+        .run(command, &working_dir, Duration::from_millis(50), |output| {
+            if let ShellOutput::Stdout(chunk) = output {
+                stdout.extend(chunk);
             }
+        })
+        .await;
+    for line in String::from_utf8_lossy(&stdout).lines() {
+        session.history_buffer.push(OutputLine::Stdout {
+            text: line.to_string(),
+            timestamp: current_timestamp_ms(),
+        });
+    }
 
-            #[cfg(windows)]
-            {
-                let output = tokio::process::Command::new("taskkill")
-                    .arg("/PID")
-                    .arg(pid.to_string())
-                    .arg("/T")
-                    .output()
-                    .await
-                    .map_err(|e| format!("Failed to spawn taskkill: {e}"))?;
-
-                if output.status.success() {
-                    Ok(())
-                } else {
-                    Err(format!(
-                        "taskkill failed: {}",
-                        String::from_utf8_lossy(&output.stderr)
-                    ))
-                }
-            }
+    session.shell_state.set_busy(false).await;
+    session.shell_state.clear_process().await;
+
+    match outcome {
+        Ok(RunOutcome::Completed { exit_code, .. }) => {
+            Ok(CommandResponse::with_exit_code(exit_code))
+        }
+        Ok(RunOutcome::ShellExited { .. }) => {
+            *slot = None;
+            Err("Persistent shell exited".to_string())
         }
-        None => Err("No command currently running".to_string()),
+        Err(e) => Err(format!("Persistent shell failed: {}", e)),
     }
 }
+
+// Helper function to cancel running command, escalating in the background like the
+// Tauri command does
+async fn cancel_command_test(session: &Session, policy: EscalationPolicy) -> Result<(), String> {
+    let command_id = session.shell_state.current_command_id();
+    session
+        .shell_state
+        .signal_command(JobSignal::Interrupt)
+        .await?;
+
+    let shell_state = session.shell_state.clone();
+    tokio::spawn(async move {
+        shell_state
+            .escalate_cancel(command_id, policy, |_| {})
+            .await;
+    });
+    Ok(())
+}
@@ -1,20 +1,20 @@
 use leptos::prelude::*;
 
-use crate::components::Terminal;
-use crate::models::TerminalState;
+use crate::components::TerminalTabs;
+use crate::models::TabsState;
 
-/// Root application component that provides global context and mounts the Terminal.
+/// Root application component that provides global context and mounts the terminal tabs.
 #[component]
 pub fn App() -> impl IntoView {
-    // Initialize terminal state
-    let state = TerminalState::new();
+    // Initialize tab state; each tab provides its own TerminalState
+    let tabs = TabsState::new();
 
     // Provide context to all child components
-    provide_context(state);
+    provide_context(tabs);
 
     view! {
         <main class="app">
-            <Terminal />
+            <TerminalTabs />
         </main>
     }
 }
@@ -2,9 +2,12 @@ use leptos::prelude::*;
 use leptos::task::spawn_local;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
-use web_sys::HtmlInputElement;
 
-use crate::models::{OutputLine, TerminalState};
+use crate::components::{OutputScroll, ScrollRequest};
+use crate::models::{
+    byte_offset, replace_range, utf16_offset, CandidateKind, CommandHistoryEntry, Completion,
+    CompletionMenu, ExecutionMode, NotificationLevel, OutputLine, SessionArgs, TerminalState,
+};
 
 #[wasm_bindgen]
 extern "C" {
@@ -14,18 +17,71 @@ extern "C" {
 
 /// Request structure for `execute_command` IPC
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct ExecuteCommandArgs {
+    session_id: String,
     command: String,
     cwd: Option<String>,
+    mode: ExecutionMode,
 }
 
+/// Request structure for `write_stdin` IPC
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteStdinArgs {
+    session_id: String,
+    data: String,
+}
+
+/// Request structure for `get_command_history` IPC
+#[derive(Serialize, Deserialize)]
+struct CommandHistoryArgs {
+    limit: Option<usize>,
+}
+
+/// Request structure for `complete` IPC
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompleteArgs {
+    session_id: String,
+    input: String,
+    /// Byte offset of the cursor in `input`
+    cursor: usize,
+}
+
+/// Number of previous commands loaded for Up/Down recall
+const RECALL_LIMIT: usize = 1_000;
+
 /// Response structure from `execute_command` IPC
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
 #[allow(dead_code)]
 struct CommandResponse {
     success: bool,
     exit_code: Option<i32>,
     error: Option<String>,
+    timed_out: bool,
+    command_id: u64,
+    started_at: u64,
+    finished_at: u64,
+    duration_ms: u64,
+    signal: Option<i32>,
+    signal_name: Option<String>,
+    core_dumped: bool,
+    stdout_lines: u64,
+    stdout_bytes: u64,
+    stderr_lines: u64,
+    stderr_bytes: u64,
+}
+
+/// Format a command's run time for display (`450ms`, `3.2s`, `2m 5s`)
+#[allow(clippy::cast_precision_loss)]
+fn format_duration(ms: u64) -> String {
+    match ms {
+        0..=999 => format!("{ms}ms"),
+        1_000..=59_999 => format!("{:.1}s", ms as f64 / 1_000.0),
+        _ => format!("{}m {}s", ms / 60_000, (ms % 60_000) / 1_000),
+    }
 }
 
 /// Get current timestamp in milliseconds
@@ -34,52 +90,124 @@ fn current_timestamp_ms() -> u64 {
     js_sys::Date::now() as u64
 }
 
-/// Text input for entering shell commands.
+/// Text input for entering shell commands, or input for the running command while busy.
+///
+/// Tab completes the word before the cursor; when several candidates match they are
+/// listed above the input, and further presses of Tab (Shift+Tab backwards) cycle
+/// through them.
 #[component]
 pub fn CommandInput() -> impl IntoView {
     let state = use_context::<TerminalState>().expect("TerminalState context missing");
+    let scroll = use_context::<OutputScroll>().expect("OutputScroll context missing");
 
     // Create a node reference for the input element
     let input_ref = NodeRef::<leptos::html::Input>::new();
-
-    // Auto-focus the input on mount
-    let focus_setup = std::cell::Cell::new(false);
-    Effect::new(move |_| {
-        if focus_setup.get() {
-            return;
-        }
-
-        if state.is_input_disabled() {
-            return;
-        }
-
-        if let Some(input) = input_ref.get() {
-            let html_input: &HtmlInputElement = &input;
-            let _ = html_input.focus();
-            focus_setup.set(true);
-        }
-    });
+    let menu = RwSignal::new(None::<CompletionMenu>);
 
     // Handle input changes
     let on_input = move |ev: leptos::ev::Event| {
         let value = event_target_value(&ev);
         state.current_input.set(value);
+        menu.set(None);
     };
 
     // Handle key down events
     let on_keydown = move |ev: leptos::ev::KeyboardEvent| {
         let key = ev.key();
+        if key != "Tab" && key != "Shift" {
+            menu.set(None);
+        }
 
-        if key == "Enter" {
+        if key == "Tab" {
+            ev.prevent_default();
+            if !state.is_busy.get() {
+                complete(state, input_ref, menu, !ev.shift_key());
+            }
+        } else if key == "Enter" {
+            ev.prevent_default();
+            if state.is_busy.get() {
+                send_input(state);
+            } else {
+                submit_command(state);
+            }
+        } else if (key == "ArrowUp" || key == "ArrowDown") && !state.is_busy.get() {
             ev.prevent_default();
-            submit_command(state);
+            if key == "ArrowUp" {
+                state.recall_older();
+            } else {
+                state.recall_newer();
+            }
+        } else if ev.shift_key() && (key == "PageUp" || key == "PageDown") {
+            ev.prevent_default();
+            scroll.request(if key == "PageUp" {
+                ScrollRequest::PageUp
+            } else {
+                ScrollRequest::PageDown
+            });
+        } else if ev.ctrl_key() && (key == "Home" || key == "End") {
+            ev.prevent_default();
+            scroll.request(if key == "Home" {
+                ScrollRequest::Line(0)
+            } else {
+                ScrollRequest::Bottom
+            });
+        } else if ev.ctrl_key() && key == "r" && !state.is_busy.get() {
+            ev.prevent_default();
+            state.history_search_open.set(true);
         } else if ev.ctrl_key() && key == "c" {
             ev.prevent_default();
-            cancel_command(state);
+            cancel_command(state, false);
+        } else if ev.ctrl_key() && key == "\\" {
+            ev.prevent_default();
+            cancel_command(state, true);
+        } else if ev.ctrl_key() && key == "z" {
+            ev.prevent_default();
+            suspend_command(state);
+        }
+    };
+
+    let on_pick = move |index: usize| {
+        let mut picked = None;
+        menu.update(|menu| {
+            if let Some(menu) = menu {
+                picked = menu.select(&state.current_input.get_untracked(), index);
+            }
+        });
+        if let Some((text, cursor)) = picked {
+            set_input(state, input_ref, text, cursor);
         }
+        menu.set(None);
     };
 
     view! {
+        <Show when=move || menu.with(Option::is_some)>
+            <div class="completion-menu">
+                {move || {
+                    let Some(current) = menu.get() else {
+                        return ().into_any();
+                    };
+                    current
+                        .candidates
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, candidate)| {
+                            view! {
+                                <span
+                                    class=candidate.kind.class()
+                                    class:selected=current.selected == Some(index)
+                                    // Keep focus in the input
+                                    on:mousedown=|ev| ev.prevent_default()
+                                    on:click=move |_| on_pick(index)
+                                >
+                                    {candidate.display}
+                                </span>
+                            }
+                        })
+                        .collect_view()
+                        .into_any()
+                }}
+            </div>
+        </Show>
         <input
             type="text"
             class="command-input"
@@ -87,10 +215,13 @@ pub fn CommandInput() -> impl IntoView {
             prop:value=move || state.current_input.get()
             on:input=on_input
             on:keydown=on_keydown
+            on:blur=move |_| menu.set(None)
             prop:disabled=move || state.is_input_disabled()
             placeholder=move || {
                 if state.listener_failed.get() {
                     "Terminal unavailable - connection failed"
+                } else if state.is_busy.get() {
+                    "Send input to running command..."
                 } else {
                     "Enter command..."
                 }
@@ -99,6 +230,127 @@ pub fn CommandInput() -> impl IntoView {
     }
 }
 
+/// Replace the input, placing the cursor at byte offset `cursor`
+fn set_input(
+    state: TerminalState,
+    input_ref: NodeRef<leptos::html::Input>,
+    text: String,
+    cursor: usize,
+) {
+    // Update the element first: re-setting the same value then leaves the cursor alone
+    if let Some(input) = input_ref.get_untracked() {
+        input.set_value(&text);
+        let position = u32::try_from(utf16_offset(&text, cursor)).unwrap_or(u32::MAX);
+        let _ = input.set_selection_range(position, position);
+    }
+    state.current_input.set(text);
+}
+
+/// Complete the word before the cursor (Tab), or cycle through the listed candidates
+fn complete(
+    state: TerminalState,
+    input_ref: NodeRef<leptos::html::Input>,
+    menu: RwSignal<Option<CompletionMenu>>,
+    forward: bool,
+) {
+    let input = state.current_input.get_untracked();
+
+    let mut cycled = None;
+    menu.update(|menu| {
+        if let Some(menu) = menu {
+            cycled = menu.cycle(&input, forward);
+        }
+    });
+    if let Some((text, cursor)) = cycled {
+        set_input(state, input_ref, text, cursor);
+        return;
+    }
+
+    let Some(session_id) = state.session_id.get_untracked() else {
+        return;
+    };
+    let cursor = input_ref
+        .get_untracked()
+        .and_then(|element| element.selection_start().ok().flatten())
+        .map_or(input.len(), |position| {
+            byte_offset(&input, position as usize)
+        });
+
+    spawn_local(async move {
+        let args = match serde_wasm_bindgen::to_value(&CompleteArgs {
+            session_id,
+            input: input.clone(),
+            cursor,
+        }) {
+            Ok(args) => args,
+            Err(e) => {
+                web_sys::console::error_1(
+                    &format!("Failed to serialize complete args: {e}").into(),
+                );
+                return;
+            }
+        };
+
+        let completion = match invoke("complete", args).await {
+            Ok(result) => match serde_wasm_bindgen::from_value::<Completion>(result) {
+                Ok(completion) => completion,
+                Err(e) => {
+                    web_sys::console::error_1(&format!("Failed to parse completion: {e}").into());
+                    return;
+                }
+            },
+            Err(e) => {
+                let error_msg = e.as_string().unwrap_or_else(|| "Unknown error".to_string());
+                web_sys::console::warn_1(&format!("complete IPC failed: {error_msg}").into());
+                return;
+            }
+        };
+
+        // The input changed while completing
+        if state.current_input.get_untracked() != input {
+            return;
+        }
+
+        match completion.candidates.as_slice() {
+            [] => {}
+            [only] => {
+                // A completed word is followed by a space; a directory can be continued
+                let suffix = if only.kind == CandidateKind::Directory {
+                    ""
+                } else {
+                    " "
+                };
+                let replacement = format!("{}{suffix}", only.replacement);
+                if let Some((text, cursor)) =
+                    replace_range(&input, completion.start, completion.end, &replacement)
+                {
+                    set_input(state, input_ref, text, cursor);
+                }
+            }
+            _ => {
+                let mut end = completion.end;
+                if completion.common_prefix.len() > end - completion.start {
+                    if let Some((text, cursor)) = replace_range(
+                        &input,
+                        completion.start,
+                        completion.end,
+                        &completion.common_prefix,
+                    ) {
+                        end = cursor;
+                        set_input(state, input_ref, text, cursor);
+                    }
+                }
+                menu.set(Some(CompletionMenu {
+                    start: completion.start,
+                    end,
+                    candidates: completion.candidates,
+                    selected: None,
+                }));
+            }
+        }
+    });
+}
+
 /// Submit the current command for execution
 fn submit_command(state: TerminalState) {
     // Don't submit if listener failed (terminal non-functional)
@@ -107,6 +359,10 @@ fn submit_command(state: TerminalState) {
         return;
     }
 
+    let Some(session_id) = state.session_id.get() else {
+        return;
+    };
+
     let cmd = state.current_input.get();
 
     // Don't submit empty commands
@@ -116,15 +372,16 @@ fn submit_command(state: TerminalState) {
 
     // Clear input immediately
     state.clear_input();
+    state.remember_command(cmd.clone());
 
-    // Set busy state
-    state.is_busy.set(true);
-
-    // Spawn async task to call IPC
+    // Spawn async task to call IPC; the busy state follows the backend's command-started
+    // and command-finished events
     spawn_local(async move {
         let args = match serde_wasm_bindgen::to_value(&ExecuteCommandArgs {
+            session_id,
             command: cmd.clone(),
             cwd: None,
+            mode: state.execution_mode.get(),
         }) {
             Ok(args) => args,
             Err(e) => {
@@ -134,21 +391,39 @@ fn submit_command(state: TerminalState) {
                     timestamp: current_timestamp_ms(),
                 };
                 state.push_history(err_line);
-                state.is_busy.set(false);
                 return;
             }
         };
 
-        match invoke("execute_command", args).await {
+        let result = invoke("execute_command", args).await;
+        // Pick up commands entered in other tabs meanwhile
+        load_command_history(state).await;
+        match result {
             Ok(result) => {
                 // Deserialize the structured response
                 match serde_wasm_bindgen::from_value::<CommandResponse>(result) {
                     Ok(response) => {
                         // Use structured fields to detect failure
-                        if let Some(error_msg) = response.error {
-                            let err_line = OutputLine::Stderr {
-                                text: error_msg,
-                                timestamp: current_timestamp_ms(),
+                        if let Some(mut error_msg) = response.error {
+                            // "Killed by SIGSEGV after 3.2s" says more than the signal alone
+                            if response.signal_name.is_some() && !response.timed_out {
+                                error_msg = format!(
+                                    "{error_msg} after {}",
+                                    format_duration(response.duration_ms)
+                                );
+                            }
+                            let timestamp = current_timestamp_ms();
+                            let err_line = if response.timed_out {
+                                OutputLine::Notification {
+                                    message: error_msg,
+                                    level: NotificationLevel::Warning,
+                                    timestamp,
+                                }
+                            } else {
+                                OutputLine::Stderr {
+                                    text: error_msg,
+                                    timestamp,
+                                }
                             };
                             state.push_history(err_line);
                         }
@@ -183,14 +458,105 @@ fn submit_command(state: TerminalState) {
                 state.show_notification("Failed to execute command".to_string());
             }
         }
+    });
+}
 
-        // Clear busy state
-        state.is_busy.set(false);
+/// Load the commands entered in any session for Up/Down recall
+#[allow(clippy::future_not_send)]
+pub async fn load_command_history(state: TerminalState) {
+    let args = match serde_wasm_bindgen::to_value(&CommandHistoryArgs {
+        limit: Some(RECALL_LIMIT),
+    }) {
+        Ok(args) => args,
+        Err(e) => {
+            web_sys::console::error_1(&format!("Failed to serialize history args: {e}").into());
+            return;
+        }
+    };
+
+    match invoke("get_command_history", args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<CommandHistoryEntry>>(result) {
+            Ok(entries) => state.set_command_history(entries),
+            Err(e) => {
+                web_sys::console::error_1(&format!("Failed to parse command history: {e}").into());
+            }
+        },
+        Err(e) => {
+            web_sys::console::warn_1(&format!("Failed to fetch command history: {e:?}").into());
+        }
+    }
+}
+
+/// Send the current input line to the running command's stdin
+fn send_input(state: TerminalState) {
+    let Some(session_id) = state.session_id.get() else {
+        return;
+    };
+
+    // A terminal expects Enter as carriage return; pipes get a plain newline
+    let line_ending = if state.execution_mode.get() == ExecutionMode::Pty {
+        "\r"
+    } else {
+        "\n"
+    };
+    let data = format!("{}{line_ending}", state.current_input.get());
+    state.clear_input();
+    write_stdin(state, session_id, data);
+}
+
+/// Send raw input to the running command of `session_id`, reporting failures in `state`
+pub fn write_stdin(state: TerminalState, session_id: String, data: String) {
+    spawn_local(async move {
+        let args = match serde_wasm_bindgen::to_value(&WriteStdinArgs { session_id, data }) {
+            Ok(args) => args,
+            Err(e) => {
+                web_sys::console::error_1(&format!("Failed to serialize input args: {e}").into());
+                return;
+            }
+        };
+
+        if let Err(e) = invoke("write_stdin", args).await {
+            let error_msg = e
+                .as_string()
+                .unwrap_or_else(|| "Unknown IPC error".to_string());
+            web_sys::console::error_1(&format!("write_stdin IPC failed: {error_msg}").into());
+            state.show_notification(format!("Failed to send input: {error_msg}"));
+        }
+    });
+}
+
+/// Suspend the currently running command (Ctrl+Z), leaving it in the session's job
+/// table for `fg` and `bg`
+fn suspend_command(state: TerminalState) {
+    if state.listener_failed.get() || !state.is_busy.get() {
+        return;
+    }
+
+    let Some(session_id) = state.session_id.get() else {
+        return;
+    };
+
+    spawn_local(async move {
+        let args = match serde_wasm_bindgen::to_value(&SessionArgs { session_id }) {
+            Ok(args) => args,
+            Err(e) => {
+                web_sys::console::error_1(&format!("Failed to serialize suspend args: {e}").into());
+                return;
+            }
+        };
+
+        // The running command returns once suspended, which clears the busy state
+        if let Err(e) = invoke("suspend_command", args).await {
+            let error_msg = e.as_string().unwrap_or_else(|| "Unknown error".to_string());
+            web_sys::console::error_1(&format!("suspend_command IPC failed: {error_msg}").into());
+            state.show_notification(format!("Suspend failed: {error_msg}"));
+        }
     });
 }
 
-/// Cancel the currently running command
-fn cancel_command(state: TerminalState) {
+/// Cancel the currently running command; `force` kills it immediately instead of
+/// interrupting it
+fn cancel_command(state: TerminalState, force: bool) {
     // Can't cancel if terminal is non-functional
     if state.listener_failed.get() {
         return;
@@ -201,10 +567,25 @@ fn cancel_command(state: TerminalState) {
         return;
     }
 
+    let Some(session_id) = state.session_id.get() else {
+        return;
+    };
+
     spawn_local(async move {
-        let args = JsValue::NULL;
+        let args = match serde_wasm_bindgen::to_value(&SessionArgs { session_id }) {
+            Ok(args) => args,
+            Err(e) => {
+                web_sys::console::error_1(&format!("Failed to serialize cancel args: {e}").into());
+                return;
+            }
+        };
 
-        match invoke("cancel_command", args).await {
+        let command = if force {
+            "force_kill_command"
+        } else {
+            "cancel_command"
+        };
+        match invoke(command, args).await {
             Ok(result) => {
                 if let Some(error) = result.as_string() {
                     if !error.is_empty() {
@@ -215,9 +596,7 @@ fn cancel_command(state: TerminalState) {
             }
             Err(e) => {
                 let error_msg = e.as_string().unwrap_or_else(|| "Unknown error".to_string());
-                web_sys::console::error_1(
-                    &format!("cancel_command IPC failed: {error_msg}").into(),
-                );
+                web_sys::console::error_1(&format!("{command} IPC failed: {error_msg}").into());
                 state.show_notification(format!("Cancel failed: {error_msg}"));
                 let normalized = error_msg.trim().to_lowercase();
                 if normalized.contains("no command currently running") {
@@ -0,0 +1,194 @@
+use leptos::ev::KeyboardEvent;
+use leptos::html::Div;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::models::{CommandHistoryEntry, ExitStatusFilter, TerminalState};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], catch)]
+    async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+}
+
+/// Arguments for the `search_command_history` IPC
+#[derive(Serialize, Deserialize)]
+struct SearchHistoryArgs {
+    query: String,
+    /// Only commands last run in this directory
+    cwd: Option<String>,
+    status: Option<ExitStatusFilter>,
+    limit: Option<usize>,
+}
+
+/// Number of matches shown
+const SEARCH_LIMIT: usize = 50;
+
+/// Reverse incremental search of the command history (Ctrl+R), shown above the input.
+///
+/// Matches update as the query is typed, most recent first. Ctrl+R or Down selects an
+/// older match, Up a newer one; Alt+D limits matches to the current directory and Alt+S
+/// cycles the exit status filter. Enter puts the selected command in the input, Escape
+/// closes the search leaving the input unchanged.
+#[component]
+pub fn HistorySearch() -> impl IntoView {
+    let state = use_context::<TerminalState>().expect("TerminalState context missing");
+    let list_ref = NodeRef::<Div>::new();
+
+    // Start from what was typed so far, as zsh does
+    let query = RwSignal::new(state.current_input.get_untracked());
+    let this_dir = RwSignal::new(false);
+    let status = RwSignal::new(None::<ExitStatusFilter>);
+    let matches = RwSignal::new(Vec::<CommandHistoryEntry>::new());
+    let selected = RwSignal::new(0_usize);
+    // Only the response to the latest search is shown
+    let generation = StoredValue::new(0_u64);
+
+    Effect::new(move |_| {
+        let args = SearchHistoryArgs {
+            query: query.get(),
+            cwd: this_dir.get().then(|| state.cwd.get_untracked()),
+            status: status.get(),
+            limit: Some(SEARCH_LIMIT),
+        };
+        generation.update_value(|generation| *generation += 1);
+        let current = generation.get_value();
+        spawn_local(async move {
+            let result = search_history(args).await;
+            if generation.try_get_value() != Some(current) {
+                return;
+            }
+            match result {
+                Ok(entries) => {
+                    matches.set(entries);
+                    selected.set(0);
+                }
+                Err(e) => {
+                    web_sys::console::warn_1(&format!("History search failed: {e}").into());
+                }
+            }
+        });
+    });
+
+    // Keep the selected match visible
+    Effect::new(move |_| {
+        selected.track();
+        if let Some(row) = list_ref.get().and_then(|list| {
+            list.query_selector(".history-match.selected")
+                .ok()
+                .flatten()
+        }) {
+            row.scroll_into_view_with_bool(false);
+        }
+    });
+
+    let accept = move |index: usize| {
+        if let Some(entry) = matches.with_untracked(|matches| matches.get(index).cloned()) {
+            state.current_input.set(entry.command);
+        }
+        state.history_search_open.set(false);
+    };
+
+    let on_keydown = move |ev: KeyboardEvent| {
+        let key = ev.key();
+        let count = matches.with_untracked(Vec::len);
+        if key == "Enter" {
+            ev.prevent_default();
+            accept(selected.get_untracked());
+        } else if key == "Escape" || (ev.ctrl_key() && key == "g") {
+            ev.prevent_default();
+            state.history_search_open.set(false);
+        } else if key == "ArrowDown" || (ev.ctrl_key() && key == "r") {
+            ev.prevent_default();
+            selected.update(|index| *index = (*index + 1).min(count.saturating_sub(1)));
+        } else if key == "ArrowUp" || (ev.ctrl_key() && key == "s") {
+            ev.prevent_default();
+            selected.update(|index| *index = index.saturating_sub(1));
+        } else if ev.alt_key() && ev.code() == "KeyD" {
+            ev.prevent_default();
+            this_dir.update(|this_dir| *this_dir = !*this_dir);
+        } else if ev.alt_key() && ev.code() == "KeyS" {
+            ev.prevent_default();
+            status.update(|status| *status = ExitStatusFilter::cycle(*status));
+        }
+    };
+
+    view! {
+        <div class="history-search">
+            <div class="history-search-list" node_ref=list_ref>
+                {move || {
+                    matches
+                        .get()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, entry)| {
+                            let (mark, mark_class) = match entry.exit_code {
+                                Some(0) => ("✓".to_string(), "history-status ok"),
+                                Some(code) => (format!("✗ {code}"), "history-status failed"),
+                                None => ("✗".to_string(), "history-status failed"),
+                            };
+                            view! {
+                                <div
+                                    class="history-match"
+                                    class:selected=move || selected.get() == index
+                                    on:mousedown=|ev| ev.prevent_default()
+                                    on:click=move |_| accept(index)
+                                >
+                                    <span class=mark_class>{mark}</span>
+                                    <span class="history-command">{entry.command}</span>
+                                    <span class="history-cwd">{entry.cwd}</span>
+                                </div>
+                            }
+                        })
+                        .collect_view()
+                }}
+                <Show when=move || matches.with(Vec::is_empty)>
+                    <div class="history-empty">"No matching commands"</div>
+                </Show>
+            </div>
+            <div class="history-search-row">
+                <span class="history-search-label">"(reverse-i-search)"</span>
+                <input
+                    type="text"
+                    class="history-search-input"
+                    prop:value=move || query.get()
+                    on:input=move |ev| query.set(event_target_value(&ev))
+                    on:keydown=on_keydown
+                />
+                <button
+                    class="history-filter"
+                    class:active=move || this_dir.get()
+                    title="Only commands run in this directory (Alt+D)"
+                    // Keep focus in the search input
+                    on:mousedown=|ev| ev.prevent_default()
+                    on:click=move |_| this_dir.update(|this_dir| *this_dir = !*this_dir)
+                >
+                    "this dir"
+                </button>
+                <button
+                    class="history-filter"
+                    class:active=move || status.get().is_some()
+                    title="Filter by exit status (Alt+S)"
+                    on:mousedown=|ev| ev.prevent_default()
+                    on:click=move |_| status.update(|status| *status = ExitStatusFilter::cycle(*status))
+                >
+                    {move || ExitStatusFilter::label(status.get())}
+                </button>
+            </div>
+        </div>
+    }
+}
+
+/// Search the backend command history
+#[allow(clippy::future_not_send)]
+async fn search_history(args: SearchHistoryArgs) -> Result<Vec<CommandHistoryEntry>, String> {
+    let args = serde_wasm_bindgen::to_value(&args)
+        .map_err(|e| format!("Failed to serialize search args: {e}"))?;
+    let result = invoke("search_command_history", args).await.map_err(|e| {
+        e.as_string()
+            .unwrap_or_else(|| "Unknown IPC error".to_string())
+    })?;
+    serde_wasm_bindgen::from_value(result).map_err(|e| format!("Failed to parse matches: {e}"))
+}
@@ -1,11 +1,23 @@
 pub mod command_input;
+pub mod history_search;
+pub mod mode_selector;
 pub mod notification_bar;
 pub mod output_display;
+pub mod pane_layout;
 pub mod prompt_indicator;
+pub mod screen_grid;
+pub mod tab_bar;
 pub mod terminal;
+pub mod terminal_tabs;
 
 pub use command_input::CommandInput;
+pub use history_search::HistorySearch;
+pub use mode_selector::ModeSelector;
 pub use notification_bar::NotificationBar;
-pub use output_display::OutputDisplay;
+pub use output_display::{OutputDisplay, OutputScroll, ScrollRequest};
+pub use pane_layout::PaneLayout;
 pub use prompt_indicator::PromptIndicator;
+pub use screen_grid::ScreenGrid;
+pub use tab_bar::TabBar;
 pub use terminal::Terminal;
+pub use terminal_tabs::TerminalTabs;
@@ -0,0 +1,38 @@
+use leptos::prelude::*;
+
+use crate::models::{ExecutionMode, TerminalState};
+
+/// Selects how the next command is executed (PTY, persistent shell, or plain pipes).
+#[component]
+pub fn ModeSelector() -> impl IntoView {
+    let state = use_context::<TerminalState>().expect("TerminalState context missing");
+
+    let on_change = move |ev: leptos::ev::Event| {
+        if let Some(mode) = ExecutionMode::from_label(&event_target_value(&ev)) {
+            state.execution_mode.set(mode);
+        }
+    };
+
+    view! {
+        <select
+            class="mode-selector"
+            title="Execution mode"
+            disabled=move || state.is_busy.get()
+            on:change=on_change
+        >
+            {ExecutionMode::ALL
+                .into_iter()
+                .map(|mode| {
+                    view! {
+                        <option
+                            value=mode.label()
+                            selected=move || state.execution_mode.get() == mode
+                        >
+                            {mode.label()}
+                        </option>
+                    }
+                })
+                .collect_view()}
+        </select>
+    }
+}
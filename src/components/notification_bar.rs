@@ -9,7 +9,8 @@ use crate::models::TerminalState;
 
 type CallbackSlot = Arc<Mutex<Option<SendWrapper<wasm_bindgen::prelude::Closure<dyn FnMut()>>>>>;
 
-/// Displays transient system notifications (non-modal).
+/// Displays transient system notifications (non-modal), dismissed after
+/// `notifications.timeout_ms` (never if 0).
 #[component]
 pub fn NotificationBar() -> impl IntoView {
     let state = use_context::<TerminalState>().expect("TerminalState context missing");
@@ -49,8 +50,11 @@ pub fn NotificationBar() -> impl IntoView {
                 callback_guard.take();
             }
 
-            if state.notification.get().is_some() {
-                // Set a timeout to clear the notification after 3 seconds
+            let timeout_ms = state
+                .config
+                .with_untracked(|config| config.notifications.timeout_ms);
+            if state.notification.get().is_some() && timeout_ms > 0 {
+                // Set a timeout to clear the notification
                 let state_clone = state;
 
                 let callback: wasm_bindgen::prelude::Closure<dyn FnMut()> =
@@ -61,7 +65,7 @@ pub fn NotificationBar() -> impl IntoView {
                 // Only store the callback if scheduling the timeout succeeds.
                 match window().set_timeout_with_callback_and_timeout_and_arguments_0(
                     callback.as_ref().unchecked_ref(),
-                    3000,
+                    i32::try_from(timeout_ms).unwrap_or(i32::MAX),
                 ) {
                     Ok(handle) => {
                         let mut callback_guard = match active_callback.lock() {
@@ -1,118 +1,281 @@
 use leptos::html::Div;
 use leptos::prelude::*;
-use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
+use web_sys::ResizeObserver;
 
-use crate::models::{OutputLine, TerminalState};
+use crate::models::{OutputLine, StyledSpan, TerminalState};
 
 type RafScrollRecord = (i32, Closure<dyn FnMut()>);
+type ResizeWatch = (ResizeObserver, Closure<dyn Fn()>);
 
-thread_local! {
-    static RAF_SCROLL_REQUEST: RefCell<Option<RafScrollRecord>> = RefCell::new(None);
+/// Height of an output row in pixels (`.output-row` in styles.css). Rows have a fixed
+/// height so the visible ones can be found from the scroll offset alone.
+const ROW_HEIGHT_PX: i32 = 21;
+
+/// Rows rendered above and below the visible ones, so fast scrolling shows no gaps
+const OVERSCAN_ROWS: usize = 30;
+
+/// Where the output display is asked to scroll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollRequest {
+    /// Show the line at this index of the history at the top
+    Line(usize),
+    PageUp,
+    PageDown,
+    /// Follow the newest output again
+    Bottom,
+}
+
+/// Scroll requests for a terminal's `OutputDisplay` (provided as context by `Terminal`).
+#[derive(Clone, Copy, Default)]
+pub struct OutputScroll(RwSignal<Option<ScrollRequest>>);
+
+impl OutputScroll {
+    /// Ask the output display to scroll
+    pub fn request(self, request: ScrollRequest) {
+        self.0.set(Some(request));
+    }
 }
 
 /// Scrollable display of terminal history.
+///
+/// Only the visible rows (plus `OVERSCAN_ROWS` on each side) are rendered; spacers stand
+/// in for the rest, so long scrollback costs no more to show than a screenful.
 #[component]
 pub fn OutputDisplay() -> impl IntoView {
     let state = use_context::<TerminalState>().expect("TerminalState context missing");
+    let scroll = use_context::<OutputScroll>().expect("OutputScroll context missing");
     let container_ref = NodeRef::<Div>::new();
     // Track if we should auto-scroll (sticky bottom)
-    let is_sticky = StoredValue::new(true);
+    let is_sticky = RwSignal::new(true);
+    let scroll_top = RwSignal::new(0);
+    let viewport_height = RwSignal::new(0);
+    // Pending scroll callback for this instance (one per tab)
+    let raf_scroll_request = StoredValue::new_local(None::<RafScrollRecord>);
+    let resize_watch = StoredValue::new_local(None::<ResizeWatch>);
+
+    // Rows to render, as a range of `history`
+    let window = Memo::new(move |_| {
+        let len = state.history.with(Vec::len);
+        let visible = usize::try_from(viewport_height.get() / ROW_HEIGHT_PX).unwrap_or(0) + 1;
+        // While following the output, the newest rows are the visible ones even before
+        // the scroll to the bottom has happened
+        let first = if is_sticky.get() {
+            len.saturating_sub(visible)
+        } else {
+            usize::try_from(scroll_top.get() / ROW_HEIGHT_PX).unwrap_or(0)
+        };
+        let start = first.saturating_sub(OVERSCAN_ROWS).min(len);
+        let end = (first + visible + OVERSCAN_ROWS).min(len);
+        (start, end, len)
+    });
 
     // Auto-scroll effect when history changes
     Effect::new(move |_| {
         let _ = state.history.get(); // Track changes
 
         // Only scroll if we are sticky
-        if is_sticky.get_value() {
+        if is_sticky.get_untracked() {
             // Schedule scrolling after the next paint to ensure DOM is updated
             if let Some(window) = web_sys::window() {
-                let window_clone = window.clone();
-                RAF_SCROLL_REQUEST.with(|cell| {
-                    if let Some((pending_id, old_closure)) = cell.borrow_mut().take() {
-                        let _ = window.cancel_animation_frame(pending_id);
-                        drop(old_closure);
-                    }
+                if let Some((pending_id, old_closure)) =
+                    raf_scroll_request.try_update_value(Option::take).flatten()
+                {
+                    let _ = window.cancel_animation_frame(pending_id);
+                    drop(old_closure);
+                }
 
-                    let scroll_closure: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || {
-                        if let Some(document) = window_clone.document() {
-                            if let Some(container) = document.get_element_by_id("output-container")
-                            {
-                                let scroll_height = container.scroll_height();
-                                container.set_scroll_top(scroll_height);
-                            }
-                        }
-
-                        RAF_SCROLL_REQUEST.with(|drop_cell| {
-                            drop_cell.borrow_mut().take();
-                        });
-                    }));
-
-                    // Schedule the scroll callback for after the next paint
-                    if let Ok(request_id) =
-                        window.request_animation_frame(scroll_closure.as_ref().unchecked_ref())
-                    {
-                        *cell.borrow_mut() = Some((request_id, scroll_closure));
+                let scroll_closure: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || {
+                    if let Some(container) = container_ref.get_untracked() {
+                        let scroll_height = container.scroll_height();
+                        container.set_scroll_top(scroll_height);
                     }
-                });
+                }));
+
+                // Schedule the scroll callback for after the next paint
+                if let Ok(request_id) =
+                    window.request_animation_frame(scroll_closure.as_ref().unchecked_ref())
+                {
+                    raf_scroll_request.set_value(Some((request_id, scroll_closure)));
+                }
             }
         }
     });
 
-    // Cancel any pending animation frame when component unmounts
-    on_cleanup(|| {
-        RAF_SCROLL_REQUEST.with(|cell| {
-            if let Some((pending_id, _old_closure)) = cell.borrow_mut().take() {
-                if let Some(window) = web_sys::window() {
-                    let _ = window.cancel_animation_frame(pending_id);
-                }
+    // Scroll as requested (keyboard shortcuts, jumps to a line)
+    Effect::new(move |_| {
+        let Some(request) = scroll.0.get() else {
+            return;
+        };
+        scroll.0.set(None);
+        let Some(container) = container_ref.get_untracked() else {
+            return;
+        };
+        // Keep a row of context when paging
+        let page = (container.client_height() - ROW_HEIGHT_PX).max(ROW_HEIGHT_PX);
+        let top = match request {
+            ScrollRequest::Line(index) => i32::try_from(index)
+                .unwrap_or(i32::MAX)
+                .saturating_mul(ROW_HEIGHT_PX),
+            ScrollRequest::PageUp => container.scroll_top() - page,
+            ScrollRequest::PageDown => container.scroll_top() + page,
+            ScrollRequest::Bottom => container.scroll_height(),
+        };
+        // The scroll event updates the window and stickiness
+        container.set_scroll_top(top.max(0));
+    });
+
+    // Track the visible height (window resizes, split drags, tab switches)
+    Effect::new(move |_| {
+        let Some(container) = container_ref.get() else {
+            return;
+        };
+        viewport_height.set(container.client_height());
+        let callback: Closure<dyn Fn()> = Closure::new(move || {
+            if let Some(container) = container_ref.get_untracked() {
+                viewport_height.set(container.client_height());
             }
         });
+        match ResizeObserver::new(callback.as_ref().unchecked_ref()) {
+            Ok(observer) => {
+                observer.observe(&container);
+                resize_watch.set_value(Some((observer, callback)));
+            }
+            Err(e) => {
+                web_sys::console::warn_1(&format!("Failed to observe resizes: {e:?}").into());
+            }
+        }
+    });
+
+    // Cancel any pending animation frame when component unmounts
+    on_cleanup(move || {
+        if let Some((pending_id, _old_closure)) =
+            raf_scroll_request.try_update_value(Option::take).flatten()
+        {
+            if let Some(window) = web_sys::window() {
+                let _ = window.cancel_animation_frame(pending_id);
+            }
+        }
+        if let Some((observer, _callback)) = resize_watch.try_update_value(Option::take).flatten() {
+            observer.disconnect();
+        }
     });
 
     let on_scroll = move |_| {
         if let Some(div) = container_ref.get() {
             // Check if we are at the bottom (with small tolerance)
             let at_bottom = (div.scroll_top() + div.client_height()) >= (div.scroll_height() - 10);
-            is_sticky.set_value(at_bottom);
+            if is_sticky.get_untracked() != at_bottom {
+                is_sticky.set(at_bottom);
+            }
+            scroll_top.set(div.scroll_top());
         }
     };
 
+    // Rows of the window, keyed so identical lines stay distinct and a rewritten
+    // partial line is rendered again
+    let rows = move || {
+        let (start, end, _) = window.get();
+        let first = state.first_position();
+        state.history.with(|history| {
+            history
+                .get(start..end)
+                .unwrap_or_default()
+                .iter()
+                .zip(start as u64..)
+                .map(|(line, index)| (line.key(first + index), line.line.clone()))
+                .collect::<Vec<_>>()
+        })
+    };
     view! {
         <div
             class="output-display"
-            id="output-container"
             node_ref=container_ref
             on:scroll=on_scroll
         >
+            <div style:height=move || spacer_height(window.get().0) />
             <For
-                each=move || state.history.get()
-                key=|line| line.unique_key()
-                children=move |line| view! { <OutputLineView line=line /> }
+                each=rows
+                key=|(key, _)| key.clone()
+                children=move |(_, line)| view! { <OutputLineView line=line /> }
             />
+            <div style:height=move || {
+                let (_, end, len) = window.get();
+                spacer_height(len - end)
+            } />
         </div>
     }
 }
 
+/// CSS height of a spacer standing in for `rows` rows that are not rendered
+fn spacer_height(rows: usize) -> String {
+    format!(
+        "{}px",
+        rows as u64 * u64::from(ROW_HEIGHT_PX.unsigned_abs())
+    )
+}
+
 /// Renders a single OutputLine with appropriate styling.
 #[component]
 fn OutputLineView(line: OutputLine) -> impl IntoView {
     let css_class = line.css_class();
-    let content = format_line_content(&line);
+    let content = match &line {
+        OutputLine::Styled { text, spans, .. } => styled_segments(text, spans).into_any(),
+        OutputLine::JobOutput {
+            job_id,
+            text,
+            spans,
+            ..
+        } => view! {
+            <span class="job-badge">{format!("[{job_id}]")}</span>
+            {styled_segments(text, spans)}
+        }
+        .into_any(),
+        _ => format_line_content(&line).into_any(),
+    };
 
     view! {
-        <div class=css_class>
+        <div class=format!("output-row {css_class}")>
             {content}
         </div>
     }
 }
 
+/// Split styled text into plain text and `<span>`s carrying each span's style
+pub fn styled_segments(text: &str, spans: &[StyledSpan]) -> Vec<AnyView> {
+    let mut segments = Vec::with_capacity(spans.len() * 2 + 1);
+    let mut pos = 0;
+    for span in spans {
+        // Ignore ranges that don't fit the text (e.g. from a newer backend)
+        let (Some(before), Some(styled)) =
+            (text.get(pos..span.start), text.get(span.start..span.end))
+        else {
+            continue;
+        };
+        if !before.is_empty() {
+            segments.push(before.to_string().into_any());
+        }
+        segments
+            .push(view! { <span style=span.style.css()>{styled.to_string()}</span> }.into_any());
+        pos = span.end;
+    }
+    if let Some(rest) = text.get(pos..) {
+        if !rest.is_empty() {
+            segments.push(rest.to_string().into_any());
+        }
+    }
+    segments
+}
+
 /// Format the content of an output line for display
 fn format_line_content(line: &OutputLine) -> String {
     match line {
         OutputLine::Command { text, .. } => format!("$ {text}"),
-        OutputLine::Stdout { text, .. } | OutputLine::Stderr { text, .. } => text.clone(),
+        OutputLine::Stdout { text, .. }
+        | OutputLine::Stderr { text, .. }
+        | OutputLine::Styled { text, .. }
+        | OutputLine::JobOutput { text, .. } => text.clone(),
         OutputLine::Notification { message, .. } => format!("⚠️  {message}"),
     }
 }
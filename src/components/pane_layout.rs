@@ -0,0 +1,138 @@
+use leptos::context::Provider;
+use leptos::ev::MouseEvent;
+use leptos::html::Div;
+use leptos::prelude::*;
+
+use crate::components::Terminal;
+use crate::models::{PaneNode, SplitDirection, Tab, MIN_SPLIT_RATIO};
+
+/// The panes of one tab, laid out according to its split tree.
+#[component]
+pub fn PaneLayout(
+    /// Tab whose panes are shown
+    tab: Tab,
+    /// Whether the tab is the visible one
+    #[prop(into)]
+    visible: Signal<bool>,
+) -> impl IntoView {
+    view! {
+        <div class="pane-layout">
+            {move || pane_view(tab.layout.get(), tab, visible)}
+        </div>
+    }
+}
+
+/// Render a layout subtree (recursive through `SplitView`)
+fn pane_view(node: PaneNode, tab: Tab, visible: Signal<bool>) -> AnyView {
+    match node {
+        PaneNode::Leaf(session_id) => {
+            view! { <PaneView tab=tab session_id=session_id visible=visible /> }.into_any()
+        }
+        PaneNode::Split {
+            direction,
+            ratio,
+            first,
+            second,
+        } => view! {
+            <SplitView
+                direction=direction
+                ratio=ratio
+                first=*first
+                second=*second
+                tab=tab
+                visible=visible
+            />
+        }
+        .into_any(),
+    }
+}
+
+/// A single pane: the terminal for one session.
+#[component]
+fn PaneView(tab: Tab, session_id: String, visible: Signal<bool>) -> impl IntoView {
+    let Some(pane) = tab.pane(&session_id) else {
+        return ().into_any();
+    };
+
+    let id = session_id.clone();
+    let focused = Signal::derive(move || tab.focused.get() == id);
+    let has_siblings = Signal::derive(move || tab.panes.with(|panes| panes.len() > 1));
+
+    let on_mousedown = move |_| {
+        if tab.focused.get_untracked() != session_id {
+            tab.focused.set(session_id.clone());
+        }
+    };
+
+    view! {
+        <div
+            class="pane"
+            class:focused=move || focused.get() && has_siblings.get()
+            on:mousedown=on_mousedown
+        >
+            <Provider value=pane.state>
+                <Terminal active=visible focused=focused />
+            </Provider>
+        </div>
+    }
+    .into_any()
+}
+
+type DragListeners = (WindowListenerHandle, WindowListenerHandle);
+
+/// Two subtrees separated by a draggable divider.
+#[component]
+fn SplitView(
+    direction: SplitDirection,
+    ratio: RwSignal<f64>,
+    first: PaneNode,
+    second: PaneNode,
+    tab: Tab,
+    visible: Signal<bool>,
+) -> impl IntoView {
+    let split_ref = NodeRef::<Div>::new();
+    let drag = StoredValue::new_local(None::<DragListeners>);
+
+    let stop_drag = move || {
+        if let Some((on_move, on_up)) = drag.try_update_value(Option::take).flatten() {
+            on_move.remove();
+            on_up.remove();
+        }
+    };
+    on_cleanup(stop_drag);
+
+    let on_divider_mousedown = move |ev: MouseEvent| {
+        ev.prevent_default();
+        stop_drag();
+
+        let on_move = window_event_listener(leptos::ev::mousemove, move |ev| {
+            let Some(split) = split_ref.get_untracked() else {
+                return;
+            };
+            let rect = split.get_bounding_client_rect();
+            let (offset, size) = match direction {
+                SplitDirection::Horizontal => {
+                    (f64::from(ev.client_x()) - rect.left(), rect.width())
+                }
+                SplitDirection::Vertical => (f64::from(ev.client_y()) - rect.top(), rect.height()),
+            };
+            if size > 0.0 {
+                ratio.set((offset / size).clamp(MIN_SPLIT_RATIO, 1.0 - MIN_SPLIT_RATIO));
+            }
+        });
+        let on_up = window_event_listener(leptos::ev::mouseup, move |_| stop_drag());
+        drag.set_value(Some((on_move, on_up)));
+    };
+
+    view! {
+        <div class=direction.css_class() node_ref=split_ref>
+            <div class="pane-slot" style:flex=move || format!("{} 1 0", ratio.get())>
+                {pane_view(first, tab, visible)}
+            </div>
+            <div class="pane-divider" on:mousedown=on_divider_mousedown></div>
+            <div class="pane-slot" style:flex=move || format!("{} 1 0", 1.0 - ratio.get())>
+                {pane_view(second, tab, visible)}
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,148 @@
+use leptos::ev::KeyboardEvent;
+use leptos::prelude::*;
+
+use crate::components::command_input::write_stdin;
+use crate::components::output_display::styled_segments;
+use crate::models::TerminalState;
+
+/// Character grid of a full-screen program, shown instead of the output while the
+/// program uses the alternate screen. Keys typed while it has focus go straight to
+/// the program.
+#[component]
+pub fn ScreenGrid() -> impl IntoView {
+    let state = use_context::<TerminalState>().expect("TerminalState context missing");
+    let screen = state.screen;
+    let rows = Memo::new(move |_| screen.with(|screen| screen.lines.len()));
+
+    let on_keydown = move |ev: KeyboardEvent| {
+        let application_cursor = screen.with_untracked(|screen| screen.application_cursor);
+        let Some(data) = key_sequence(&ev, application_cursor) else {
+            return;
+        };
+        ev.prevent_default();
+        ev.stop_propagation();
+        if let Some(session_id) = state.session_id.get_untracked() {
+            write_stdin(state, session_id, data);
+        }
+    };
+
+    let cursor_visible = move || screen.with(|screen| screen.cursor_visible);
+    let cursor_top =
+        move || screen.with(|screen| format!("{}em", f64::from(screen.cursor_row) * 1.5));
+    let cursor_left = move || screen.with(|screen| format!("{}ch", screen.cursor_col));
+
+    view! {
+        <div class="screen-grid" tabindex="0" on:keydown=on_keydown>
+            <For
+                each=move || 0..rows.get()
+                key=|row| *row
+                children=move |row| view! { <ScreenRow row=row /> }
+            />
+            <Show when=cursor_visible>
+                <div class="screen-cursor" style:top=cursor_top style:left=cursor_left></div>
+            </Show>
+        </div>
+    }
+}
+
+/// A single row; re-rendered only when its own content changes.
+#[component]
+fn ScreenRow(row: usize) -> impl IntoView {
+    let state = use_context::<TerminalState>().expect("TerminalState context missing");
+    let line = Memo::new(move |_| {
+        state
+            .screen
+            .with(|screen| screen.lines.get(row).cloned().unwrap_or_default())
+    });
+
+    view! {
+        <div class="screen-row">
+            {move || line.with(|line| styled_segments(&line.text, &line.spans))}
+        </div>
+    }
+}
+
+/// Bytes an xterm sends for a key press, or `None` for keys left to the app
+/// (Ctrl+Shift shortcuts, Alt+Arrow pane navigation, Meta combinations).
+fn key_sequence(ev: &KeyboardEvent, application_cursor: bool) -> Option<String> {
+    let (ctrl, alt, shift) = (ev.ctrl_key(), ev.alt_key(), ev.shift_key());
+    if ev.meta_key() || (ctrl && shift) {
+        return None;
+    }
+
+    let key = ev.key();
+    let sequence = match key.as_str() {
+        "Enter" => "\r".to_string(),
+        "Backspace" => "\x7f".to_string(),
+        "Tab" if shift => "\x1b[Z".to_string(),
+        "Tab" => "\t".to_string(),
+        "Escape" => "\x1b".to_string(),
+        "ArrowUp" | "ArrowDown" | "ArrowRight" | "ArrowLeft" | "Home" | "End" => {
+            if alt {
+                return None;
+            }
+            let code = match key.as_str() {
+                "ArrowUp" => 'A',
+                "ArrowDown" => 'B',
+                "ArrowRight" => 'C',
+                "ArrowLeft" => 'D',
+                "Home" => 'H',
+                _ => 'F',
+            };
+            // Modified keys carry xterm's modifier parameter (1 + shift + 4 * ctrl)
+            let modifier = 1 + u8::from(shift) + 4 * u8::from(ctrl);
+            if modifier > 1 {
+                format!("\x1b[1;{modifier}{code}")
+            } else if application_cursor {
+                format!("\x1bO{code}")
+            } else {
+                format!("\x1b[{code}")
+            }
+        }
+        "Insert" => "\x1b[2~".to_string(),
+        "Delete" => "\x1b[3~".to_string(),
+        "PageUp" => "\x1b[5~".to_string(),
+        "PageDown" => "\x1b[6~".to_string(),
+        "F1" => "\x1bOP".to_string(),
+        "F2" => "\x1bOQ".to_string(),
+        "F3" => "\x1bOR".to_string(),
+        "F4" => "\x1bOS".to_string(),
+        "F5" => "\x1b[15~".to_string(),
+        "F6" => "\x1b[17~".to_string(),
+        "F7" => "\x1b[18~".to_string(),
+        "F8" => "\x1b[19~".to_string(),
+        "F9" => "\x1b[20~".to_string(),
+        "F10" => "\x1b[21~".to_string(),
+        "F11" => "\x1b[23~".to_string(),
+        "F12" => "\x1b[24~".to_string(),
+        _ => {
+            let mut chars = key.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                // Modifier keys and other named keys
+                return None;
+            };
+            let text = if ctrl {
+                control_char(c)?.to_string()
+            } else {
+                key.clone()
+            };
+            if alt {
+                format!("\x1b{text}")
+            } else {
+                text
+            }
+        }
+    };
+    Some(sequence)
+}
+
+/// Control character sent for Ctrl+`c` (Ctrl+A is 0x01, Ctrl+[ is Escape, ...)
+fn control_char(c: char) -> Option<char> {
+    let code = match c.to_ascii_uppercase() {
+        ' ' => 0,
+        '?' => 0x7f,
+        c @ '@'..='_' => u32::from(c) - 0x40,
+        _ => return None,
+    };
+    char::from_u32(code)
+}
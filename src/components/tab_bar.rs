@@ -0,0 +1,249 @@
+use leptos::ev::KeyboardEvent;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::models::{SessionArgs, SessionInfo, SplitDirection, Tab, TabsState};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], catch)]
+    async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+}
+
+/// Arguments for the `create_session` IPC
+#[derive(Serialize)]
+struct CreateSessionArgs {
+    /// Start directory; `None` uses the backend default
+    cwd: Option<String>,
+}
+
+/// Row of tabs with buttons to open a new tab or split the focused pane.
+#[component]
+pub fn TabBar() -> impl IntoView {
+    let tabs = use_context::<TabsState>().expect("TabsState context missing");
+
+    let on_new_tab = move |_| {
+        spawn_local(async move {
+            open_new_tab(tabs).await;
+        });
+    };
+
+    view! {
+        <div class="tab-bar">
+            <For
+                each=move || tabs.tabs.get()
+                key=|tab| tab.id
+                children=move |tab| view! { <TabButton tab=tab /> }
+            />
+            <button class="tab-new" title="New tab" on:click=on_new_tab>
+                "+"
+            </button>
+            <div class="tab-bar-actions">
+                <button
+                    class="tab-split"
+                    title="Split right (Ctrl+Shift+D)"
+                    on:click=move |_| spawn_local(split_focused_pane(tabs, SplitDirection::Horizontal))
+                >
+                    "◫"
+                </button>
+                <button
+                    class="tab-split"
+                    title="Split down (Ctrl+Shift+E)"
+                    on:click=move |_| spawn_local(split_focused_pane(tabs, SplitDirection::Vertical))
+                >
+                    "⊟"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+/// A single tab: title, busy/activity indicators, close button.
+/// Double-click the title to rename the tab.
+#[component]
+fn TabButton(tab: Tab) -> impl IntoView {
+    let tabs = use_context::<TabsState>().expect("TabsState context missing");
+    let is_editing = RwSignal::new(false);
+    let draft = RwSignal::new(String::new());
+
+    let is_active = Signal::derive(move || tabs.is_active(tab.id));
+    let is_busy = Signal::derive(move || tab.is_busy());
+    let has_activity = tab.has_activity;
+    let custom_title = tab.custom_title;
+
+    let on_select = move |_| tabs.activate(tab.id);
+
+    let on_start_rename = move |_| {
+        draft.set(tab.title());
+        is_editing.set(true);
+    };
+
+    let commit_rename = move || {
+        if !is_editing.get() {
+            return;
+        }
+        let title = draft.get().trim().to_string();
+        custom_title.set((!title.is_empty()).then_some(title));
+        is_editing.set(false);
+    };
+
+    let on_rename_key = move |ev: KeyboardEvent| match ev.key().as_str() {
+        "Enter" => {
+            ev.prevent_default();
+            commit_rename();
+        }
+        "Escape" => {
+            ev.prevent_default();
+            is_editing.set(false);
+        }
+        _ => {}
+    };
+
+    let on_close = move |ev: leptos::ev::MouseEvent| {
+        // Don't also select the tab being closed
+        ev.stop_propagation();
+        spawn_local(close_tab(tabs, tab));
+    };
+
+    let title = move || tab.title();
+
+    view! {
+        <div
+            class="tab"
+            class:active=move || is_active.get()
+            class:tab-busy=move || is_busy.get()
+            on:click=on_select
+        >
+            <span class="tab-indicator">
+                {move || {
+                    if is_busy.get() {
+                        "⏳"
+                    } else if has_activity.get() {
+                        "●"
+                    } else {
+                        ""
+                    }
+                }}
+            </span>
+            {move || {
+                if is_editing.get() {
+                    view! {
+                        <input
+                            class="tab-rename"
+                            type="text"
+                            prop:value=move || draft.get()
+                            on:input=move |ev| draft.set(event_target_value(&ev))
+                            on:keydown=on_rename_key
+                            on:blur=move |_| commit_rename()
+                            on:click=|ev| ev.stop_propagation()
+                            autofocus
+                        />
+                    }
+                        .into_any()
+                } else {
+                    view! {
+                        <span class="tab-title" title="Double-click to rename" on:dblclick=on_start_rename>
+                            {title}
+                        </span>
+                    }
+                        .into_any()
+                }
+            }}
+            <button class="tab-close" title="Close tab" on:click=on_close>
+                "×"
+            </button>
+        </div>
+    }
+}
+
+/// Create a backend session, starting in the focused pane's directory.
+#[allow(clippy::future_not_send)]
+async fn create_session(tabs: TabsState) -> Result<SessionInfo, String> {
+    let cwd = tabs
+        .focused_pane()
+        .map(|pane| pane.state.cwd.get())
+        .filter(|cwd| !cwd.is_empty());
+    let args = serde_wasm_bindgen::to_value(&CreateSessionArgs { cwd })
+        .map_err(|e| format!("Failed to serialize session args: {e}"))?;
+
+    let result = invoke("create_session", args)
+        .await
+        .map_err(|e| format!("Failed to create session: {e:?}"))?;
+    serde_wasm_bindgen::from_value::<SessionInfo>(result)
+        .map_err(|e| format!("Failed to parse session: {e:?}"))
+}
+
+/// Close a backend session. Failures are only logged: the session may already be gone.
+#[allow(clippy::future_not_send)]
+async fn close_session(session_id: String) {
+    let args = match serde_wasm_bindgen::to_value(&SessionArgs { session_id }) {
+        Ok(args) => args,
+        Err(e) => {
+            web_sys::console::error_1(&format!("Failed to serialize session args: {e}").into());
+            return;
+        }
+    };
+
+    if let Err(e) = invoke("close_session", args).await {
+        web_sys::console::warn_1(&format!("Failed to close session: {e:?}").into());
+    }
+}
+
+/// Create a backend session and open it in a new, active tab.
+#[allow(clippy::future_not_send)]
+pub async fn open_new_tab(tabs: TabsState) {
+    match create_session(tabs).await {
+        Ok(session) => {
+            let tab_id = tabs.open(session);
+            tabs.activate(tab_id);
+        }
+        Err(e) => {
+            web_sys::console::error_1(&e.into());
+            tabs.show_notification("Failed to open a new tab");
+        }
+    }
+}
+
+/// Close every session in a tab and remove it, keeping at least one tab open.
+#[allow(clippy::future_not_send)]
+async fn close_tab(tabs: TabsState, tab: Tab) {
+    for pane in tab.panes.get() {
+        close_session(pane.session_id).await;
+    }
+
+    tabs.remove(tab.id);
+    if tabs.tabs.get().is_empty() {
+        open_new_tab(tabs).await;
+    }
+}
+
+/// Split the active tab's focused pane, attaching the new pane to a new session.
+#[allow(clippy::future_not_send)]
+pub async fn split_focused_pane(tabs: TabsState, direction: SplitDirection) {
+    if tabs.active_tab().is_none() {
+        return;
+    }
+    match create_session(tabs).await {
+        Ok(session) => tabs.split_focused(direction, session),
+        Err(e) => {
+            web_sys::console::error_1(&e.into());
+            tabs.show_notification("Failed to split pane");
+        }
+    }
+}
+
+/// Close the active tab's focused pane and its session, keeping at least one tab open.
+#[allow(clippy::future_not_send)]
+pub async fn close_focused_pane(tabs: TabsState) {
+    let Some(pane) = tabs.focused_pane() else {
+        return;
+    };
+    close_session(pane.session_id.clone()).await;
+
+    tabs.remove_pane(&pane.session_id);
+    if tabs.tabs.get().is_empty() {
+        open_new_tab(tabs).await;
+    }
+}
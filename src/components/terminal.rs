@@ -1,69 +1,135 @@
-use js_sys::Function;
+use leptos::html::Div;
 use leptos::prelude::*;
 use leptos::task::spawn_local;
 use serde::{Deserialize, Serialize};
-use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, ResizeObserver};
 
-use crate::components::{CommandInput, NotificationBar, OutputDisplay, PromptIndicator};
-use crate::models::{OutputLine, TerminalState};
+use crate::components::command_input::load_command_history;
+use crate::components::{
+    CommandInput, HistorySearch, ModeSelector, NotificationBar, OutputDisplay, OutputScroll,
+    PromptIndicator, ScreenGrid,
+};
+use crate::models::{HistoryLine, SessionArgs, TerminalState};
 
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], catch)]
     async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+}
 
-    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "event"], catch)]
-    async fn listen(event: &str, handler: &Closure<dyn Fn(JsValue)>) -> Result<JsValue, JsValue>;
+/// Arguments for the `get_history_since` IPC
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistorySinceArgs {
+    session_id: String,
+    seq: u64,
 }
 
-/// Tauri event payload structure
-#[derive(Serialize, Deserialize, Debug)]
-struct TauriEvent {
-    payload: OutputLine,
+/// Arguments for the `resize_pty` IPC
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResizePtyArgs {
+    session_id: String,
+    rows: u16,
+    cols: u16,
 }
 
-/// Main terminal container that orchestrates all sub-components.
+/// Approximate monospace cell size in pixels (14px font, 1.5 line height)
+/// used to derive the PTY window size from the output area.
+const CELL_WIDTH_TENTHS_PX: i64 = 84;
+const CELL_HEIGHT_PX: i64 = 21;
+
+type ResizeWatch = (ResizeObserver, Closure<dyn Fn()>);
+
+/// Terminal for one pane: orchestrates the sub-components for the `TerminalState` in context.
 #[component]
-pub fn Terminal() -> impl IntoView {
+pub fn Terminal(
+    /// Whether this terminal's tab is the visible one
+    #[prop(into)]
+    active: Signal<bool>,
+    /// Whether this terminal is the focused pane of its tab
+    #[prop(into)]
+    focused: Signal<bool>,
+) -> impl IntoView {
     let state = use_context::<TerminalState>().expect("TerminalState context missing");
-    let listeners = StoredValue::new_local(ListenerHandles::default());
+    provide_context(OutputScroll::default());
+    let container_ref = NodeRef::<Div>::new();
     let is_alive = Arc::new(AtomicBool::new(true));
 
-    // Set up Tauri event listeners on mount - run only once per component instance
-    let listeners_for_cleanup = listeners;
     let is_alive_for_cleanup = Arc::clone(&is_alive);
-    on_cleanup(move || {
-        is_alive_for_cleanup.store(false, Ordering::SeqCst);
-        cleanup_listener_handles(listeners_for_cleanup);
-    });
-
-    let state_for_listeners = state;
-    let listeners_for_listeners = listeners;
-    let is_alive_for_listeners = Arc::clone(&is_alive);
-    Effect::new(move |_| {
-        let state = state_for_listeners;
-        let listeners = listeners_for_listeners;
-        let is_alive = Arc::clone(&is_alive_for_listeners);
-        setup_event_listeners(state, listeners, &is_alive);
-    });
+    on_cleanup(move || is_alive_for_cleanup.store(false, Ordering::SeqCst));
 
-    // Fetch initial history and cwd on mount - run only once per component instance
-    let state_for_fetch = state;
+    // Fetch initial history on mount - run only once per component instance
     let is_alive_for_fetch = Arc::clone(&is_alive);
     Effect::new(move |_| {
-        let state = state_for_fetch;
         let is_alive = Arc::clone(&is_alive_for_fetch);
         spawn_local(async move {
             fetch_initial_state(state, is_alive).await;
         });
     });
 
+    // Keep the PTY window size in sync with the output area (window resizes, split
+    // drags, and tab switches all change it). The whole terminal is observed because
+    // the output is hidden while a full-screen program's grid takes its place.
+    let last_size = StoredValue::new(None::<(u16, u16)>);
+    let resize_watch = StoredValue::new_local(None::<ResizeWatch>);
+    Effect::new(move |_| {
+        let Some(terminal) = container_ref.get() else {
+            return;
+        };
+        let callback: Closure<dyn Fn()> =
+            Closure::new(move || sync_pty_size(state, container_ref, last_size));
+        match ResizeObserver::new(callback.as_ref().unchecked_ref()) {
+            Ok(observer) => {
+                observer.observe(&terminal);
+                resize_watch.set_value(Some((observer, callback)));
+            }
+            Err(e) => {
+                web_sys::console::warn_1(&format!("Failed to observe resizes: {e:?}").into());
+            }
+        }
+    });
+    on_cleanup(move || {
+        if let Some((observer, _callback)) = resize_watch.try_update_value(Option::take).flatten() {
+            observer.disconnect();
+        }
+    });
+
+    // Full-screen programs get a character grid in place of the output
+    let screen_active = Memo::new(move |_| state.is_screen_active());
+
+    // Keep keyboard focus on the focused pane's input (also after a command finishes),
+    // on the history search while it is open, or on the grid while a full-screen program
+    // runs
+    Effect::new(move |_| {
+        if !active.get() || !focused.get() || state.is_input_disabled() {
+            return;
+        }
+        let selector = if screen_active.get() {
+            ".screen-grid"
+        } else if state.history_search_open.get() {
+            ".history-search-input"
+        } else {
+            ".command-input"
+        };
+        if let Some(input) = container_ref
+            .get()
+            .and_then(|terminal| terminal.query_selector(selector).ok().flatten())
+        {
+            let _ = input.unchecked_into::<HtmlElement>().focus();
+        }
+    });
+
     view! {
-        <div class="terminal-container">
+        <div
+            class="terminal-container"
+            class:screen-active=move || screen_active.get()
+            node_ref=container_ref
+        >
             <NotificationBar />
             {move || {
                 if state.listener_failed.get() {
@@ -79,178 +145,72 @@ pub fn Terminal() -> impl IntoView {
                     ().into_any()
                 }
             }}
+            // Hidden rather than unmounted, so its scroll position and resize observer survive
             <OutputDisplay />
+            <Show when=move || screen_active.get()>
+                <ScreenGrid />
+            </Show>
+            <Show when=move || state.history_search_open.get()>
+                <HistorySearch />
+            </Show>
             <div class="input-row">
                 <PromptIndicator />
                 <CommandInput />
+                <ModeSelector />
             </div>
         </div>
     }
 }
 
-#[derive(Default)]
-struct ListenerHandles {
-    output: Option<ListenerHandle>,
-    notify: Option<ListenerHandle>,
-}
-
-#[derive(Clone)]
-struct ListenerHandle {
-    callback: Rc<Closure<dyn Fn(JsValue)>>,
-    unlisten: JsValue,
-}
-
-type ListenerStore = StoredValue<ListenerHandles, LocalStorage>;
-
-fn cleanup_listener_handles(handles: ListenerStore) {
-    handles.update_value(|handles| {
-        if let Some(handle) = handles.output.take() {
-            if let Err(e) = call_unlisten(handle.unlisten) {
-                web_sys::console::error_1(
-                    &format!("Failed to unlisten output-line handler: {e:?}").into(),
-                );
-            }
-            drop(handle.callback);
-        }
+/// Derive the terminal size from the output area (or the grid replacing it) and report it
+/// to the backend if it changed.
+fn sync_pty_size(
+    state: TerminalState,
+    container_ref: NodeRef<Div>,
+    last_size: StoredValue<Option<(u16, u16)>>,
+) {
+    let Some(session_id) = state.session_id.get_untracked() else {
+        return;
+    };
+    let Some(container) = container_ref.get_untracked().and_then(|terminal| {
+        terminal
+            .query_selector(".screen-grid")
+            .ok()
+            .flatten()
+            .or_else(|| terminal.query_selector(".output-display").ok().flatten())
+    }) else {
+        return;
+    };
 
-        if let Some(handle) = handles.notify.take() {
-            if let Err(e) = call_unlisten(handle.unlisten) {
-                web_sys::console::error_1(
-                    &format!("Failed to unlisten shell-notification handler: {e:?}").into(),
-                );
-            }
-            drop(handle.callback);
-        }
-    });
-}
+    let width = i64::from(container.client_width());
+    let height = i64::from(container.client_height());
+    if width <= 0 || height <= 0 {
+        return;
+    }
 
-fn call_unlisten(unlisten: JsValue) -> Result<(), JsValue> {
-    let func: Function = unlisten.dyn_into()?;
-    func.call0(&JsValue::NULL)?;
-    Ok(())
-}
+    let rows = u16::try_from(height / CELL_HEIGHT_PX).unwrap_or(u16::MAX);
+    let cols = u16::try_from(width * 10 / CELL_WIDTH_TENTHS_PX).unwrap_or(u16::MAX);
+    if last_size.get_value() == Some((rows, cols)) {
+        return;
+    }
+    last_size.set_value(Some((rows, cols)));
 
-/// Set up Tauri event listeners for output-line and shell-notification events
-fn setup_event_listeners(
-    state: TerminalState,
-    listeners: ListenerStore,
-    is_alive: &Arc<AtomicBool>,
-) {
-    let is_alive = Arc::clone(is_alive);
-    let is_alive_for_output_handler = Arc::clone(&is_alive);
-    // Output line listener
-    let output_handler = Rc::new(Closure::new(move |event: JsValue| {
-        if !is_alive_for_output_handler.load(Ordering::SeqCst) {
-            return;
-        }
-        match serde_wasm_bindgen::from_value::<TauriEvent>(event) {
-            Ok(tauri_event) => {
-                state.push_history(tauri_event.payload);
-            }
-            Err(e) => {
-                web_sys::console::error_1(
-                    &format!("Failed to parse output-line event: {e:?}").into(),
-                );
-            }
-        }
-    }));
+    let args = ResizePtyArgs {
+        session_id,
+        rows,
+        cols,
+    };
 
-    let state_output = state;
-    let listeners_output = listeners;
-    let output_handler_for_listen = output_handler.clone();
-    let is_alive_output = Arc::clone(&is_alive);
     spawn_local(async move {
-        match listen("output-line", &output_handler_for_listen).await {
-            Ok(unlisten) => {
-                if !is_alive_output.load(Ordering::SeqCst) {
-                    if let Err(e) = call_unlisten(unlisten) {
-                        web_sys::console::warn_1(
-                            &format!("Failed to unlisten output-line handler after unmount: {e:?}")
-                                .into(),
-                        );
-                    }
-                    return;
-                }
-                listeners_output.update_value(|handles| {
-                    handles.output = Some(ListenerHandle {
-                        callback: output_handler,
-                        unlisten,
-                    });
-                });
-            }
+        let args = match serde_wasm_bindgen::to_value(&args) {
+            Ok(args) => args,
             Err(e) => {
-                if !is_alive_output.load(Ordering::SeqCst) {
-                    return;
-                }
-                let err_text = e.as_string().unwrap_or_else(|| format!("{e:?}"));
-                let error_msg =
-                    format!("Terminal connection failed: output-line listener error: {err_text}");
-                let notification_msg = format!("Terminal is non-functional: {error_msg}");
-                web_sys::console::error_1(&wasm_bindgen::JsValue::from(error_msg.as_str()));
-                state_output.set_listener_failed(error_msg);
-                state_output.show_notification(notification_msg);
-            }
-        }
-    });
-
-    // Notification listener
-    let state_notify = state;
-    let listeners_notify = listeners;
-    let is_alive_notify = Arc::clone(&is_alive);
-    let notify_handler = Rc::new(Closure::new({
-        let is_alive_notify = Arc::clone(&is_alive_notify);
-        move |event: JsValue| {
-            if !is_alive_notify.load(Ordering::SeqCst) {
+                web_sys::console::error_1(&format!("Failed to serialize resize args: {e}").into());
                 return;
             }
-            match serde_wasm_bindgen::from_value::<TauriEvent>(event) {
-                Ok(tauri_event) => {
-                    if let OutputLine::Notification { message, .. } = tauri_event.payload {
-                        state_notify.show_notification(message);
-                    }
-                }
-                Err(e) => {
-                    web_sys::console::error_1(
-                        &format!("Failed to parse notification event: {e:?}").into(),
-                    );
-                }
-            }
-        }
-    }));
-    let notify_handler_for_listen = notify_handler.clone();
-    let is_alive_notify = Arc::clone(&is_alive_notify);
-    spawn_local(async move {
-        match listen("shell-notification", &notify_handler_for_listen).await {
-            Ok(unlisten) => {
-                if !is_alive_notify.load(Ordering::SeqCst) {
-                    if let Err(e) = call_unlisten(unlisten) {
-                        web_sys::console::warn_1(
-                            &format!("Failed to unlisten shell-notification handler after unmount: {e:?}")
-                            .into(),
-                        );
-                    }
-                    return;
-                }
-                listeners_notify.update_value(|handles| {
-                    handles.notify = Some(ListenerHandle {
-                        callback: notify_handler,
-                        unlisten,
-                    });
-                });
-            }
-            Err(e) => {
-                if !is_alive_notify.load(Ordering::SeqCst) {
-                    return;
-                }
-                let err_text = e.as_string().unwrap_or_else(|| format!("{e:?}"));
-                let error_msg = format!(
-                    "Terminal connection failed: shell-notification listener error: {err_text}"
-                );
-                let notification_msg = format!("Terminal is non-functional: {error_msg}");
-                web_sys::console::error_1(&wasm_bindgen::JsValue::from(error_msg.as_str()));
-                state_notify.set_listener_failed(error_msg);
-                state_notify.show_notification(notification_msg);
-            }
+        };
+        if let Err(e) = invoke("resize_pty", args).await {
+            web_sys::console::warn_1(&format!("Failed to resize PTY: {e:?}").into());
         }
     });
 }
@@ -287,21 +247,48 @@ async fn set_home_dir_in_memory(state: TerminalState, is_alive: Arc<AtomicBool>)
     }
 }
 
-/// Fetch initial history and cwd from backend
+/// Fetch the session's existing history from the backend
 #[allow(clippy::future_not_send)]
 async fn fetch_initial_state(state: TerminalState, is_alive: Arc<AtomicBool>) {
     // We intentionally avoid storing the raw home directory; track only presence.
     set_home_dir_in_memory(state, Arc::clone(&is_alive)).await;
 
+    load_command_history(state).await;
+
+    sync_history(state, is_alive).await;
+}
+
+/// Fetch the history lines not seen yet (all of them the first time) and merge them into
+/// the session's output. The last line seen is fetched again in case it was a partial
+/// line that has been rewritten since.
+#[allow(clippy::future_not_send)]
+pub async fn sync_history(state: TerminalState, is_alive: Arc<AtomicBool>) {
+    let Some(session_id) = state.session_id.get_untracked() else {
+        return;
+    };
+    let request = match state.last_seq() {
+        Some(seq) => serde_wasm_bindgen::to_value(&HistorySinceArgs { session_id, seq })
+            .map(|args| ("get_history_since", args)),
+        None => serde_wasm_bindgen::to_value(&SessionArgs { session_id })
+            .map(|args| ("get_history", args)),
+    };
+    let (command, args) = match request {
+        Ok(request) => request,
+        Err(e) => {
+            web_sys::console::error_1(&format!("Failed to serialize history args: {e}").into());
+            return;
+        }
+    };
+
     // Fetch history with error handling
-    match invoke("get_history", JsValue::NULL).await {
+    match invoke(command, args).await {
         Ok(history_result) => {
-            match serde_wasm_bindgen::from_value::<Vec<OutputLine>>(history_result) {
+            match serde_wasm_bindgen::from_value::<Vec<HistoryLine>>(history_result) {
                 Ok(history) => {
                     if !is_alive.load(Ordering::SeqCst) {
                         return;
                     }
-                    state.set_history(history);
+                    state.sync_history(history);
                 }
                 Err(e) => {
                     web_sys::console::error_1(&format!("Failed to parse history: {e:?}").into());
@@ -320,21 +307,4 @@ async fn fetch_initial_state(state: TerminalState, is_alive: Arc<AtomicBool>) {
             state.show_notification("Failed to connect to shell service".to_string());
         }
     }
-
-    // Fetch cwd with error handling
-    match invoke("get_cwd", JsValue::NULL).await {
-        Ok(cwd_result) => {
-            if let Some(cwd) = cwd_result.as_string() {
-                if !is_alive.load(Ordering::SeqCst) {
-                    return;
-                }
-                state.cwd.set(cwd);
-            } else {
-                web_sys::console::warn_1(&"CWD response was not a string".into());
-            }
-        }
-        Err(e) => {
-            web_sys::console::error_1(&format!("Failed to fetch cwd: {e:?}").into());
-        }
-    }
 }
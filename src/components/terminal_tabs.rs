@@ -0,0 +1,364 @@
+use js_sys::Function;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::components::tab_bar::{close_focused_pane, open_new_tab, split_focused_pane};
+use crate::components::terminal::sync_history;
+use crate::components::{PaneLayout, TabBar};
+use crate::models::{
+    CommandFinished, CommandStarted, ConfigStatus, CwdChanged, FocusDirection, OutputBatch,
+    OutputLine, SessionInfo, SessionScreen, SplitDirection, TabsState,
+};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], catch)]
+    async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "event"], catch)]
+    async fn listen(event: &str, handler: &Closure<dyn Fn(JsValue)>) -> Result<JsValue, JsValue>;
+}
+
+/// Tauri event payload structure
+#[derive(Serialize, Deserialize, Debug)]
+struct TauriEvent<T> {
+    payload: T,
+}
+
+/// Tab bar plus the pane layout of each tab; routes backend events to the pane they belong to.
+///
+/// Keyboard shortcuts: Ctrl+Shift+D splits the focused pane side by side, Ctrl+Shift+E
+/// splits it top to bottom, Ctrl+Shift+W closes it, and Alt+Arrow moves focus between panes.
+#[component]
+pub fn TerminalTabs() -> impl IntoView {
+    let tabs = use_context::<TabsState>().expect("TabsState context missing");
+    let listeners = StoredValue::new_local(ListenerHandles::default());
+    let is_alive = Arc::new(AtomicBool::new(true));
+
+    // Set up Tauri event listeners on mount - run only once per component instance
+    let is_alive_for_cleanup = Arc::clone(&is_alive);
+    on_cleanup(move || {
+        is_alive_for_cleanup.store(false, Ordering::SeqCst);
+        cleanup_listener_handles(listeners);
+    });
+
+    let is_alive_for_listeners = Arc::clone(&is_alive);
+    Effect::new(move |_| {
+        setup_event_listeners(tabs, listeners, &is_alive_for_listeners);
+    });
+
+    // Open a tab for every backend session (or a fresh session) on mount, then apply
+    // the settings (a config file error is shown in the focused pane)
+    let is_alive_for_load = Arc::clone(&is_alive);
+    Effect::new(move |_| {
+        let is_alive = Arc::clone(&is_alive_for_load);
+        spawn_local(async move {
+            load_sessions(tabs, Arc::clone(&is_alive)).await;
+            load_config(tabs, is_alive).await;
+        });
+    });
+
+    let shortcut_handle = window_event_listener(leptos::ev::keydown, move |ev| {
+        let key = ev.key();
+        if ev.ctrl_key() && ev.shift_key() && !ev.alt_key() {
+            match key.to_ascii_uppercase().as_str() {
+                "D" => spawn_local(split_focused_pane(tabs, SplitDirection::Horizontal)),
+                "E" => spawn_local(split_focused_pane(tabs, SplitDirection::Vertical)),
+                "W" => spawn_local(close_focused_pane(tabs)),
+                _ => return,
+            }
+            ev.prevent_default();
+        } else if ev.alt_key() && !ev.ctrl_key() && !ev.shift_key() {
+            if let Some(direction) = FocusDirection::from_key(&key) {
+                ev.prevent_default();
+                tabs.move_focus(direction);
+            }
+        }
+    });
+    on_cleanup(move || shortcut_handle.remove());
+
+    view! {
+        <TabBar />
+        <For
+            each=move || tabs.tabs.get()
+            key=|tab| tab.id
+            children=move |tab| {
+                let active = Signal::derive(move || tabs.is_active(tab.id));
+                view! {
+                    <div class="tab-panel" class:hidden=move || !active.get()>
+                        <PaneLayout tab=tab visible=active />
+                    </div>
+                }
+            }
+        />
+    }
+}
+
+#[derive(Default)]
+struct ListenerHandles {
+    handles: Vec<ListenerHandle>,
+}
+
+#[derive(Clone)]
+struct ListenerHandle {
+    event: &'static str,
+    callback: Rc<Closure<dyn Fn(JsValue)>>,
+    unlisten: JsValue,
+}
+
+type ListenerStore = StoredValue<ListenerHandles, LocalStorage>;
+
+fn cleanup_listener_handles(handles: ListenerStore) {
+    handles.update_value(|handles| {
+        for handle in handles.handles.drain(..) {
+            if let Err(e) = call_unlisten(handle.unlisten) {
+                web_sys::console::error_1(
+                    &format!("Failed to unlisten {} handler: {e:?}", handle.event).into(),
+                );
+            }
+            drop(handle.callback);
+        }
+    });
+}
+
+fn call_unlisten(unlisten: JsValue) -> Result<(), JsValue> {
+    let func: Function = unlisten.dyn_into()?;
+    func.call0(&JsValue::NULL)?;
+    Ok(())
+}
+
+/// Build a listener callback that parses the event payload as `T` and hands it to `on_payload`.
+/// Callbacks are ignored after the component unmounts.
+fn event_handler<T, F>(
+    event: &'static str,
+    is_alive: &Arc<AtomicBool>,
+    on_payload: F,
+) -> Rc<Closure<dyn Fn(JsValue)>>
+where
+    T: DeserializeOwned + 'static,
+    F: Fn(T) + 'static,
+{
+    let is_alive = Arc::clone(is_alive);
+    Rc::new(Closure::new(move |value: JsValue| {
+        if !is_alive.load(Ordering::SeqCst) {
+            return;
+        }
+        match serde_wasm_bindgen::from_value::<TauriEvent<T>>(value) {
+            Ok(tauri_event) => on_payload(tauri_event.payload),
+            Err(e) => {
+                web_sys::console::error_1(&format!("Failed to parse {event} event: {e:?}").into());
+            }
+        }
+    }))
+}
+
+/// Register `handler` for `event`, storing the unlisten handle for cleanup.
+/// A registration failure leaves the terminal non-functional and is surfaced to the user.
+fn register_listener(
+    event: &'static str,
+    handler: Rc<Closure<dyn Fn(JsValue)>>,
+    tabs: TabsState,
+    listeners: ListenerStore,
+    is_alive: &Arc<AtomicBool>,
+) {
+    let is_alive = Arc::clone(is_alive);
+    spawn_local(async move {
+        add_listener(event, handler, tabs, listeners, &is_alive).await;
+    });
+}
+
+/// Register `handler` for `event` like `register_listener`, returning whether it was
+/// registered.
+#[allow(clippy::future_not_send)]
+async fn add_listener(
+    event: &'static str,
+    handler: Rc<Closure<dyn Fn(JsValue)>>,
+    tabs: TabsState,
+    listeners: ListenerStore,
+    is_alive: &AtomicBool,
+) -> bool {
+    match listen(event, &handler).await {
+        Ok(unlisten) => {
+            if !is_alive.load(Ordering::SeqCst) {
+                if let Err(e) = call_unlisten(unlisten) {
+                    web_sys::console::warn_1(
+                        &format!("Failed to unlisten {event} handler after unmount: {e:?}").into(),
+                    );
+                }
+                return false;
+            }
+            listeners.update_value(|handles| {
+                handles.handles.push(ListenerHandle {
+                    event,
+                    callback: handler,
+                    unlisten,
+                });
+            });
+            true
+        }
+        Err(e) => {
+            if !is_alive.load(Ordering::SeqCst) {
+                return false;
+            }
+            let err_text = e.as_string().unwrap_or_else(|| format!("{e:?}"));
+            let error_msg =
+                format!("Terminal connection failed: {event} listener error: {err_text}");
+            let notification_msg = format!("Terminal is non-functional: {error_msg}");
+            web_sys::console::error_1(&wasm_bindgen::JsValue::from(error_msg.as_str()));
+            tabs.set_listener_failed(error_msg);
+            tabs.show_notification(notification_msg);
+            false
+        }
+    }
+}
+
+/// Set up Tauri event listeners for output-batch, screen-update, shell-notification,
+/// cwd-changed, command-started, command-finished and config-changed events
+fn setup_event_listeners(tabs: TabsState, listeners: ListenerStore, is_alive: &Arc<AtomicBool>) {
+    // Output listener
+    let output_handler = event_handler("output-batch", is_alive, move |batch: OutputBatch| {
+        tabs.push_output(batch);
+    });
+    let is_alive_for_output = Arc::clone(is_alive);
+    spawn_local(async move {
+        if add_listener(
+            "output-batch",
+            output_handler,
+            tabs,
+            listeners,
+            &is_alive_for_output,
+        )
+        .await
+        {
+            // Catch up on output sent before the listener was in place
+            for pane in tabs.panes() {
+                spawn_local(sync_history(pane.state, Arc::clone(&is_alive_for_output)));
+            }
+        }
+    });
+
+    // Screen listener (full-screen programs in PTY mode)
+    let screen_handler = event_handler("screen-update", is_alive, move |event: SessionScreen| {
+        tabs.push_screen(event);
+    });
+    register_listener("screen-update", screen_handler, tabs, listeners, is_alive);
+
+    // Notification listener
+    let notify_handler = event_handler("shell-notification", is_alive, move |line: OutputLine| {
+        if let OutputLine::Notification { message, .. } = line {
+            tabs.show_notification(message);
+        }
+    });
+    register_listener(
+        "shell-notification",
+        notify_handler,
+        tabs,
+        listeners,
+        is_alive,
+    );
+
+    // Working directory listener (e.g. `cd` in a persistent shell)
+    let cwd_handler = event_handler("cwd-changed", is_alive, move |event: CwdChanged| {
+        if let Some((_, pane)) = tabs.find_pane(&event.session_id) {
+            pane.state.cwd.set(event.cwd);
+        }
+    });
+    register_listener("cwd-changed", cwd_handler, tabs, listeners, is_alive);
+
+    // Command lifecycle listeners drive each pane's busy state
+    let started_handler =
+        event_handler("command-started", is_alive, move |event: CommandStarted| {
+            tabs.command_started(&event);
+        });
+    register_listener(
+        "command-started",
+        started_handler,
+        tabs,
+        listeners,
+        is_alive,
+    );
+
+    let finished_handler = event_handler(
+        "command-finished",
+        is_alive,
+        move |event: CommandFinished| {
+            tabs.command_finished(event);
+        },
+    );
+    register_listener(
+        "command-finished",
+        finished_handler,
+        tabs,
+        listeners,
+        is_alive,
+    );
+
+    // Settings reloaded after the config file changed
+    let config_handler = event_handler("config-changed", is_alive, move |status: ConfigStatus| {
+        tabs.apply_config(status);
+    });
+    register_listener("config-changed", config_handler, tabs, listeners, is_alive);
+}
+
+/// Open a tab for each existing backend session, or a new session if there are none.
+#[allow(clippy::future_not_send)]
+async fn load_sessions(tabs: TabsState, is_alive: Arc<AtomicBool>) {
+    let sessions = match invoke("list_sessions", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<SessionInfo>>(result) {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                web_sys::console::error_1(&format!("Failed to parse sessions: {e:?}").into());
+                Vec::new()
+            }
+        },
+        Err(e) => {
+            web_sys::console::error_1(&format!("Failed to list sessions: {e:?}").into());
+            Vec::new()
+        }
+    };
+    if !is_alive.load(Ordering::SeqCst) {
+        return;
+    }
+
+    if sessions.is_empty() {
+        open_new_tab(tabs).await;
+        return;
+    }
+
+    let mut first = None;
+    for session in sessions {
+        let tab_id = tabs.open(session);
+        first.get_or_insert(tab_id);
+    }
+    if let Some(tab_id) = first {
+        tabs.activate(tab_id);
+    }
+}
+
+/// Apply the user configuration in effect.
+#[allow(clippy::future_not_send)]
+async fn load_config(tabs: TabsState, is_alive: Arc<AtomicBool>) {
+    let status = match invoke("get_config", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<ConfigStatus>(result) {
+            Ok(status) => status,
+            Err(e) => {
+                web_sys::console::error_1(&format!("Failed to parse config: {e:?}").into());
+                return;
+            }
+        },
+        Err(e) => {
+            web_sys::console::error_1(&format!("Failed to get config: {e:?}").into());
+            return;
+        }
+    };
+    if is_alive.load(Ordering::SeqCst) {
+        tabs.apply_config(status);
+    }
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// How a command's standard streams are connected to the backend.
+/// Mirrors the backend `ExecutionMode` type for IPC serialization.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// stdout/stderr captured through pipes (programs see no TTY)
+    Piped,
+    /// Command attached to a pseudo-terminal
+    #[default]
+    Pty,
+    /// Command written to the session's long-lived shell (state persists between commands)
+    Persistent,
+}
+
+impl ExecutionMode {
+    /// All modes, in the order offered to the user
+    pub const ALL: [Self; 3] = [Self::Pty, Self::Persistent, Self::Piped];
+
+    /// Short label shown in the mode selector
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Piped => "pipe",
+            Self::Pty => "pty",
+            Self::Persistent => "shell",
+        }
+    }
+
+    /// Parse a label produced by [`ExecutionMode::label`]
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.label() == label)
+    }
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+/// A command line entered in any session.
+/// Mirrors the backend `CommandHistoryEntry` type for IPC serialization.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandHistoryEntry {
+    pub command: String,
+    pub cwd: String,
+    pub exit_code: Option<i32>,
+    pub timestamp: u64,
+}
+
+/// Outcome of the last run a history search is restricted to.
+/// Mirrors the backend `ExitStatusFilter` type for IPC serialization.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExitStatusFilter {
+    Succeeded,
+    Failed,
+}
+
+impl ExitStatusFilter {
+    /// Next filter in the cycle all → succeeded → failed → all
+    pub const fn cycle(filter: Option<Self>) -> Option<Self> {
+        match filter {
+            None => Some(Self::Succeeded),
+            Some(Self::Succeeded) => Some(Self::Failed),
+            Some(Self::Failed) => None,
+        }
+    }
+
+    /// Label shown on the search's status filter
+    pub const fn label(filter: Option<Self>) -> &'static str {
+        match filter {
+            None => "any status",
+            Some(Self::Succeeded) => "succeeded",
+            Some(Self::Failed) => "failed",
+        }
+    }
+}
+
+/// Up/Down navigation through previously entered commands, as in a shell.
+///
+/// The line being typed when navigation starts is kept as a draft and comes back after
+/// moving down past the most recent command.
+#[derive(Debug, Clone, Default)]
+pub struct CommandRecall {
+    /// Commands oldest first, each appearing once
+    commands: Vec<String>,
+    /// Index of the recalled command; `None` while editing the draft
+    position: Option<usize>,
+    /// Line being typed before navigation started
+    draft: String,
+}
+
+impl CommandRecall {
+    /// Replace the commands (oldest first), ending any navigation
+    pub fn set_commands(&mut self, commands: Vec<String>) {
+        self.commands = commands;
+        self.reset();
+    }
+
+    /// Add a command just entered, moving an earlier copy of it to the end
+    pub fn push(&mut self, command: String) {
+        self.commands.retain(|existing| *existing != command);
+        self.commands.push(command);
+        self.reset();
+    }
+
+    /// Recall the command before the current one, saving `input` as the draft when
+    /// navigation starts. Returns `None` at the oldest command.
+    pub fn older(&mut self, input: &str) -> Option<String> {
+        let index = match self.position {
+            None => {
+                let last = self.commands.len().checked_sub(1)?;
+                self.draft = input.to_string();
+                last
+            }
+            Some(index) => index.checked_sub(1)?,
+        };
+        self.position = Some(index);
+        self.commands.get(index).cloned()
+    }
+
+    /// Recall the command after the current one, or the draft after the most recent
+    /// command. Returns `None` when not navigating.
+    pub fn newer(&mut self) -> Option<String> {
+        let index = self.position? + 1;
+        if index < self.commands.len() {
+            self.position = Some(index);
+            self.commands.get(index).cloned()
+        } else {
+            self.position = None;
+            Some(std::mem::take(&mut self.draft))
+        }
+    }
+
+    /// End navigation, forgetting the draft
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+}
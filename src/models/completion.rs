@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+/// What a completion candidate refers to.
+/// Mirrors the backend `CandidateKind` type for IPC serialization.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CandidateKind {
+    Command,
+    Directory,
+    File,
+    Variable,
+    Branch,
+    Subcommand,
+}
+
+impl CandidateKind {
+    /// CSS class of a candidate of this kind
+    pub const fn class(self) -> &'static str {
+        match self {
+            Self::Command => "completion-item completion-command",
+            Self::Directory => "completion-item completion-directory",
+            Self::File => "completion-item completion-file",
+            Self::Variable => "completion-item completion-variable",
+            Self::Branch => "completion-item completion-branch",
+            Self::Subcommand => "completion-item completion-subcommand",
+        }
+    }
+}
+
+/// One way to complete the word under the cursor.
+/// Mirrors the backend `CompletionCandidate` type for IPC serialization.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompletionCandidate {
+    pub replacement: String,
+    pub display: String,
+    pub kind: CandidateKind,
+}
+
+/// Candidates for the word `input[start..end]` (byte offsets), returned by `complete`.
+/// Mirrors the backend `Completion` type for IPC serialization.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Completion {
+    pub start: usize,
+    pub end: usize,
+    pub candidates: Vec<CompletionCandidate>,
+    pub common_prefix: String,
+}
+
+/// Candidates listed after Tab found several; pressing Tab again puts them in the input
+/// one after another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionMenu {
+    /// Byte range of the input holding the word, or the candidate that replaced it
+    pub start: usize,
+    pub end: usize,
+    pub candidates: Vec<CompletionCandidate>,
+    /// Candidate currently in the input; `None` until cycling starts
+    pub selected: Option<usize>,
+}
+
+impl CompletionMenu {
+    /// Put the next candidate (the previous one if `!forward`) in `input`. Returns the
+    /// new input and the byte offset of the cursor after the candidate.
+    pub fn cycle(&mut self, input: &str, forward: bool) -> Option<(String, usize)> {
+        let count = self.candidates.len();
+        let index = match (self.selected, forward) {
+            _ if count == 0 => return None,
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+        };
+        self.select(input, index)
+    }
+
+    /// Put candidate `index` in `input`, as [`CompletionMenu::cycle`] does
+    pub fn select(&mut self, input: &str, index: usize) -> Option<(String, usize)> {
+        let replacement = &self.candidates.get(index)?.replacement;
+        let (text, cursor) = replace_range(input, self.start, self.end, replacement)?;
+        self.selected = Some(index);
+        self.end = cursor;
+        Some((text, cursor))
+    }
+}
+
+/// Replace bytes `start..end` of `input`, returning the new input and the byte offset
+/// just after the replacement. `None` if the range is not valid in `input`.
+pub fn replace_range(
+    input: &str,
+    start: usize,
+    end: usize,
+    replacement: &str,
+) -> Option<(String, usize)> {
+    input.get(start..end)?;
+    let text = format!("{}{replacement}{}", &input[..start], &input[end..]);
+    Some((text, start + replacement.len()))
+}
+
+/// Byte offset of the UTF-16 offset `utf16_offset` (a DOM cursor position) in `text`
+pub fn byte_offset(text: &str, utf16_offset: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        if units >= utf16_offset {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// UTF-16 offset (a DOM cursor position) of the byte offset `byte_offset` in `text`
+pub fn utf16_offset(text: &str, byte_offset: usize) -> usize {
+    text.get(..byte_offset).map_or_else(
+        || text.encode_utf16().count(),
+        |prefix| prefix.encode_utf16().count(),
+    )
+}
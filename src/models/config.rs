@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// User settings applied by the interface.
+/// Mirrors the parts of the backend `Config` type used by the UI.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Config {
+    pub scrollback: ScrollbackConfig,
+    pub notifications: NotificationConfig,
+}
+
+/// Mirrors the backend `ScrollbackConfig` type for IPC serialization.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ScrollbackConfig {
+    /// Lines kept in each pane's output
+    pub display_lines: usize,
+}
+
+impl Default for ScrollbackConfig {
+    fn default() -> Self {
+        Self {
+            display_lines: 100_000,
+        }
+    }
+}
+
+/// Mirrors the backend `NotificationConfig` type for IPC serialization.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// How long a notification stays visible; 0 keeps it until the next one
+    pub timeout_ms: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 3000 }
+    }
+}
+
+/// Settings in effect and the config file's problem, if any (`get_config` result and
+/// `config-changed` payload).
+/// Mirrors the backend `ConfigStatus` type for IPC serialization.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConfigStatus {
+    pub config: Config,
+    pub path: Option<String>,
+    pub error: Option<String>,
+}
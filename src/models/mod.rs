@@ -1,5 +1,25 @@
+pub mod command;
+pub mod command_history;
+pub mod completion;
+pub mod config;
 pub mod output_line;
+pub mod panes;
+pub mod screen;
+pub mod session;
+pub mod tabs;
 pub mod terminal_state;
 
-pub use output_line::OutputLine;
+pub use command::ExecutionMode;
+pub use command_history::{CommandHistoryEntry, CommandRecall, ExitStatusFilter};
+pub use completion::{
+    byte_offset, replace_range, utf16_offset, CandidateKind, Completion, CompletionMenu,
+};
+pub use config::{Config, ConfigStatus};
+pub use output_line::{HistoryLine, NotificationLevel, OutputLine, StyledSpan};
+pub use panes::{FocusDirection, Pane, PaneNode, SplitDirection, MIN_SPLIT_RATIO};
+pub use screen::{Screen, SessionScreen};
+pub use session::{
+    CommandFinished, CommandStarted, CwdChanged, OutputBatch, SessionArgs, SessionInfo,
+};
+pub use tabs::{Tab, TabsState};
 pub use terminal_state::TerminalState;
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
 
 /// Represents a single line in the terminal history buffer.
 /// Mirrors the backend `OutputLine` type for IPC serialization.
@@ -14,6 +15,23 @@ pub enum OutputLine {
     Stdout { text: String, timestamp: u64 },
     /// Standard error from command
     Stderr { text: String, timestamp: u64 },
+    /// Command output containing SGR styling (colors, bold, ...).
+    /// `text` is the plain text; `spans` mark its styled byte ranges.
+    Styled {
+        stream: OutputStream,
+        text: String,
+        spans: Vec<StyledSpan>,
+        timestamp: u64,
+    },
+    /// Output of a command running as a job (started with `&` or suspended), tagged
+    /// with the job number. `spans` is empty for unstyled text.
+    JobOutput {
+        job_id: u32,
+        stream: OutputStream,
+        text: String,
+        spans: Vec<StyledSpan>,
+        timestamp: u64,
+    },
     /// System notification (e.g., "Shell restarted", "Output truncated...")
     Notification {
         message: String,
@@ -23,12 +41,22 @@ pub enum OutputLine {
 }
 
 impl OutputLine {
+    /// Get the job this line of command output came from (if it ran as a job)
+    pub const fn job_id(&self) -> Option<u32> {
+        match self {
+            Self::JobOutput { job_id, .. } => Some(*job_id),
+            _ => None,
+        }
+    }
+
     /// Get the timestamp of this output line
     pub const fn timestamp(&self) -> u64 {
         match self {
             Self::Command { timestamp, .. }
             | Self::Stdout { timestamp, .. }
             | Self::Stderr { timestamp, .. }
+            | Self::Styled { timestamp, .. }
+            | Self::JobOutput { timestamp, .. }
             | Self::Notification { timestamp, .. } => *timestamp,
         }
     }
@@ -37,9 +65,11 @@ impl OutputLine {
     #[allow(dead_code)]
     pub fn text(&self) -> &str {
         match self {
-            Self::Command { text, .. } | Self::Stdout { text, .. } | Self::Stderr { text, .. } => {
-                text
-            }
+            Self::Command { text, .. }
+            | Self::Stdout { text, .. }
+            | Self::Stderr { text, .. }
+            | Self::Styled { text, .. }
+            | Self::JobOutput { text, .. } => text,
             Self::Notification { message, .. } => message,
         }
     }
@@ -48,15 +78,30 @@ impl OutputLine {
     pub const fn css_class(&self) -> &'static str {
         match self {
             Self::Command { .. } => "line-command",
-            Self::Stdout { .. } => "line-stdout",
-            Self::Stderr { .. } => "line-stderr",
+            Self::Stdout { .. }
+            | Self::Styled {
+                stream: OutputStream::Stdout,
+                ..
+            }
+            | Self::JobOutput {
+                stream: OutputStream::Stdout,
+                ..
+            } => "line-stdout",
+            Self::Stderr { .. }
+            | Self::Styled {
+                stream: OutputStream::Stderr,
+                ..
+            }
+            | Self::JobOutput {
+                stream: OutputStream::Stderr,
+                ..
+            } => "line-stderr",
             Self::Notification { .. } => "line-notification",
         }
     }
 
-    /// Generate a session-unique key for this output line.
-    /// Combines timestamp with text content to reduce collisions when
-    /// multiple lines share the same timestamp (millisecond resolution).
+    /// Generate a key for the content of this output line, which changes when a partial
+    /// line is rewritten. Identical lines share it; `HistoryLine::key` tells them apart.
     /// Note: uses `DefaultHasher`, which is not stable across runs/versions.
     pub fn unique_key(&self) -> String {
         use std::collections::hash_map::DefaultHasher;
@@ -67,6 +112,14 @@ impl OutputLine {
             Self::Command { text, .. } => ("cmd", text),
             Self::Stdout { text, .. } => ("out", text),
             Self::Stderr { text, .. } => ("err", text),
+            Self::Styled { stream, text, .. } => match stream {
+                OutputStream::Stdout => ("sout", text),
+                OutputStream::Stderr => ("serr", text),
+            },
+            Self::JobOutput { stream, text, .. } => match stream {
+                OutputStream::Stdout => ("jout", text),
+                OutputStream::Stderr => ("jerr", text),
+            },
             Self::Notification { message, level, .. } => (
                 match level {
                     NotificationLevel::Info => "not_info",
@@ -81,12 +134,35 @@ impl OutputLine {
         prefix.hash(&mut hasher);
         timestamp.hash(&mut hasher);
         payload.hash(&mut hasher);
+        self.job_id().hash(&mut hasher);
         let digest = hasher.finish();
 
         format!("{prefix}_{timestamp:016x}_{digest:016x}")
     }
 }
 
+/// A line of the terminal history with its sequence number.
+/// Mirrors the backend `HistoryLine` type for IPC serialization.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HistoryLine {
+    /// Number of lines added to the session before this one; `None` for lines the
+    /// frontend adds itself (errors talking to the backend)
+    pub seq: Option<u64>,
+    pub line: OutputLine,
+}
+
+impl HistoryLine {
+    /// Key identifying this version of the line: its sequence number (its `position` in
+    /// the view for lines without one) and its content
+    pub fn key(&self, position: u64) -> String {
+        let content = self.line.unique_key();
+        self.seq.map_or_else(
+            || format!("local{position}:{content}"),
+            |seq| format!("{seq}:{content}"),
+        )
+    }
+}
+
 /// Notification severity level
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum NotificationLevel {
@@ -94,3 +170,104 @@ pub enum NotificationLevel {
     Warning,
     Error,
 }
+
+/// Stream a line of command output was written to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Terminal color from an SGR sequence.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Color {
+    /// Palette index: 0-7 standard, 8-15 bright, 16-255 the xterm 256-color cube and greys
+    Indexed(u8),
+    /// 24-bit color
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// CSS color value; the 16 base colors come from the `--ansi-N` theme variables
+    pub fn css(self) -> String {
+        match self {
+            Self::Indexed(index @ 0..=15) => format!("var(--ansi-{index})"),
+            Self::Indexed(index @ 16..=231) => {
+                let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+                let cube = index - 16;
+                format!(
+                    "rgb({}, {}, {})",
+                    level(cube / 36),
+                    level((cube / 6) % 6),
+                    level(cube % 6)
+                )
+            }
+            Self::Indexed(index) => {
+                let grey = 8 + (index - 232) * 10;
+                format!("rgb({grey}, {grey}, {grey})")
+            }
+            Self::Rgb(r, g, b) => format!("rgb({r}, {g}, {b})"),
+        }
+    }
+}
+
+/// Text attributes set by SGR sequences.
+// Attributes are independent on/off flags, as in the SGR protocol itself
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TextStyle {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+impl TextStyle {
+    /// Inline CSS for this style. `inverse` swaps the foreground and background,
+    /// falling back to the theme colors for whichever is unset.
+    pub fn css(&self) -> String {
+        let fg = self.fg.map(Color::css);
+        let bg = self.bg.map(Color::css);
+        let (fg, bg) = if self.inverse {
+            (
+                Some(bg.unwrap_or_else(|| "var(--bg-primary)".to_string())),
+                Some(fg.unwrap_or_else(|| "var(--text-stdout)".to_string())),
+            )
+        } else {
+            (fg, bg)
+        };
+
+        let mut css = String::new();
+        if let Some(fg) = fg {
+            let _ = write!(css, "color: {fg};");
+        }
+        if let Some(bg) = bg {
+            let _ = write!(css, "background-color: {bg};");
+        }
+        if self.bold {
+            css.push_str("font-weight: bold;");
+        }
+        if self.dim {
+            css.push_str("opacity: 0.6;");
+        }
+        if self.italic {
+            css.push_str("font-style: italic;");
+        }
+        if self.underline {
+            css.push_str("text-decoration: underline;");
+        }
+        css
+    }
+}
+
+/// A styled byte range `start..end` of an output line's text.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StyledSpan {
+    pub start: usize,
+    pub end: usize,
+    pub style: TextStyle,
+}
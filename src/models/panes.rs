@@ -0,0 +1,219 @@
+use leptos::prelude::*;
+
+use crate::models::TerminalState;
+
+/// Smallest share of a split either side can be dragged down to
+pub const MIN_SPLIT_RATIO: f64 = 0.1;
+
+/// How a split arranges its two children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Side by side (divider is vertical)
+    Horizontal,
+    /// Stacked top to bottom (divider is horizontal)
+    Vertical,
+}
+
+impl SplitDirection {
+    /// CSS class for the split container
+    pub const fn css_class(self) -> &'static str {
+        match self {
+            Self::Horizontal => "pane-split horizontal",
+            Self::Vertical => "pane-split vertical",
+        }
+    }
+}
+
+/// Direction to move pane focus in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl FocusDirection {
+    /// Map an arrow key name to a direction
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "ArrowLeft" => Some(Self::Left),
+            "ArrowRight" => Some(Self::Right),
+            "ArrowUp" => Some(Self::Up),
+            "ArrowDown" => Some(Self::Down),
+            _ => None,
+        }
+    }
+}
+
+/// A terminal pane attached to one backend session.
+#[derive(Clone)]
+pub struct Pane {
+    /// Backend session shown in this pane
+    pub session_id: String,
+    /// Independent terminal state (history, cwd, busy flag, input)
+    pub state: TerminalState,
+}
+
+/// Position of a pane within its tab, in fractions of the tab's area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaneRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl PaneRect {
+    const FULL: Self = Self {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+}
+
+/// Layout tree of a tab: leaves are sessions, inner nodes split their area in two.
+#[derive(Clone)]
+pub enum PaneNode {
+    /// A pane showing the given session
+    Leaf(String),
+    /// Two subtrees sharing the area; `ratio` is the first child's share
+    Split {
+        direction: SplitDirection,
+        ratio: RwSignal<f64>,
+        first: Box<Self>,
+        second: Box<Self>,
+    },
+}
+
+impl PaneNode {
+    /// Replace the leaf `target` with an even split of `target` and `new_session`.
+    /// Returns false if `target` is not in the tree.
+    pub fn split_leaf(
+        &mut self,
+        target: &str,
+        direction: SplitDirection,
+        new_session: String,
+    ) -> bool {
+        match self {
+            Self::Leaf(id) if id == target => {
+                let existing = Self::Leaf(std::mem::take(id));
+                *self = Self::Split {
+                    direction,
+                    ratio: RwSignal::new(0.5),
+                    first: Box::new(existing),
+                    second: Box::new(Self::Leaf(new_session)),
+                };
+                true
+            }
+            Self::Leaf(_) => false,
+            Self::Split { first, second, .. } => {
+                first.split_leaf(target, direction, new_session.clone())
+                    || second.split_leaf(target, direction, new_session)
+            }
+        }
+    }
+
+    /// The tree with the leaf `target` removed (its sibling takes the freed space).
+    /// Returns `None` if `target` was the only leaf.
+    pub fn without_leaf(self, target: &str) -> Option<Self> {
+        match self {
+            Self::Leaf(id) if id == target => None,
+            Self::Leaf(_) => Some(self),
+            Self::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => match (first.without_leaf(target), second.without_leaf(target)) {
+                (Some(first), Some(second)) => Some(Self::Split {
+                    direction,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(remaining), None) | (None, Some(remaining)) => Some(remaining),
+                (None, None) => None,
+            },
+        }
+    }
+
+    /// Session IDs of all leaves, in layout order
+    pub fn leaves(&self) -> Vec<String> {
+        let mut leaves = Vec::new();
+        self.collect_rects(PaneRect::FULL, &mut |id, _| leaves.push(id.to_string()));
+        leaves
+    }
+
+    /// The closest pane in `direction` from the leaf `from`, if any.
+    pub fn neighbor(&self, from: &str, direction: FocusDirection) -> Option<String> {
+        let mut rects = Vec::new();
+        self.collect_rects(PaneRect::FULL, &mut |id, rect| {
+            rects.push((id.to_string(), rect));
+        });
+        let origin = rects.iter().find(|(id, _)| id == from)?.1;
+        let (origin_x, origin_y) = origin.center();
+
+        rects
+            .into_iter()
+            .filter(|(id, _)| id != from)
+            .filter_map(|(id, rect)| {
+                let (x, y) = rect.center();
+                // Distance along the movement axis, and offset across it
+                let (distance, offset) = match direction {
+                    FocusDirection::Left => (origin.x - (rect.x + rect.width), y - origin_y),
+                    FocusDirection::Right => (rect.x - (origin.x + origin.width), y - origin_y),
+                    FocusDirection::Up => (origin.y - (rect.y + rect.height), x - origin_x),
+                    FocusDirection::Down => (rect.y - (origin.y + origin.height), x - origin_x),
+                };
+                (distance > -f64::EPSILON).then_some((id, distance, offset.abs()))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.2.total_cmp(&b.2)))
+            .map(|(id, _, _)| id)
+    }
+
+    fn collect_rects(&self, area: PaneRect, visit: &mut impl FnMut(&str, PaneRect)) {
+        match self {
+            Self::Leaf(id) => visit(id, area),
+            Self::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                let ratio = ratio.get_untracked();
+                let (first_area, second_area) = match direction {
+                    SplitDirection::Horizontal => {
+                        let width = area.width * ratio;
+                        (
+                            PaneRect { width, ..area },
+                            PaneRect {
+                                x: area.x + width,
+                                width: area.width - width,
+                                ..area
+                            },
+                        )
+                    }
+                    SplitDirection::Vertical => {
+                        let height = area.height * ratio;
+                        (
+                            PaneRect { height, ..area },
+                            PaneRect {
+                                y: area.y + height,
+                                height: area.height - height,
+                                ..area
+                            },
+                        )
+                    }
+                };
+                first.collect_rects(first_area, visit);
+                second.collect_rects(second_area, visit);
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::StyledSpan;
+
+/// One row of the emulated screen.
+/// Mirrors the backend `ScreenLine` type for IPC serialization.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScreenLine {
+    pub row: u16,
+    pub text: String,
+    pub spans: Vec<StyledSpan>,
+}
+
+/// Changed rows and cursor state of the emulated screen.
+/// Mirrors the backend `ScreenUpdate` type for IPC serialization.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreenUpdate {
+    pub rows: u16,
+    pub cols: u16,
+    pub full: bool,
+    pub lines: Vec<ScreenLine>,
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+    pub cursor_visible: bool,
+    pub alternate: bool,
+    pub application_cursor: bool,
+}
+
+/// Payload of the `screen-update` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionScreen {
+    pub session_id: String,
+    pub update: ScreenUpdate,
+}
+
+/// Screen of a full-screen program (editor, pager, `top`) running in a pane,
+/// assembled from `screen-update` events.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Screen {
+    /// One entry per row, top to bottom
+    pub lines: Vec<ScreenLine>,
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+    pub cursor_visible: bool,
+    /// Is the program using the alternate screen (and the grid shown)?
+    pub alternate: bool,
+    /// Should arrow keys send application sequences?
+    pub application_cursor: bool,
+}
+
+impl Screen {
+    /// Apply changed rows and the new cursor state
+    pub fn apply(&mut self, update: ScreenUpdate) {
+        let rows = usize::from(update.rows);
+        if update.full || self.lines.len() != rows {
+            self.lines = (0..update.rows)
+                .map(|row| ScreenLine {
+                    row,
+                    ..ScreenLine::default()
+                })
+                .collect();
+        }
+        for line in update.lines {
+            if let Some(slot) = self.lines.get_mut(usize::from(line.row)) {
+                *slot = line;
+            }
+        }
+
+        self.cursor_row = update.cursor_row;
+        self.cursor_col = update.cursor_col;
+        self.cursor_visible = update.cursor_visible;
+        self.alternate = update.alternate;
+        self.application_cursor = update.application_cursor;
+    }
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::HistoryLine;
+
+/// Summary of a backend terminal session.
+/// Mirrors the backend `SessionInfo` type for IPC serialization.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub id: String,
+    pub cwd: String,
+    pub is_busy: bool,
+}
+
+/// Payload of the `output-batch` event: a session's output gathered over a short window.
+/// Each line replaces the line with the same sequence number (a partial line being
+/// rewritten), if there is one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputBatch {
+    pub session_id: String,
+    pub lines: Vec<HistoryLine>,
+}
+
+/// Payload of the `cwd-changed` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CwdChanged {
+    pub session_id: String,
+    pub cwd: String,
+}
+
+/// Payload of the `command-started` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandStarted {
+    pub session_id: String,
+    pub command_id: u64,
+    pub command: String,
+    pub cwd: String,
+}
+
+/// Payload of the `command-finished` event (the fields the UI uses).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandFinished {
+    pub session_id: String,
+    pub command_id: u64,
+    /// Working directory after the command
+    pub cwd: String,
+}
+
+/// Arguments for session-scoped IPC commands that take no other parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionArgs {
+    pub session_id: String,
+}
@@ -0,0 +1,316 @@
+use leptos::prelude::*;
+
+use crate::models::{
+    CommandFinished, CommandStarted, Config, ConfigStatus, FocusDirection, OutputBatch, Pane,
+    PaneNode, Screen, SessionInfo, SessionScreen, SplitDirection, TerminalState,
+};
+
+/// One terminal tab: a layout of panes, each attached to its own backend session.
+#[derive(Clone, Copy)]
+pub struct Tab {
+    /// Unique tab ID (the tab's key)
+    pub id: u64,
+    /// Panes shown in this tab
+    pub panes: RwSignal<Vec<Pane>>,
+    /// How the panes are arranged
+    pub layout: RwSignal<PaneNode>,
+    /// Session ID of the focused pane
+    pub focused: RwSignal<String>,
+    /// Title set by the user; the focused pane's cwd is shown otherwise
+    pub custom_title: RwSignal<Option<String>>,
+    /// New output arrived while the tab was in the background
+    pub has_activity: RwSignal<bool>,
+}
+
+impl Tab {
+    /// Title shown in the tab bar
+    pub fn title(&self) -> String {
+        if let Some(title) = self.custom_title.get() {
+            return title;
+        }
+        let cwd = self
+            .focused_pane()
+            .map(|pane| pane.state.cwd.get())
+            .unwrap_or_default();
+        let trimmed = cwd.trim_end_matches(['/', '\\']);
+        match trimmed.rsplit(['/', '\\']).next() {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ if !cwd.is_empty() => cwd,
+            _ => "shell".to_string(),
+        }
+    }
+
+    /// Find the pane showing `session_id`
+    pub fn pane(&self, session_id: &str) -> Option<Pane> {
+        self.panes
+            .get()
+            .into_iter()
+            .find(|pane| pane.session_id == session_id)
+    }
+
+    /// The focused pane
+    pub fn focused_pane(&self) -> Option<Pane> {
+        self.pane(&self.focused.get())
+    }
+
+    /// Check whether any pane in the tab is running a command
+    pub fn is_busy(&self) -> bool {
+        self.panes.get().iter().any(|pane| pane.state.is_busy.get())
+    }
+}
+
+/// Open tabs and which one is active.
+/// Shared via `use_context()`; each pane's `TerminalState` is provided to its own subtree.
+#[derive(Clone, Copy)]
+pub struct TabsState {
+    /// Tabs in display order
+    pub tabs: RwSignal<Vec<Tab>>,
+    /// ID of the active tab
+    pub active: RwSignal<Option<u64>>,
+    /// Next tab ID to hand out
+    next_id: StoredValue<u64>,
+    /// True if event listener registration failed (shared by every pane)
+    pub listener_failed: RwSignal<bool>,
+    /// Error message when listener failed (shared by every pane)
+    pub listener_error: RwSignal<Option<String>>,
+    /// User settings (shared by every pane)
+    pub config: RwSignal<Config>,
+}
+
+impl TabsState {
+    /// Create an empty tab set
+    pub fn new() -> Self {
+        Self {
+            tabs: RwSignal::new(Vec::new()),
+            active: RwSignal::new(None),
+            next_id: StoredValue::new(1),
+            listener_failed: RwSignal::new(false),
+            listener_error: RwSignal::new(None),
+            config: RwSignal::new(Config::default()),
+        }
+    }
+
+    /// Create the pane state for a backend session
+    fn new_pane(&self, session: SessionInfo) -> Pane {
+        let mut state = TerminalState::new();
+        state.listener_failed = self.listener_failed;
+        state.listener_error = self.listener_error;
+        state.config = self.config;
+        state.session_id.set(Some(session.id.clone()));
+        state.cwd.set(session.cwd);
+        state.is_busy.set(session.is_busy);
+
+        Pane {
+            session_id: session.id,
+            state,
+        }
+    }
+
+    /// Add a tab with a single pane for a backend session and return its ID.
+    /// If the session is already shown, the ID of the tab showing it is returned instead.
+    pub fn open(&self, session: SessionInfo) -> u64 {
+        if let Some((tab, _)) = self.find_pane(&session.id) {
+            return tab.id;
+        }
+
+        let id = self.next_id.get_value();
+        self.next_id.set_value(id + 1);
+
+        let session_id = session.id.clone();
+        let tab = Tab {
+            id,
+            panes: RwSignal::new(vec![self.new_pane(session)]),
+            layout: RwSignal::new(PaneNode::Leaf(session_id.clone())),
+            focused: RwSignal::new(session_id),
+            custom_title: RwSignal::new(None),
+            has_activity: RwSignal::new(false),
+        };
+        self.tabs.update(|tabs| tabs.push(tab));
+        id
+    }
+
+    /// Find a tab by ID
+    pub fn find(&self, tab_id: u64) -> Option<Tab> {
+        self.tabs.get().into_iter().find(|tab| tab.id == tab_id)
+    }
+
+    /// Find the pane showing `session_id` and the tab it is in
+    pub fn find_pane(&self, session_id: &str) -> Option<(Tab, Pane)> {
+        self.tabs
+            .get()
+            .into_iter()
+            .find_map(|tab| tab.pane(session_id).map(|pane| (tab, pane)))
+    }
+
+    /// Every pane of every tab
+    pub fn panes(&self) -> Vec<Pane> {
+        self.tabs
+            .get_untracked()
+            .into_iter()
+            .flat_map(|tab| tab.panes.get_untracked())
+            .collect()
+    }
+
+    /// The active tab (if any)
+    pub fn active_tab(&self) -> Option<Tab> {
+        self.active.get().and_then(|id| self.find(id))
+    }
+
+    /// The focused pane of the active tab (if any)
+    pub fn focused_pane(&self) -> Option<Pane> {
+        self.active_tab().and_then(|tab| tab.focused_pane())
+    }
+
+    /// Check whether `tab_id` is the active tab
+    pub fn is_active(&self, tab_id: u64) -> bool {
+        self.active.get() == Some(tab_id)
+    }
+
+    /// Switch to a tab, clearing its activity indicator
+    pub fn activate(&self, tab_id: u64) {
+        if let Some(tab) = self.find(tab_id) {
+            tab.has_activity.set(false);
+            self.active.set(Some(tab_id));
+        }
+    }
+
+    /// Remove a tab; if it was active, its right neighbour (or the last tab) becomes active
+    pub fn remove(&self, tab_id: u64) {
+        let mut next_active = None;
+        self.tabs.update(|tabs| {
+            if let Some(index) = tabs.iter().position(|tab| tab.id == tab_id) {
+                tabs.remove(index);
+                next_active = tabs.get(index).or_else(|| tabs.last()).map(|tab| tab.id);
+            }
+        });
+
+        if self.is_active(tab_id) {
+            match next_active {
+                Some(id) => self.activate(id),
+                None => self.active.set(None),
+            }
+        }
+    }
+
+    /// Split the active tab's focused pane, showing `session` in the new pane and focusing it
+    pub fn split_focused(&self, direction: SplitDirection, session: SessionInfo) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let target = tab.focused.get();
+        let session_id = session.id.clone();
+
+        let mut layout = tab.layout.get();
+        if !layout.split_leaf(&target, direction, session_id.clone()) {
+            return;
+        }
+        let pane = self.new_pane(session);
+        tab.panes.update(|panes| panes.push(pane));
+        tab.layout.set(layout);
+        tab.focused.set(session_id);
+    }
+
+    /// Remove the pane showing `session_id`; a tab left without panes is removed too
+    pub fn remove_pane(&self, session_id: &str) {
+        let Some((tab, _)) = self.find_pane(session_id) else {
+            return;
+        };
+
+        let Some(layout) = tab.layout.get().without_leaf(session_id) else {
+            self.remove(tab.id);
+            return;
+        };
+        if tab.focused.get() == session_id {
+            if let Some(first) = layout.leaves().into_iter().next() {
+                tab.focused.set(first);
+            }
+        }
+        tab.layout.set(layout);
+        tab.panes
+            .update(|panes| panes.retain(|pane| pane.session_id != session_id));
+    }
+
+    /// Move focus in the active tab to the nearest pane in `direction`
+    pub fn move_focus(&self, direction: FocusDirection) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let neighbor = tab
+            .layout
+            .with(|layout| layout.neighbor(&tab.focused.get(), direction));
+        if let Some(session_id) = neighbor {
+            tab.focused.set(session_id);
+        }
+    }
+
+    /// Route a batch of output to its pane, flagging background tabs as active
+    pub fn push_output(&self, batch: OutputBatch) {
+        let Some((tab, pane)) = self.find_pane(&batch.session_id) else {
+            return;
+        };
+        pane.state.apply_output(batch.lines);
+        if !self.is_active(tab.id) {
+            tab.has_activity.set(true);
+        }
+    }
+
+    /// Route a screen update of a full-screen program to its pane
+    pub fn push_screen(&self, event: SessionScreen) {
+        let Some((tab, pane)) = self.find_pane(&event.session_id) else {
+            return;
+        };
+        pane.state
+            .screen
+            .update(|screen| screen.apply(event.update));
+        if !self.is_active(tab.id) {
+            tab.has_activity.set(true);
+        }
+    }
+
+    /// Mark the pane of a session whose command started as busy, wherever the command
+    /// was submitted from
+    pub fn command_started(&self, event: &CommandStarted) {
+        if let Some((_, pane)) = self.find_pane(&event.session_id) {
+            // A screen left over from an earlier full-screen program is stale
+            pane.state.screen.set(Screen::default());
+            pane.state.is_busy.set(true);
+        }
+    }
+
+    /// Mark the pane of a session whose command finished as idle
+    pub fn command_finished(&self, event: CommandFinished) {
+        if let Some((_, pane)) = self.find_pane(&event.session_id) {
+            pane.state.is_busy.set(false);
+            pane.state.cwd.set(event.cwd);
+        }
+    }
+
+    /// Show a notification in the focused pane of the active tab
+    pub fn show_notification(&self, message: impl Into<String>) {
+        if let Some(pane) = self.focused_pane() {
+            pane.state.show_notification(message);
+        }
+    }
+
+    /// Apply settings loaded from the config file, reporting a file that could not be
+    /// applied
+    pub fn apply_config(&self, status: ConfigStatus) {
+        self.config.set(status.config);
+        if let Some(error) = status.error {
+            let path = status.path.unwrap_or_else(|| "config file".to_string());
+            self.show_notification(format!("Invalid {path}: {error}"));
+        }
+    }
+
+    /// Mark listener as failed with an error message (affects every pane)
+    pub fn set_listener_failed(&self, error: impl Into<String>) {
+        self.listener_failed.set(true);
+        self.listener_error.set(Some(error.into()));
+    }
+}
+
+impl Default for TabsState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
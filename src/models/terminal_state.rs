@@ -1,15 +1,19 @@
 use leptos::prelude::*;
 
-use crate::models::OutputLine;
+use crate::models::{
+    CommandHistoryEntry, CommandRecall, Config, ExecutionMode, HistoryLine, OutputLine, Screen,
+};
 
 /// Frontend-only reactive state (Leptos signals).
 /// Shared state accessible to all components via `use_context()`.
 #[derive(Clone, Copy)]
 pub struct TerminalState {
+    /// Backend session this terminal is attached to (set once the session is opened)
+    pub session_id: RwSignal<Option<String>>,
     /// Current command being typed
     pub current_input: RwSignal<String>,
-    /// Terminal history (synced from backend)
-    pub history: RwSignal<Vec<OutputLine>>,
+    /// Terminal history (synced from backend), in sequence order
+    pub history: RwSignal<Vec<HistoryLine>>,
     /// Current working directory
     pub cwd: RwSignal<String>,
     /// True if a home directory exists (presence only, no PII)
@@ -22,14 +26,25 @@ pub struct TerminalState {
     pub listener_failed: RwSignal<bool>,
     /// Error message when listener failed
     pub listener_error: RwSignal<Option<String>>,
+    /// How commands are executed (PTY by default so programs see a terminal)
+    pub execution_mode: RwSignal<ExecutionMode>,
+    /// Screen of the running full-screen program (PTY mode)
+    pub screen: RwSignal<Screen>,
+    /// Is the reverse history search (Ctrl+R) open?
+    pub history_search_open: RwSignal<bool>,
+    /// User settings (shared by every pane)
+    pub config: RwSignal<Config>,
+    /// Previously entered commands recalled with Up/Down
+    command_recall: StoredValue<CommandRecall>,
+    /// Number of lines ever added to `history` (positions survive trimming)
+    history_total: StoredValue<u64>,
 }
 
 impl TerminalState {
-    pub const HISTORY_CAPACITY: usize = 10_000;
-
     /// Create a new terminal state with default values
     pub fn new() -> Self {
         Self {
+            session_id: RwSignal::new(None),
             current_input: RwSignal::new(String::new()),
             history: RwSignal::new(Vec::new()),
             cwd: RwSignal::new(String::new()),
@@ -38,6 +53,12 @@ impl TerminalState {
             notification: RwSignal::new(None),
             listener_failed: RwSignal::new(false),
             listener_error: RwSignal::new(None),
+            execution_mode: RwSignal::new(ExecutionMode::default()),
+            screen: RwSignal::new(Screen::default()),
+            history_search_open: RwSignal::new(false),
+            config: RwSignal::new(Config::default()),
+            command_recall: StoredValue::new(CommandRecall::default()),
+            history_total: StoredValue::new(0),
         }
     }
 
@@ -46,25 +67,124 @@ impl TerminalState {
         self.current_input.set(String::new());
     }
 
-    /// Add a line to the history
+    /// Replace the commands recalled with Up/Down (oldest first)
+    pub fn set_command_history(&self, entries: Vec<CommandHistoryEntry>) {
+        let commands = entries.into_iter().map(|entry| entry.command).collect();
+        self.command_recall
+            .update_value(|recall| recall.set_commands(commands));
+    }
+
+    /// Make a command just entered the most recent one to recall
+    pub fn remember_command(&self, command: String) {
+        self.command_recall
+            .update_value(|recall| recall.push(command));
+    }
+
+    /// Replace the input with the previous command (Up)
+    pub fn recall_older(&self) {
+        let input = self.current_input.get_untracked();
+        let recalled = self
+            .command_recall
+            .try_update_value(|recall| recall.older(&input))
+            .flatten();
+        if let Some(command) = recalled {
+            self.current_input.set(command);
+        }
+    }
+
+    /// Replace the input with the next command, or the draft after the last one (Down)
+    pub fn recall_newer(&self) {
+        let recalled = self
+            .command_recall
+            .try_update_value(CommandRecall::newer)
+            .flatten();
+        if let Some(command) = recalled {
+            self.current_input.set(command);
+        }
+    }
+
+    /// Number of output lines kept (`scrollback.display_lines`)
+    fn history_capacity(&self) -> usize {
+        self.config
+            .with_untracked(|config| config.scrollback.display_lines)
+    }
+
+    /// Position of the first line of `history` among all lines ever added, which stays
+    /// the same as older lines are trimmed
+    pub fn first_position(&self) -> u64 {
+        self.history_total.get_value() - self.history.with_untracked(Vec::len) as u64
+    }
+
+    /// Sequence number of the newest line received from the backend
+    pub fn last_seq(&self) -> Option<u64> {
+        self.history
+            .with_untracked(|h| h.iter().rev().find_map(|line| line.seq))
+    }
+
+    /// Add a line of the frontend's own to the history
     pub fn push_history(&self, line: OutputLine) {
+        let capacity = self.history_capacity();
+        self.history_total.update_value(|total| *total += 1);
         self.history.update(|h| {
-            h.push(line);
-            let excess = h.len().saturating_sub(Self::HISTORY_CAPACITY);
-            if excess > 0 {
-                h.drain(0..excess);
-            }
+            h.push(HistoryLine { seq: None, line });
+            trim(h, capacity);
         });
     }
 
-    /// Set the history (replacing existing)
-    pub fn set_history(&self, lines: Vec<OutputLine>) {
-        let mut history = lines;
-        let excess = history.len().saturating_sub(Self::HISTORY_CAPACITY);
-        if excess > 0 {
-            history.drain(0..excess);
-        }
-        self.history.set(history);
+    /// Merge lines fetched from the backend history (consecutive, in sequence order) into
+    /// the history, in place of the versions already shown, so fetching lines again
+    /// neither duplicates nor reorders them
+    pub fn sync_history(&self, lines: Vec<HistoryLine>) {
+        let (Some(first), Some(last)) = (
+            lines.first().and_then(|line| line.seq),
+            lines.last().and_then(|line| line.seq),
+        ) else {
+            return;
+        };
+        let capacity = self.history_capacity();
+        let added = lines.len();
+        let mut removed = 0;
+        self.history.update(|h| {
+            let start = h
+                .iter()
+                .position(|line| line.seq.is_some_and(|seq| seq >= first))
+                .unwrap_or(h.len());
+            let end = h
+                .iter()
+                .rposition(|line| line.seq.is_some_and(|seq| seq <= last))
+                .map_or(start, |index| (index + 1).max(start));
+            removed = end - start;
+            h.splice(start..end, lines);
+            trim(h, capacity);
+        });
+        self.history_total
+            .update_value(|total| *total = *total + added as u64 - removed as u64);
+    }
+
+    /// Apply the lines of an `output-batch` event in a single history update: a line
+    /// with the sequence number of one already shown (a partial line being rewritten)
+    /// replaces it, newer lines are added
+    pub fn apply_output(&self, lines: Vec<HistoryLine>) {
+        let capacity = self.history_capacity();
+        let mut last_seq = self.last_seq();
+        let mut added = 0;
+        self.history.update(|h| {
+            for update in lines {
+                let Some(seq) = update.seq else {
+                    continue;
+                };
+                if last_seq.is_none_or(|last| seq > last) {
+                    last_seq = Some(seq);
+                    h.push(update);
+                    added += 1;
+                } else if let Some(slot) = h.iter_mut().rev().find(|line| line.seq == Some(seq)) {
+                    *slot = update;
+                }
+                // Otherwise the line was trimmed since it was added
+            }
+            trim(h, capacity);
+        });
+        self.history_total.update_value(|total| *total += added);
     }
 
     /// Show a notification (auto-dismiss should be handled by component)
@@ -77,15 +197,15 @@ impl TerminalState {
         self.notification.set(None);
     }
 
-    /// Mark listener as failed with an error message
-    pub fn set_listener_failed(&self, error: impl Into<String>) {
-        self.listener_failed.set(true);
-        self.listener_error.set(Some(error.into()));
+    /// Check if a full-screen program is running (its screen replaces the output)
+    pub fn is_screen_active(&self) -> bool {
+        self.is_busy.get() && self.screen.with(|screen| screen.alternate)
     }
 
-    /// Check if input should be disabled (busy or listener failed)
+    /// Check if input should be disabled (listener failed or no session yet).
+    /// Input stays enabled while busy so it can be sent to the running command.
     pub fn is_input_disabled(&self) -> bool {
-        self.is_busy.get() || self.listener_failed.get()
+        self.listener_failed.get() || self.session_id.get().is_none()
     }
 }
 
@@ -94,3 +214,11 @@ impl Default for TerminalState {
         Self::new()
     }
 }
+
+/// Drop the oldest lines beyond `capacity`
+fn trim(history: &mut Vec<HistoryLine>, capacity: usize) {
+    let excess = history.len().saturating_sub(capacity);
+    if excess > 0 {
+        history.drain(0..excess);
+    }
+}